@@ -1,5 +1,7 @@
 pub const DEFAULT_CHUNK_ROWS: usize = 16_384;
+pub const DEFAULT_BLOCK_ROWS: usize = 1024;
 
 pub struct Config {
     pub chunk_rows: usize,
+    pub block_rows: usize,
 }
@@ -1,24 +1,379 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use common::error::{Error, Result};
+
+use crate::bitmap::Bitmap;
+use crate::schema::{DataType, Schema, SERIES_ID_COLUMN, TS_COLUMN, VALUE_COLUMN};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    I64(Vec<i64>),
+    U32(Vec<u32>),
+    F64(Vec<f64>),
+    Utf8(Vec<String>),
+}
+
+impl Column {
+    pub fn new_empty(dtype: DataType) -> Self {
+        match dtype {
+            DataType::I64 => Column::I64(Vec::new()),
+            DataType::U32 => Column::U32(Vec::new()),
+            DataType::F64 => Column::F64(Vec::new()),
+            DataType::Utf8 => Column::Utf8(Vec::new()),
+        }
+    }
+
+    pub fn dtype(&self) -> DataType {
+        match self {
+            Column::I64(_) => DataType::I64,
+            Column::U32(_) => DataType::U32,
+            Column::F64(_) => DataType::F64,
+            Column::Utf8(_) => DataType::Utf8,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::I64(values) => values.len(),
+            Column::U32(values) => values.len(),
+            Column::F64(values) => values.len(),
+            Column::Utf8(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_i64(&self) -> Option<&[i64]> {
+        match self {
+            Column::I64(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<&[u32]> {
+        match self {
+            Column::U32(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<&[f64]> {
+        match self {
+            Column::F64(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_utf8(&self) -> Option<&[String]> {
+        match self {
+            Column::Utf8(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> Column {
+        match self {
+            Column::I64(values) => Column::I64(values[range].to_vec()),
+            Column::U32(values) => Column::U32(values[range].to_vec()),
+            Column::F64(values) => Column::F64(values[range].to_vec()),
+            Column::Utf8(values) => Column::Utf8(values[range].to_vec()),
+        }
+    }
+
+    /// Keeps the values whose mask entry is true.
+    pub fn filter(&self, mask: &[bool]) -> Column {
+        fn keep<T: Clone>(values: &[T], mask: &[bool]) -> Vec<T> {
+            values
+                .iter()
+                .zip(mask)
+                .filter(|(_, keep)| **keep)
+                .map(|(value, _)| value.clone())
+                .collect()
+        }
+        match self {
+            Column::I64(values) => Column::I64(keep(values, mask)),
+            Column::U32(values) => Column::U32(keep(values, mask)),
+            Column::F64(values) => Column::F64(keep(values, mask)),
+            Column::Utf8(values) => Column::Utf8(keep(values, mask)),
+        }
+    }
+
+    /// Gathers the values at `rows`, in order.
+    pub fn take(&self, rows: &[u32]) -> Column {
+        fn gather<T: Clone>(values: &[T], rows: &[u32]) -> Vec<T> {
+            rows.iter()
+                .map(|&row| values[row as usize].clone())
+                .collect()
+        }
+        match self {
+            Column::I64(values) => Column::I64(gather(values, rows)),
+            Column::U32(values) => Column::U32(gather(values, rows)),
+            Column::F64(values) => Column::F64(gather(values, rows)),
+            Column::Utf8(values) => Column::Utf8(gather(values, rows)),
+        }
+    }
+
+    /// Appends `other[range]`; both columns must have the same type.
+    pub fn extend_from(&mut self, other: &Column, range: Range<usize>) -> Result<()> {
+        match (self, other) {
+            (Column::I64(dst), Column::I64(src)) => dst.extend_from_slice(&src[range]),
+            (Column::U32(dst), Column::U32(src)) => dst.extend_from_slice(&src[range]),
+            (Column::F64(dst), Column::F64(src)) => dst.extend_from_slice(&src[range]),
+            (Column::Utf8(dst), Column::Utf8(src)) => dst.extend_from_slice(&src[range]),
+            _ => return Err(Error::Corrupt("column type mismatch".into())),
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Column::I64(values) => values.clear(),
+            Column::U32(values) => values.clear(),
+            Column::F64(values) => values.clear(),
+            Column::Utf8(values) => values.clear(),
+        }
+    }
+}
+
+/// Equal-length columns described by a schema. A batch without columns has
+/// no rows.
+///
+/// Each column may carry a validity bitmap; values in null slots are
+/// unspecified and must be ignored.
 #[derive(Debug, Clone)]
 pub struct RecordBatch {
-    pub ts: Vec<i64>,
-    pub series_id: Vec<u32>,
-    pub value: Vec<f64>,
+    schema: Arc<Schema>,
+    columns: Vec<Column>,
+    /// `None` when every row of the column is valid.
+    validity: Vec<Option<Bitmap>>,
 }
 
 impl RecordBatch {
+    pub fn try_new(schema: Arc<Schema>, columns: Vec<Column>) -> Result<Self> {
+        let validity = vec![None; columns.len()];
+        Self::try_new_with_validity(schema, columns, validity)
+    }
+
+    pub fn try_new_with_validity(
+        schema: Arc<Schema>,
+        columns: Vec<Column>,
+        validity: Vec<Option<Bitmap>>,
+    ) -> Result<Self> {
+        if columns.len() != schema.len() {
+            return Err(Error::Corrupt(format!(
+                "expected {} columns, got {}",
+                schema.len(),
+                columns.len()
+            )));
+        }
+        for (field, column) in schema.fields().iter().zip(&columns) {
+            if column.dtype() != field.dtype {
+                return Err(Error::Corrupt(format!(
+                    "column {} has type {:?}, expected {:?}",
+                    field.name,
+                    column.dtype(),
+                    field.dtype
+                )));
+            }
+            if column.len() != columns[0].len() {
+                return Err(Error::Corrupt("column length mismatch".into()));
+            }
+        }
+        if validity.len() != columns.len() {
+            return Err(Error::Corrupt("validity count mismatch".into()));
+        }
+        let validity = validity
+            .into_iter()
+            .zip(&columns)
+            .map(|(bitmap, column)| match bitmap {
+                Some(bitmap) if bitmap.len() != column.len() => {
+                    Err(Error::Corrupt("validity length mismatch".into()))
+                }
+                Some(bitmap) if bitmap.null_count() == 0 => Ok(None),
+                bitmap => Ok(bitmap),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            schema,
+            columns,
+            validity,
+        })
+    }
+
+    pub fn new_empty(schema: Arc<Schema>) -> Self {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| Column::new_empty(field.dtype))
+            .collect();
+        let validity = vec![None; schema.len()];
+        Self {
+            schema,
+            columns,
+            validity,
+        }
+    }
+
+    /// Builds a batch with `Schema::time_series()`.
+    ///
+    /// Panics if the three vectors differ in length.
+    pub fn time_series(ts: Vec<i64>, series_id: Vec<u32>, value: Vec<f64>) -> Self {
+        assert!(
+            ts.len() == series_id.len() && ts.len() == value.len(),
+            "column length mismatch"
+        );
+        Self {
+            schema: Arc::new(Schema::time_series()),
+            columns: vec![Column::I64(ts), Column::U32(series_id), Column::F64(value)],
+            validity: vec![None; 3],
+        }
+    }
+
+    pub fn schema(&self) -> &Arc<Schema> {
+        &self.schema
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn into_columns(self) -> Vec<Column> {
+        self.columns
+    }
+
+    pub fn validity(&self, idx: usize) -> Option<&Bitmap> {
+        self.validity[idx].as_ref()
+    }
+
+    pub fn validity_by_name(&self, name: &str) -> Option<&Bitmap> {
+        self.validity[self.schema.index_of(name)?].as_ref()
+    }
+
+    pub fn is_valid(&self, idx: usize, row: usize) -> bool {
+        self.validity[idx]
+            .as_ref()
+            .map_or(true, |bitmap| bitmap.get(row))
+    }
+
+    pub fn null_count(&self, idx: usize) -> usize {
+        self.validity[idx].as_ref().map_or(0, Bitmap::null_count)
+    }
+
+    pub fn column(&self, idx: usize) -> &Column {
+        &self.columns[idx]
+    }
+
+    pub fn column_by_name(&self, name: &str) -> Option<&Column> {
+        self.schema.index_of(name).map(|idx| &self.columns[idx])
+    }
+
     pub fn len(&self) -> usize {
-        self.ts.len()
+        self.columns.first().map_or(0, Column::len)
     }
 
     pub fn is_empty(&self) -> bool {
-        self.ts.is_empty()
+        self.len() == 0
+    }
+
+    /// The `ts` column, or an empty slice when the batch has none.
+    pub fn ts(&self) -> &[i64] {
+        self.column_by_name(TS_COLUMN)
+            .and_then(Column::as_i64)
+            .unwrap_or(&[])
+    }
+
+    /// The `series_id` column, or an empty slice when the batch has none.
+    pub fn series_id(&self) -> &[u32] {
+        self.column_by_name(SERIES_ID_COLUMN)
+            .and_then(Column::as_u32)
+            .unwrap_or(&[])
+    }
+
+    /// The `value` column, or an empty slice when the batch has none.
+    pub fn value(&self) -> &[f64] {
+        self.column_by_name(VALUE_COLUMN)
+            .and_then(Column::as_f64)
+            .unwrap_or(&[])
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> RecordBatch {
+        RecordBatch {
+            schema: self.schema.clone(),
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.slice(range.clone()))
+                .collect(),
+            validity: self
+                .validity
+                .iter()
+                .map(|bitmap| {
+                    let bitmap = bitmap.as_ref()?.slice(range.clone());
+                    (bitmap.null_count() > 0).then_some(bitmap)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn filter(&self, mask: &[bool]) -> RecordBatch {
+        RecordBatch {
+            schema: self.schema.clone(),
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.filter(mask))
+                .collect(),
+            validity: self
+                .validity
+                .iter()
+                .map(|bitmap| {
+                    let bitmap = bitmap.as_ref()?.filter(mask);
+                    (bitmap.null_count() > 0).then_some(bitmap)
+                })
+                .collect(),
+        }
     }
 
-    pub fn slice(&self, range: std::ops::Range<usize>) -> RecordBatch {
+    /// Gathers the rows at `rows`, in order.
+    pub fn take(&self, rows: &[u32]) -> RecordBatch {
         RecordBatch {
-            ts: self.ts[range.clone()].to_vec(),
-            series_id: self.series_id[range.clone()].to_vec(),
-            value: self.value[range].to_vec(),
+            schema: self.schema.clone(),
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.take(rows))
+                .collect(),
+            validity: self
+                .validity
+                .iter()
+                .map(|bitmap| {
+                    let bitmap = bitmap.as_ref()?.take(rows);
+                    (bitmap.null_count() > 0).then_some(bitmap)
+                })
+                .collect(),
+        }
+    }
+
+    /// Keeps only the named columns, in the order given.
+    pub fn project(self, names: &[&str]) -> Result<RecordBatch> {
+        // Rejects unknown and repeated names, so each column is taken once.
+        let schema = self.schema.project(names)?;
+        let mut columns: Vec<Option<Column>> = self.columns.into_iter().map(Some).collect();
+        let mut validity = self.validity;
+        let mut projected = Vec::with_capacity(names.len());
+        let mut projected_validity = Vec::with_capacity(names.len());
+        for name in names {
+            let idx = self.schema.index_of(name).unwrap();
+            projected.push(columns[idx].take().unwrap());
+            projected_validity.push(validity[idx].take());
         }
+        Ok(RecordBatch {
+            schema: Arc::new(schema),
+            columns: projected,
+            validity: projected_validity,
+        })
     }
 }
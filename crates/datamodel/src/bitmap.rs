@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+use common::error::{Error, Result};
+
+/// Validity of each row in a column: bit `i` is set when row `i` holds a
+/// value and clear when it is null. Bits are packed LSB first, which is also
+/// the on-disk layout.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Bitmap {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bitmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `len` rows, all valid.
+    pub fn new_valid(len: usize) -> Self {
+        let mut bitmap = Self::new();
+        bitmap.extend_constant(true, len);
+        bitmap
+    }
+
+    pub fn from_bools(valid: &[bool]) -> Self {
+        let mut bitmap = Self::new();
+        for &bit in valid {
+            bitmap.push(bit);
+        }
+        bitmap
+    }
+
+    /// Takes `len` bits from packed bytes; bits past `len` must be zero.
+    pub fn from_bytes(bytes: Vec<u8>, len: usize) -> Result<Self> {
+        if bytes.len() != len.div_ceil(8) {
+            return Err(Error::Corrupt("bitmap length mismatch".into()));
+        }
+        if len % 8 != 0 && bytes[len / 8] >> (len % 8) != 0 {
+            return Err(Error::Corrupt("bitmap has bits past its length".into()));
+        }
+        Ok(Self { bytes, len })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, idx: usize) -> bool {
+        assert!(idx < self.len, "bitmap index out of bounds");
+        self.bytes[idx / 8] & (1 << (idx % 8)) != 0
+    }
+
+    pub fn push(&mut self, valid: bool) {
+        if self.len % 8 == 0 {
+            self.bytes.push(0);
+        }
+        if valid {
+            self.bytes[self.len / 8] |= 1 << (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    pub fn extend_constant(&mut self, valid: bool, count: usize) {
+        for _ in 0..count {
+            self.push(valid);
+        }
+    }
+
+    pub fn extend_from(&mut self, other: &Bitmap, range: Range<usize>) {
+        for idx in range {
+            self.push(other.get(idx));
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|idx| self.get(idx))
+    }
+
+    pub fn null_count(&self) -> usize {
+        let set: u32 = self.bytes.iter().map(|byte| byte.count_ones()).sum();
+        self.len - set as usize
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> Bitmap {
+        let mut out = Bitmap::new();
+        out.extend_from(self, range);
+        out
+    }
+
+    /// Keeps the bits whose mask entry is true.
+    pub fn filter(&self, mask: &[bool]) -> Bitmap {
+        let mut out = Bitmap::new();
+        for (idx, _) in mask.iter().enumerate().filter(|(_, keep)| **keep) {
+            out.push(self.get(idx));
+        }
+        out
+    }
+
+    /// Gathers the bits at `rows`, in order.
+    pub fn take(&self, rows: &[u32]) -> Bitmap {
+        let mut out = Bitmap::new();
+        for &row in rows {
+            out.push(self.get(row as usize));
+        }
+        out
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.len = 0;
+    }
+}
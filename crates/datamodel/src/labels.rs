@@ -0,0 +1,84 @@
+use std::fmt;
+
+use common::error::{Error, Result};
+
+/// The label set identifying a series, e.g. `host=a,region=us`. Labels are
+/// kept sorted by name, so equal sets compare and hash equal regardless of
+/// the order they were given in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Labels {
+    pairs: Vec<(String, String)>,
+}
+
+impl Labels {
+    pub fn new<N, V>(pairs: impl IntoIterator<Item = (N, V)>) -> Result<Self>
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let mut pairs: Vec<(String, String)> = pairs
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        pairs.sort();
+        for (i, (name, _)) in pairs.iter().enumerate() {
+            if name.is_empty() {
+                return Err(Error::Unsupported("empty label name".into()));
+            }
+            if i > 0 && pairs[i - 1].0 == *name {
+                return Err(Error::Unsupported(format!("duplicate label: {}", name)));
+            }
+        }
+        Ok(Self { pairs })
+    }
+
+    /// Parses the `name=value,name=value` form produced by `Display`. Labels
+    /// whose names or values contain `,`, or whose names contain `=`, do not
+    /// survive that form.
+    pub fn parse(text: &str) -> Result<Self> {
+        if text.is_empty() {
+            return Ok(Self::default());
+        }
+        let pairs = text
+            .split(',')
+            .map(|pair| {
+                pair.split_once('=')
+                    .ok_or_else(|| Error::Unsupported(format!("bad label: {}", pair)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(pairs)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .binary_search_by(|(n, _)| n.as_str().cmp(name))
+            .ok()
+            .map(|idx| self.pairs[idx].1.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.pairs
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl fmt::Display for Labels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 //! datamodel crate scaffold.
 
 pub mod batch;
+pub mod bitmap;
+pub mod labels;
 pub mod schema;
 pub mod types;
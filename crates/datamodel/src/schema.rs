@@ -1,15 +1,127 @@
-#![allow(dead_code)]
-struct Field {
-    name: String,
-    dtype: DataType,
-}
+use common::error::{Error, Result};
+
+/// Name of the i64 timestamp column every chunk is ordered and indexed by.
+pub const TS_COLUMN: &str = "ts";
+/// Name of the u32 column holding each row's series id.
+pub const SERIES_ID_COLUMN: &str = "series_id";
+/// Name of the f64 column holding each row's sample value.
+pub const VALUE_COLUMN: &str = "value";
 
-enum DataType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
     I64,
     U32,
     F64,
+    Utf8,
+}
+
+impl DataType {
+    /// Stable on-disk id.
+    pub fn id(self) -> u8 {
+        match self {
+            DataType::I64 => 0,
+            DataType::U32 => 1,
+            DataType::F64 => 2,
+            DataType::Utf8 => 3,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(DataType::I64),
+            1 => Some(DataType::U32),
+            2 => Some(DataType::F64),
+            3 => Some(DataType::Utf8),
+            _ => None,
+        }
+    }
+
+    /// Bytes per value in the raw encoding. Strings are stored as u32
+    /// codes into a per-chunk dictionary, so this is the width of a code.
+    pub fn width(self) -> usize {
+        match self {
+            DataType::I64 | DataType::F64 => 8,
+            DataType::U32 | DataType::Utf8 => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub dtype: DataType,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, dtype: DataType) -> Self {
+        Self {
+            name: name.into(),
+            dtype,
+        }
+    }
 }
 
-struct Schema {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
     fields: Vec<Field>,
 }
+
+impl Schema {
+    pub fn new(fields: Vec<Field>) -> Result<Self> {
+        for (i, field) in fields.iter().enumerate() {
+            if field.name.is_empty() {
+                return Err(Error::Unsupported("empty field name".into()));
+            }
+            if fields[..i].iter().any(|f| f.name == field.name) {
+                return Err(Error::Unsupported(format!(
+                    "duplicate field: {}",
+                    field.name
+                )));
+            }
+        }
+        Ok(Self { fields })
+    }
+
+    /// The original fixed layout: `ts: i64`, `series_id: u32`, `value: f64`.
+    pub fn time_series() -> Self {
+        Self {
+            fields: vec![
+                Field::new(TS_COLUMN, DataType::I64),
+                Field::new(SERIES_ID_COLUMN, DataType::U32),
+                Field::new(VALUE_COLUMN, DataType::F64),
+            ],
+        }
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn field(&self, idx: usize) -> &Field {
+        &self.fields[idx]
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == name)
+    }
+
+    /// Schema with only the named fields, in the order given.
+    pub fn project(&self, names: &[&str]) -> Result<Schema> {
+        let mut fields = Vec::with_capacity(names.len());
+        for name in names {
+            let idx = self
+                .index_of(name)
+                .ok_or_else(|| Error::Unsupported(format!("unknown column: {}", name)))?;
+            fields.push(self.fields[idx].clone());
+        }
+        Schema::new(fields)
+    }
+}
@@ -0,0 +1,96 @@
+//! Gorilla-style delta-of-delta encoding for i64 timestamps.
+//!
+//! Layout: the first value as 8 little-endian bytes, followed by a bitstream
+//! holding one delta-of-delta per remaining value. Each delta-of-delta is
+//! zigzag encoded and written with the smallest prefix bucket that fits:
+//!
+//! - `0`: delta unchanged
+//! - `10` + 7 bits, `110` + 9 bits, `1110` + 12 bits
+//! - `1111` + 64 bits: anything else
+//!
+//! Arithmetic wraps, so any i64 sequence round-trips exactly.
+
+use common::error::{Error, Result};
+
+use crate::bits::{zigzag_decode, zigzag_encode, BitReader, BitWriter};
+
+const BUCKETS: [(u64, u32, u32); 3] = [(0b10, 2, 7), (0b110, 3, 9), (0b1110, 4, 12)];
+
+pub fn encode_i64(values: &[i64]) -> Vec<u8> {
+    let first = match values.first() {
+        Some(first) => *first,
+        None => return Vec::new(),
+    };
+
+    let mut writer = BitWriter::new();
+    let mut prev = first;
+    let mut prev_delta = 0i64;
+    for &value in &values[1..] {
+        let delta = value.wrapping_sub(prev);
+        let dod = zigzag_encode(delta.wrapping_sub(prev_delta));
+        write_dod(&mut writer, dod);
+        prev = value;
+        prev_delta = delta;
+    }
+
+    let mut out = Vec::with_capacity(8 + values.len() / 4);
+    out.extend_from_slice(&first.to_le_bytes());
+    out.extend_from_slice(&writer.finish());
+    out
+}
+
+pub fn decode_i64(buf: &[u8], count: usize) -> Result<Vec<i64>> {
+    if count == 0 {
+        if !buf.is_empty() {
+            return Err(Error::Corrupt("delta payload for empty column".into()));
+        }
+        return Ok(Vec::new());
+    }
+    if buf.len() < 8 {
+        return Err(Error::Corrupt("delta payload too short".into()));
+    }
+
+    let first = i64::from_le_bytes(buf[..8].try_into().unwrap());
+    let mut reader = BitReader::new(&buf[8..]);
+    let mut out = Vec::with_capacity(count);
+    out.push(first);
+    let mut prev = first;
+    let mut prev_delta = 0i64;
+    for _ in 1..count {
+        let dod = zigzag_decode(read_dod(&mut reader)?);
+        let delta = prev_delta.wrapping_add(dod);
+        let value = prev.wrapping_add(delta);
+        out.push(value);
+        prev = value;
+        prev_delta = delta;
+    }
+    Ok(out)
+}
+
+fn write_dod(writer: &mut BitWriter, dod: u64) {
+    if dod == 0 {
+        writer.write_bit(false);
+        return;
+    }
+    for (prefix, prefix_bits, value_bits) in BUCKETS {
+        if dod < (1u64 << value_bits) {
+            writer.write_bits(prefix, prefix_bits);
+            writer.write_bits(dod, value_bits);
+            return;
+        }
+    }
+    writer.write_bits(0b1111, 4);
+    writer.write_bits(dod, 64);
+}
+
+fn read_dod(reader: &mut BitReader<'_>) -> Result<u64> {
+    let mut ones = 0;
+    while ones < 4 && reader.read_bit()? {
+        ones += 1;
+    }
+    match ones {
+        0 => Ok(0),
+        4 => reader.read_bits(64),
+        n => reader.read_bits(BUCKETS[n - 1].2),
+    }
+}
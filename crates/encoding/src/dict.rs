@@ -0,0 +1,98 @@
+//! Dictionary encoding with bit-packed codes for u32 columns.
+//!
+//! Layout: dictionary size as a varint, the sorted dictionary values as
+//! varints, the code bit width as one byte, then one code per row packed
+//! MSB-first.
+
+use std::collections::BTreeSet;
+
+use common::error::{Error, Result};
+
+use crate::bits::{BitReader, BitWriter};
+use crate::varint;
+
+pub fn encode_u32(values: &[u32]) -> Vec<u8> {
+    let dict: Vec<u32> = values
+        .iter()
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let width = code_width(dict.len());
+
+    let mut out = Vec::new();
+    varint::write_u32(&mut out, dict.len() as u32);
+    for &value in &dict {
+        varint::write_u32(&mut out, value);
+    }
+    out.push(width as u8);
+
+    let mut writer = BitWriter::new();
+    if width > 0 {
+        for value in values {
+            let code = dict.binary_search(value).unwrap();
+            writer.write_bits(code as u64, width);
+        }
+    }
+    out.extend_from_slice(&writer.finish());
+    out
+}
+
+pub fn decode_u32(buf: &[u8], count: usize) -> Result<Vec<u32>> {
+    let mut pos = 0;
+    let dict_len = varint::read_u32(buf, &mut pos)? as usize;
+    if dict_len > count {
+        return Err(Error::Corrupt("dictionary larger than column".into()));
+    }
+    let mut dict = Vec::with_capacity(dict_len);
+    for _ in 0..dict_len {
+        dict.push(varint::read_u32(buf, &mut pos)?);
+    }
+    let width = *buf
+        .get(pos)
+        .ok_or_else(|| Error::Corrupt("dictionary width missing".into()))? as u32;
+    pos += 1;
+    if width != code_width(dict_len) {
+        return Err(Error::Corrupt("dictionary width mismatch".into()));
+    }
+
+    if width == 0 {
+        return match dict.first() {
+            Some(&value) => Ok(vec![value; count]),
+            None if count == 0 => Ok(Vec::new()),
+            None => Err(Error::Corrupt("empty dictionary".into())),
+        };
+    }
+
+    let mut reader = BitReader::new(&buf[pos..]);
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let code = reader.read_bits(width)? as usize;
+        let value = *dict
+            .get(code)
+            .ok_or_else(|| Error::Corrupt("dictionary code out of range".into()))?;
+        out.push(value);
+    }
+    Ok(out)
+}
+
+/// Returns `None` when the column has more distinct values than `max_distinct`.
+pub fn encoded_len(values: &[u32], max_distinct: usize) -> Option<usize> {
+    let mut dict = BTreeSet::new();
+    for &value in values {
+        if dict.insert(value) && dict.len() > max_distinct {
+            return None;
+        }
+    }
+    let width = code_width(dict.len()) as usize;
+    let dict_bytes: usize = dict.iter().map(|&value| varint::len_u32(value)).sum();
+    Some(varint::len_u32(dict.len() as u32) + dict_bytes + 1 + (values.len() * width).div_ceil(8))
+}
+
+fn code_width(dict_len: usize) -> u32 {
+    if dict_len <= 1 {
+        0
+    } else {
+        usize::BITS - (dict_len - 1).leading_zeros()
+    }
+}
@@ -1,3 +1,8 @@
-//! encoding crate scaffold.
+//! Column encodings used by the chunk format.
 
-pub fn placeholder() {}
+pub mod bits;
+pub mod delta;
+pub mod dict;
+pub mod rle;
+pub mod varint;
+pub mod xor;
@@ -0,0 +1,51 @@
+//! Run-length encoding for u32 columns.
+//!
+//! Layout: a sequence of `(value, run_len)` pairs, both as LEB128 varints.
+
+use common::error::{Error, Result};
+
+use crate::varint;
+
+pub fn encode_u32(values: &[u32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (value, run) in runs(values) {
+        varint::write_u32(&mut out, value);
+        varint::write_u32(&mut out, run);
+    }
+    out
+}
+
+pub fn decode_u32(buf: &[u8], count: usize) -> Result<Vec<u32>> {
+    let mut out = Vec::with_capacity(count);
+    let mut pos = 0;
+    while pos < buf.len() {
+        let value = varint::read_u32(buf, &mut pos)?;
+        let run = varint::read_u32(buf, &mut pos)? as usize;
+        if run == 0 || out.len() + run > count {
+            return Err(Error::Corrupt("rle run out of range".into()));
+        }
+        out.resize(out.len() + run, value);
+    }
+    if out.len() != count {
+        return Err(Error::Corrupt("rle row count mismatch".into()));
+    }
+    Ok(out)
+}
+
+pub fn encoded_len(values: &[u32]) -> usize {
+    runs(values)
+        .map(|(value, run)| varint::len_u32(value) + varint::len_u32(run))
+        .sum()
+}
+
+fn runs(values: &[u32]) -> impl Iterator<Item = (u32, u32)> + '_ {
+    let mut idx = 0;
+    std::iter::from_fn(move || {
+        let value = *values.get(idx)?;
+        let start = idx;
+        while idx < values.len() && values[idx] == value && idx - start < u32::MAX as usize {
+            idx += 1;
+        }
+        Some((value, (idx - start) as u32))
+    })
+}
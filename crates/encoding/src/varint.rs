@@ -0,0 +1,37 @@
+use common::error::{Error, Result};
+
+pub fn write_u32(buf: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+pub fn read_u32(buf: &[u8], pos: &mut usize) -> Result<u32> {
+    let mut out = 0u32;
+    for i in 0..5 {
+        let byte = *buf
+            .get(*pos)
+            .ok_or_else(|| Error::Corrupt("varint truncated".into()))?;
+        *pos += 1;
+        if i == 4 && byte > 0x0F {
+            return Err(Error::Corrupt("varint overflow".into()));
+        }
+        out |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(out);
+        }
+    }
+    Err(Error::Corrupt("varint overflow".into()))
+}
+
+pub fn len_u32(value: u32) -> usize {
+    match value {
+        0..=0x7F => 1,
+        0x80..=0x3FFF => 2,
+        0x4000..=0x1F_FFFF => 3,
+        0x20_0000..=0x0FFF_FFFF => 4,
+        _ => 5,
+    }
+}
@@ -0,0 +1,97 @@
+//! Gorilla-style XOR encoding for f64 values.
+//!
+//! Layout: the first value's bits as 8 little-endian bytes, followed by a
+//! bitstream with one entry per remaining value, XORed against the previous:
+//!
+//! - `0`: identical to the previous value
+//! - `10` + meaningful bits: fits inside the previous leading/trailing window
+//! - `11` + 6 bits leading zeros + 6 bits (length - 1) + meaningful bits
+
+use common::error::{Error, Result};
+
+use crate::bits::{BitReader, BitWriter};
+
+pub fn encode_f64(values: &[f64]) -> Vec<u8> {
+    let first = match values.first() {
+        Some(first) => first.to_bits(),
+        None => return Vec::new(),
+    };
+
+    let mut writer = BitWriter::new();
+    let mut prev = first;
+    let mut window: Option<(u32, u32)> = None;
+    for value in &values[1..] {
+        let bits = value.to_bits();
+        let xor = bits ^ prev;
+        prev = bits;
+        if xor == 0 {
+            writer.write_bit(false);
+            continue;
+        }
+        writer.write_bit(true);
+
+        let leading = xor.leading_zeros();
+        let trailing = xor.trailing_zeros();
+        match window {
+            Some((prev_leading, prev_trailing))
+                if leading >= prev_leading && trailing >= prev_trailing =>
+            {
+                writer.write_bit(false);
+                let len = 64 - prev_leading - prev_trailing;
+                writer.write_bits(xor >> prev_trailing, len);
+            }
+            _ => {
+                let len = 64 - leading - trailing;
+                writer.write_bit(true);
+                writer.write_bits(leading as u64, 6);
+                writer.write_bits((len - 1) as u64, 6);
+                writer.write_bits(xor >> trailing, len);
+                window = Some((leading, trailing));
+            }
+        }
+    }
+
+    let mut out = Vec::with_capacity(8 + values.len() * 2);
+    out.extend_from_slice(&first.to_le_bytes());
+    out.extend_from_slice(&writer.finish());
+    out
+}
+
+pub fn decode_f64(buf: &[u8], count: usize) -> Result<Vec<f64>> {
+    if count == 0 {
+        if !buf.is_empty() {
+            return Err(Error::Corrupt("xor payload for empty column".into()));
+        }
+        return Ok(Vec::new());
+    }
+    if buf.len() < 8 {
+        return Err(Error::Corrupt("xor payload too short".into()));
+    }
+
+    let first = u64::from_le_bytes(buf[..8].try_into().unwrap());
+    let mut reader = BitReader::new(&buf[8..]);
+    let mut out = Vec::with_capacity(count);
+    out.push(f64::from_bits(first));
+    let mut prev = first;
+    let mut window: Option<(u32, u32)> = None;
+    for _ in 1..count {
+        if reader.read_bit()? {
+            let (leading, trailing) = if reader.read_bit()? {
+                let leading = reader.read_bits(6)? as u32;
+                let len = reader.read_bits(6)? as u32 + 1;
+                if leading + len > 64 {
+                    return Err(Error::Corrupt("xor window out of range".into()));
+                }
+                let trailing = 64 - leading - len;
+                window = Some((leading, trailing));
+                (leading, trailing)
+            } else {
+                window.ok_or_else(|| Error::Corrupt("xor window reused before set".into()))?
+            };
+            let len = 64 - leading - trailing;
+            prev ^= reader.read_bits(len)? << trailing;
+        }
+        out.push(f64::from_bits(prev));
+    }
+    Ok(out)
+}
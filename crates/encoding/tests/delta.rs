@@ -0,0 +1,60 @@
+use common::error::Result;
+use encoding::delta::{decode_i64, encode_i64};
+
+#[test]
+fn regular_timestamps_compress() -> Result<()> {
+    let ts: Vec<i64> = (0..16_384).map(|i| 1_700_000_000_000 + i * 1000).collect();
+    let buf = encode_i64(&ts);
+    assert!(buf.len() < ts.len() / 4, "encoded {} bytes", buf.len());
+    assert_eq!(decode_i64(&buf, ts.len())?, ts);
+    Ok(())
+}
+
+#[test]
+fn irregular_and_extreme_values_roundtrip() -> Result<()> {
+    let mut rng = Lcg::new(0x0DDB_1A5E_5BAD_5EED);
+    let mut ts = vec![i64::MIN, i64::MAX, 0, -1, 1, i64::MAX, i64::MIN];
+    let mut cur = 0i64;
+    for _ in 0..4096 {
+        cur += (rng.next_u64() % 5000) as i64 - 1000;
+        ts.push(cur);
+    }
+    for _ in 0..256 {
+        ts.push(rng.next_u64() as i64);
+    }
+    let buf = encode_i64(&ts);
+    assert_eq!(decode_i64(&buf, ts.len())?, ts);
+    Ok(())
+}
+
+#[test]
+fn empty_and_single() -> Result<()> {
+    assert!(encode_i64(&[]).is_empty());
+    assert!(decode_i64(&[], 0)?.is_empty());
+    let buf = encode_i64(&[42]);
+    assert_eq!(decode_i64(&buf, 1)?, vec![42]);
+    Ok(())
+}
+
+#[test]
+fn truncated_payload_is_corrupt() {
+    let ts: Vec<i64> = (0..100).map(|i| i * i).collect();
+    let buf = encode_i64(&ts);
+    assert!(decode_i64(&buf[..buf.len() / 2], ts.len()).is_err());
+    assert!(decode_i64(&buf[..4], ts.len()).is_err());
+}
+
+struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        self.state
+    }
+}
@@ -0,0 +1,41 @@
+use common::error::Result;
+use encoding::{dict, rle};
+
+#[test]
+fn rle_roundtrip_runs() -> Result<()> {
+    let mut values = Vec::new();
+    for series in 0..64u32 {
+        values.extend(std::iter::repeat(series * 1_000_003).take(256));
+    }
+    let buf = rle::encode_u32(&values);
+    assert_eq!(buf.len(), rle::encoded_len(&values));
+    assert!(buf.len() < 64 * 8);
+    assert_eq!(rle::decode_u32(&buf, values.len())?, values);
+    assert!(rle::decode_u32(&buf, values.len() - 1).is_err());
+    assert!(rle::decode_u32(&buf, values.len() + 1).is_err());
+    Ok(())
+}
+
+#[test]
+fn dict_roundtrip_low_cardinality() -> Result<()> {
+    let values: Vec<u32> = (0..16_384u32)
+        .map(|i| (i % 37) * 7919 + u32::MAX / 2)
+        .collect();
+    let buf = dict::encode_u32(&values);
+    assert_eq!(Some(buf.len()), dict::encoded_len(&values, 1024));
+    assert!(buf.len() < values.len());
+    assert_eq!(dict::decode_u32(&buf, values.len())?, values);
+    assert!(dict::decode_u32(&buf[..buf.len() - 1], values.len()).is_err());
+    assert_eq!(dict::encoded_len(&values, 16), None);
+    Ok(())
+}
+
+#[test]
+fn single_value_and_empty() -> Result<()> {
+    let values = vec![9u32; 1000];
+    assert_eq!(dict::decode_u32(&dict::encode_u32(&values), 1000)?, values);
+    assert_eq!(rle::decode_u32(&rle::encode_u32(&values), 1000)?, values);
+    assert!(dict::decode_u32(&dict::encode_u32(&[]), 0)?.is_empty());
+    assert!(rle::decode_u32(&rle::encode_u32(&[]), 0)?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use common::error::Result;
+use encoding::xor::{decode_f64, encode_f64};
+
+#[test]
+fn slowly_changing_values_compress() -> Result<()> {
+    let mut values = Vec::with_capacity(16_384);
+    let mut cur = 21.5f64;
+    for i in 0..16_384 {
+        if i % 16 == 0 {
+            cur += 0.25;
+        }
+        values.push(cur);
+    }
+    let buf = encode_f64(&values);
+    assert!(
+        buf.len() * 5 < values.len() * 8,
+        "encoded {} bytes",
+        buf.len()
+    );
+    assert_eq!(decode_f64(&buf, values.len())?, values);
+    Ok(())
+}
+
+#[test]
+fn arbitrary_bits_roundtrip() -> Result<()> {
+    let mut rng = Lcg::new(0xF10A_7F10_A7F1_0A7F);
+    let mut values = vec![
+        0.0,
+        -0.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::MIN_POSITIVE,
+    ];
+    for i in 0..4096 {
+        values.push((i as f64).sin());
+        values.push(f64::from_bits(rng.next_u64()));
+    }
+    let buf = encode_f64(&values);
+    let decoded = decode_f64(&buf, values.len())?;
+    assert_eq!(decoded.len(), values.len());
+    for (a, b) in decoded.iter().zip(values.iter()) {
+        assert_eq!(a.to_bits(), b.to_bits());
+    }
+    Ok(())
+}
+
+#[test]
+fn truncated_payload_is_corrupt() {
+    let values: Vec<f64> = (0..100).map(|i| (i as f64).sqrt()).collect();
+    let buf = encode_f64(&values);
+    assert!(decode_f64(&buf[..buf.len() / 2], values.len()).is_err());
+    assert!(decode_f64(&[], 1).is_err());
+}
+
+struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1);
+        self.state
+    }
+}
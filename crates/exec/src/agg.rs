@@ -1,13 +1,617 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use common::{Error, Result};
+use datamodel::batch::RecordBatch;
+use storage::stats::ColumnStats;
+
 #[derive(Debug, Clone)]
 pub struct AggRow {
     pub window_start: i64,
+    /// Non-null values in the window; never 0.
     pub count: u32,
-    pub sum: f64,
-    pub min: f64,
-    pub max: f64,
+    /// One result per selected aggregate, in selection order; `None` where
+    /// an aggregate has no result, like the variance of a single value.
+    pub values: Vec<Option<f64>>,
+    names: Arc<[String]>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct AggResult {
     pub rows: Vec<AggRow>,
 }
+
+/// What a grouped aggregate groups rows by, besides their window.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GroupKey {
+    Series(u32),
+    Tag(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupedAggRow {
+    pub key: GroupKey,
+    pub agg: AggRow,
+}
+
+/// Rows ordered by group, then window.
+#[derive(Debug, Clone, Default)]
+pub struct GroupedAggResult {
+    pub rows: Vec<GroupedAggRow>,
+}
+
+/// The start of the `window`-wide window `ts` falls in, with windows
+/// aligned so one starts at `origin`. Rounds down, also before `origin`.
+pub(crate) fn window_start(ts: i64, window: i64, origin: i64) -> i64 {
+    let (ts, window, origin) = (ts as i128, window as i128, origin as i128);
+    let start = origin + (ts - origin).div_euclid(window) * window;
+    // Only a window holding i64::MIN can start before it.
+    i64::try_from(start).unwrap_or(i64::MIN)
+}
+
+/// The `ts` and value columns of `batch`, which must hold a value for
+/// every row.
+pub(crate) fn ts_and_values(batch: &RecordBatch) -> Result<(&[i64], &[f64])> {
+    let (ts, values) = (batch.ts(), batch.value());
+    if ts.len() != batch.len() || values.len() != batch.len() {
+        return Err(Error::Corrupt(format!(
+            "aggregate input needs ts and value columns of {} rows",
+            batch.len()
+        )));
+    }
+    Ok((ts, values))
+}
+
+impl AggRow {
+    /// Builds a count/sum/min/max row from column statistics alone, without
+    /// reading column data.
+    pub fn from_stats(window_start: i64, stats: &ColumnStats) -> Option<AggRow> {
+        Aggregates::summary().row_from_stats(window_start, stats)
+    }
+
+    /// The result of the aggregate selected as `name`; `None` if it was not
+    /// selected or has no result.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        let idx = self.names.iter().position(|n| n == name)?;
+        self.values[idx]
+    }
+
+    /// The selected aggregates, in the order of `values`.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+/// An aggregate over the non-null values of a window. Its state starts at
+/// `init`, takes each value through `update` in no particular order, may be
+/// combined with the state of other rows of the same window through `merge`,
+/// and becomes a result in `finalize`.
+pub trait AggregateFunction: Send + Sync + 'static {
+    type State: Send + 'static;
+
+    fn init(&self) -> Self::State;
+
+    fn update(&self, state: &mut Self::State, ts: i64, value: f64);
+
+    fn merge(&self, state: &mut Self::State, other: Self::State);
+
+    fn finalize(&self, state: &Self::State) -> Option<f64>;
+
+    /// The state over a whole column from its stats alone, for aggregates
+    /// that can be answered without reading the data.
+    fn state_from_stats(&self, _stats: &ColumnStats) -> Option<Self::State> {
+        None
+    }
+}
+
+type AggState = Box<dyn Any + Send>;
+
+/// `AggregateFunction` with its state type erased, so a selection can mix
+/// aggregates.
+trait DynAggregate: Send + Sync {
+    fn init_state(&self) -> AggState;
+    fn update_state(&self, state: &mut AggState, ts: i64, value: f64);
+    fn merge_state(&self, state: &mut AggState, other: AggState);
+    fn finalize_state(&self, state: &AggState) -> Option<f64>;
+    fn stats_state(&self, stats: &ColumnStats) -> Option<AggState>;
+}
+
+impl<F: AggregateFunction> DynAggregate for F {
+    fn init_state(&self) -> AggState {
+        Box::new(self.init())
+    }
+
+    fn update_state(&self, state: &mut AggState, ts: i64, value: f64) {
+        self.update(downcast_mut::<F>(state), ts, value);
+    }
+
+    fn merge_state(&self, state: &mut AggState, other: AggState) {
+        let other = *other
+            .downcast::<F::State>()
+            .expect("state of another aggregate");
+        self.merge(downcast_mut::<F>(state), other);
+    }
+
+    fn finalize_state(&self, state: &AggState) -> Option<f64> {
+        let state = state
+            .downcast_ref::<F::State>()
+            .expect("state of another aggregate");
+        self.finalize(state)
+    }
+
+    fn stats_state(&self, stats: &ColumnStats) -> Option<AggState> {
+        let state = self.state_from_stats(stats)?;
+        Some(Box::new(state))
+    }
+}
+
+fn downcast_mut<F: AggregateFunction>(state: &mut AggState) -> &mut F::State {
+    state
+        .downcast_mut::<F::State>()
+        .expect("state of another aggregate")
+}
+
+/// Aggregates by name, from which each query selects the ones it needs.
+#[derive(Clone, Default)]
+pub struct AggRegistry {
+    funcs: HashMap<String, Arc<dyn DynAggregate>>,
+}
+
+impl AggRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// count, sum, avg, min, max, first, last, and the sample `variance`
+    /// and `stddev`.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        let builtins: [(&str, Arc<dyn DynAggregate>); 9] = [
+            ("count", Arc::new(Count)),
+            ("sum", Arc::new(Sum)),
+            ("avg", Arc::new(Avg)),
+            ("min", Arc::new(Min)),
+            ("max", Arc::new(Max)),
+            ("first", Arc::new(First)),
+            ("last", Arc::new(Last)),
+            ("variance", Arc::new(Variance)),
+            ("stddev", Arc::new(Stddev)),
+        ];
+        for (name, func) in builtins {
+            registry.funcs.insert(name.to_string(), func);
+        }
+        registry
+    }
+
+    /// Fails if `name` is taken.
+    pub fn register<F: AggregateFunction>(&mut self, name: &str, func: F) -> Result<()> {
+        if self.funcs.contains_key(name) {
+            return Err(Error::Unsupported(format!(
+                "aggregate already registered: {}",
+                name
+            )));
+        }
+        self.funcs.insert(name.to_string(), Arc::new(func));
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.funcs.contains_key(name)
+    }
+
+    /// The named aggregates, in the order given.
+    pub fn select(&self, names: &[&str]) -> Result<Aggregates> {
+        let mut funcs = Vec::with_capacity(names.len());
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(Error::Unsupported(format!(
+                    "aggregate selected twice: {}",
+                    name
+                )));
+            }
+            let func = self
+                .funcs
+                .get(*name)
+                .ok_or_else(|| Error::Unsupported(format!("unknown aggregate: {}", name)))?;
+            funcs.push(func.clone());
+        }
+        Ok(Aggregates {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            funcs,
+        })
+    }
+}
+
+impl fmt::Debug for AggRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.funcs.keys().collect();
+        names.sort();
+        f.debug_struct("AggRegistry")
+            .field("funcs", &names)
+            .finish()
+    }
+}
+
+/// The aggregates one query computes for each window.
+#[derive(Clone)]
+pub struct Aggregates {
+    names: Arc<[String]>,
+    funcs: Vec<Arc<dyn DynAggregate>>,
+}
+
+/// Running state of one window.
+pub(crate) struct WindowAcc {
+    pub(crate) window_start: i64,
+    count: u32,
+    states: Vec<AggState>,
+}
+
+impl Aggregates {
+    /// count, sum, min and max: what the downsampling operators compute
+    /// unless a query selects otherwise.
+    pub fn summary() -> Self {
+        AggRegistry::with_builtins()
+            .select(&["count", "sum", "min", "max"])
+            .expect("builtin aggregates")
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// A row for a whole column from its stats; `None` if the column has
+    /// no non-null values or an aggregate needs the data itself.
+    pub fn row_from_stats(&self, window_start: i64, stats: &ColumnStats) -> Option<AggRow> {
+        let count = stats.count.checked_sub(stats.null_count)?;
+        if count == 0 {
+            return None;
+        }
+        let states = self
+            .funcs
+            .iter()
+            .map(|func| func.stats_state(stats))
+            .collect::<Option<Vec<_>>>()?;
+        Some(self.finish(WindowAcc {
+            window_start,
+            count,
+            states,
+        }))
+    }
+
+    pub(crate) fn start(&self, window_start: i64, ts: i64, value: f64) -> WindowAcc {
+        let mut states: Vec<AggState> = self.funcs.iter().map(|func| func.init_state()).collect();
+        for (func, state) in self.funcs.iter().zip(&mut states) {
+            func.update_state(state, ts, value);
+        }
+        WindowAcc {
+            window_start,
+            count: 1,
+            states,
+        }
+    }
+
+    pub(crate) fn update(&self, acc: &mut WindowAcc, ts: i64, value: f64) -> Result<()> {
+        acc.count = acc
+            .count
+            .checked_add(1)
+            .ok_or_else(|| Error::Unsupported("count overflow".into()))?;
+        for (func, state) in self.funcs.iter().zip(&mut acc.states) {
+            func.update_state(state, ts, value);
+        }
+        Ok(())
+    }
+
+    /// Folds in another accumulator for the same window.
+    pub(crate) fn merge(&self, acc: &mut WindowAcc, other: WindowAcc) -> Result<()> {
+        acc.count = acc
+            .count
+            .checked_add(other.count)
+            .ok_or_else(|| Error::Unsupported("count overflow".into()))?;
+        for ((func, state), other) in self.funcs.iter().zip(&mut acc.states).zip(other.states) {
+            func.merge_state(state, other);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(&self, acc: WindowAcc) -> AggRow {
+        AggRow {
+            window_start: acc.window_start,
+            count: acc.count,
+            values: self
+                .funcs
+                .iter()
+                .zip(&acc.states)
+                .map(|(func, state)| func.finalize_state(state))
+                .collect(),
+            names: self.names.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for Aggregates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Aggregates").field(&self.names).finish()
+    }
+}
+
+struct Count;
+
+impl AggregateFunction for Count {
+    type State = u64;
+
+    fn init(&self) -> u64 {
+        0
+    }
+
+    fn update(&self, state: &mut u64, _ts: i64, _value: f64) {
+        *state += 1;
+    }
+
+    fn merge(&self, state: &mut u64, other: u64) {
+        *state += other;
+    }
+
+    fn finalize(&self, state: &u64) -> Option<f64> {
+        Some(*state as f64)
+    }
+
+    fn state_from_stats(&self, stats: &ColumnStats) -> Option<u64> {
+        Some(stats.count.checked_sub(stats.null_count)? as u64)
+    }
+}
+
+struct Sum;
+
+impl AggregateFunction for Sum {
+    type State = f64;
+
+    fn init(&self) -> f64 {
+        0.0
+    }
+
+    fn update(&self, state: &mut f64, _ts: i64, value: f64) {
+        *state += value;
+    }
+
+    fn merge(&self, state: &mut f64, other: f64) {
+        *state += other;
+    }
+
+    fn finalize(&self, state: &f64) -> Option<f64> {
+        Some(*state)
+    }
+
+    fn state_from_stats(&self, stats: &ColumnStats) -> Option<f64> {
+        Some(stats.sum)
+    }
+}
+
+struct Avg;
+
+impl AggregateFunction for Avg {
+    /// Sum and count.
+    type State = (f64, u64);
+
+    fn init(&self) -> (f64, u64) {
+        (0.0, 0)
+    }
+
+    fn update(&self, state: &mut (f64, u64), _ts: i64, value: f64) {
+        state.0 += value;
+        state.1 += 1;
+    }
+
+    fn merge(&self, state: &mut (f64, u64), other: (f64, u64)) {
+        state.0 += other.0;
+        state.1 += other.1;
+    }
+
+    fn finalize(&self, state: &(f64, u64)) -> Option<f64> {
+        (state.1 > 0).then(|| state.0 / state.1 as f64)
+    }
+
+    fn state_from_stats(&self, stats: &ColumnStats) -> Option<(f64, u64)> {
+        Some((stats.sum, stats.count.checked_sub(stats.null_count)? as u64))
+    }
+}
+
+/// Min and max skip NaN, as the column stats do; when every value is NaN
+/// the result is NaN.
+struct Min;
+
+impl AggregateFunction for Min {
+    type State = Option<f64>;
+
+    fn init(&self) -> Option<f64> {
+        None
+    }
+
+    fn update(&self, state: &mut Option<f64>, _ts: i64, value: f64) {
+        self.merge(state, Some(value));
+    }
+
+    fn merge(&self, state: &mut Option<f64>, other: Option<f64>) {
+        match (*state, other) {
+            (None, _) => *state = other,
+            (Some(min), Some(value)) if value < min || min.is_nan() => *state = other,
+            _ => {}
+        }
+    }
+
+    fn finalize(&self, state: &Option<f64>) -> Option<f64> {
+        *state
+    }
+
+    fn state_from_stats(&self, stats: &ColumnStats) -> Option<Option<f64>> {
+        Some(Some(stats.min.as_f64()))
+    }
+}
+
+struct Max;
+
+impl AggregateFunction for Max {
+    type State = Option<f64>;
+
+    fn init(&self) -> Option<f64> {
+        None
+    }
+
+    fn update(&self, state: &mut Option<f64>, _ts: i64, value: f64) {
+        self.merge(state, Some(value));
+    }
+
+    fn merge(&self, state: &mut Option<f64>, other: Option<f64>) {
+        match (*state, other) {
+            (None, _) => *state = other,
+            (Some(max), Some(value)) if value > max || max.is_nan() => *state = other,
+            _ => {}
+        }
+    }
+
+    fn finalize(&self, state: &Option<f64>) -> Option<f64> {
+        *state
+    }
+
+    fn state_from_stats(&self, stats: &ColumnStats) -> Option<Option<f64>> {
+        Some(Some(stats.max.as_f64()))
+    }
+}
+
+/// The value with the earliest `ts`; of equal timestamps, the one seen
+/// first.
+struct First;
+
+impl AggregateFunction for First {
+    type State = Option<(i64, f64)>;
+
+    fn init(&self) -> Option<(i64, f64)> {
+        None
+    }
+
+    fn update(&self, state: &mut Option<(i64, f64)>, ts: i64, value: f64) {
+        self.merge(state, Some((ts, value)));
+    }
+
+    fn merge(&self, state: &mut Option<(i64, f64)>, other: Option<(i64, f64)>) {
+        match (*state, other) {
+            (None, _) => *state = other,
+            (Some((ts, _)), Some((other_ts, _))) if other_ts < ts => *state = other,
+            _ => {}
+        }
+    }
+
+    fn finalize(&self, state: &Option<(i64, f64)>) -> Option<f64> {
+        state.map(|(_, value)| value)
+    }
+}
+
+/// The value with the latest `ts`; of equal timestamps, the one seen last.
+struct Last;
+
+impl AggregateFunction for Last {
+    type State = Option<(i64, f64)>;
+
+    fn init(&self) -> Option<(i64, f64)> {
+        None
+    }
+
+    fn update(&self, state: &mut Option<(i64, f64)>, ts: i64, value: f64) {
+        self.merge(state, Some((ts, value)));
+    }
+
+    fn merge(&self, state: &mut Option<(i64, f64)>, other: Option<(i64, f64)>) {
+        match (*state, other) {
+            (None, _) => *state = other,
+            (Some((ts, _)), Some((other_ts, _))) if other_ts >= ts => *state = other,
+            _ => {}
+        }
+    }
+
+    fn finalize(&self, state: &Option<(i64, f64)>) -> Option<f64> {
+        state.map(|(_, value)| value)
+    }
+}
+
+/// Count, mean and sum of squared deviations from the mean, updated with
+/// Welford's method so the variance does not cancel out catastrophically.
+#[derive(Default)]
+struct Moments {
+    n: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Moments {
+    fn update(&mut self, value: f64) {
+        self.n += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn merge(&mut self, other: Moments) {
+        if other.n == 0 {
+            return;
+        }
+        if self.n == 0 {
+            *self = other;
+            return;
+        }
+        let n = self.n + other.n;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.n as f64 / n as f64;
+        self.m2 += other.m2 + delta * delta * (self.n as f64 * other.n as f64 / n as f64);
+        self.n = n;
+    }
+
+    /// Sample variance; `None` for fewer than two values.
+    fn variance(&self) -> Option<f64> {
+        (self.n > 1).then(|| self.m2 / (self.n - 1) as f64)
+    }
+}
+
+struct Variance;
+
+impl AggregateFunction for Variance {
+    type State = Moments;
+
+    fn init(&self) -> Moments {
+        Moments::default()
+    }
+
+    fn update(&self, state: &mut Moments, _ts: i64, value: f64) {
+        state.update(value);
+    }
+
+    fn merge(&self, state: &mut Moments, other: Moments) {
+        state.merge(other);
+    }
+
+    fn finalize(&self, state: &Moments) -> Option<f64> {
+        state.variance()
+    }
+}
+
+struct Stddev;
+
+impl AggregateFunction for Stddev {
+    type State = Moments;
+
+    fn init(&self) -> Moments {
+        Moments::default()
+    }
+
+    fn update(&self, state: &mut Moments, _ts: i64, value: f64) {
+        state.update(value);
+    }
+
+    fn merge(&self, state: &mut Moments, other: Moments) {
+        state.merge(other);
+    }
+
+    fn finalize(&self, state: &Moments) -> Option<f64> {
+        state.variance().map(f64::sqrt)
+    }
+}
@@ -1,80 +1,748 @@
-use datamodel::batch::RecordBatch;
+use std::cmp::Ordering;
 use std::fmt;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Copy)]
+use common::error::{Error, Result};
+use datamodel::batch::RecordBatch;
+use datamodel::schema::{DataType, Schema, SERIES_ID_COLUMN, TS_COLUMN, VALUE_COLUMN};
+pub use roaring::RoaringBitmap;
+use storage::bloom::BloomFilter;
+use storage::meta::ChunkMeta;
+use storage::stats::{ColumnStats, StatValue};
+
+use crate::kernels::Kernel;
+use crate::pred_parser;
+use crate::selection::SelectionVector;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Col {
     Ts,
     SeriesId,
     Value,
+    /// Any other schema column.
+    Named(String),
+}
+
+/// A literal operand. Integers and floats compare exactly with each other
+/// and with every numeric column; strings only compare with utf8 columns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lit {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
 }
 
-#[derive(Debug, Clone)]
+/// A filter over rows, with SQL null semantics: a comparison with a null,
+/// or between mismatched types, is unknown. `Not` of unknown is unknown,
+/// `And`/`Or` follow three-valued logic, and only rows that come out true
+/// pass.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Pred {
-    GtF64(Col, f64),
-    LtI64(Col, i64),
+    Cmp(Col, CmpOp, Lit),
+    /// Inclusive at both ends.
+    Between(Col, Lit, Lit),
+    In(Col, Vec<Lit>),
+    IsNull(Col),
+    PrefixStr(Col, String),
+    /// An integer column, normally `series_id`, holds one of the set's ids.
+    /// The set is shared so cloning a plan does not copy it.
+    InSeries(Col, Arc<RoaringBitmap>),
     And(Box<Pred>, Box<Pred>),
+    Or(Box<Pred>, Box<Pred>),
+    Not(Box<Pred>),
+}
+
+impl Col {
+    /// The well-known columns get their own variants.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            TS_COLUMN => Col::Ts,
+            SERIES_ID_COLUMN => Col::SeriesId,
+            VALUE_COLUMN => Col::Value,
+            name => Col::Named(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Col::Ts => TS_COLUMN,
+            Col::SeriesId => SERIES_ID_COLUMN,
+            Col::Value => VALUE_COLUMN,
+            Col::Named(name) => name,
+        }
+    }
+}
+
+impl From<i64> for Lit {
+    fn from(v: i64) -> Self {
+        Lit::Int(v)
+    }
+}
+
+impl From<i32> for Lit {
+    fn from(v: i32) -> Self {
+        Lit::Int(v as i64)
+    }
+}
+
+impl From<u32> for Lit {
+    fn from(v: u32) -> Self {
+        Lit::Int(v as i64)
+    }
+}
+
+impl From<f64> for Lit {
+    fn from(v: f64) -> Self {
+        Lit::Float(v)
+    }
+}
+
+impl From<&str> for Lit {
+    fn from(v: &str) -> Self {
+        Lit::Str(v.to_string())
+    }
+}
+
+impl From<String> for Lit {
+    fn from(v: String) -> Self {
+        Lit::Str(v)
+    }
+}
+
+impl CmpOp {
+    /// The operator that holds exactly when `self` does not, for ordered
+    /// operands.
+    pub fn negate(self) -> Self {
+        match self {
+            CmpOp::Eq => CmpOp::Ne,
+            CmpOp::Ne => CmpOp::Eq,
+            CmpOp::Lt => CmpOp::Ge,
+            CmpOp::Le => CmpOp::Gt,
+            CmpOp::Gt => CmpOp::Le,
+            CmpOp::Ge => CmpOp::Lt,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        }
+    }
+
+    /// Unordered operands (a NaN) are only ever not equal.
+    pub(crate) fn holds(self, ord: Option<Ordering>) -> bool {
+        match ord {
+            None => self == CmpOp::Ne,
+            Some(ord) => match self {
+                CmpOp::Eq => ord == Ordering::Equal,
+                CmpOp::Ne => ord != Ordering::Equal,
+                CmpOp::Lt => ord == Ordering::Less,
+                CmpOp::Le => ord != Ordering::Greater,
+                CmpOp::Gt => ord == Ordering::Greater,
+                CmpOp::Ge => ord != Ordering::Less,
+            },
+        }
+    }
 }
 
 impl Pred {
-    pub fn eval_batch(&self, batch: &RecordBatch) -> Vec<bool> {
+    pub fn cmp(col: Col, op: CmpOp, lit: impl Into<Lit>) -> Self {
+        Pred::Cmp(col, op, lit.into())
+    }
+
+    pub fn eq(col: Col, lit: impl Into<Lit>) -> Self {
+        Self::cmp(col, CmpOp::Eq, lit)
+    }
+
+    pub fn ne(col: Col, lit: impl Into<Lit>) -> Self {
+        Self::cmp(col, CmpOp::Ne, lit)
+    }
+
+    pub fn lt(col: Col, lit: impl Into<Lit>) -> Self {
+        Self::cmp(col, CmpOp::Lt, lit)
+    }
+
+    pub fn le(col: Col, lit: impl Into<Lit>) -> Self {
+        Self::cmp(col, CmpOp::Le, lit)
+    }
+
+    pub fn gt(col: Col, lit: impl Into<Lit>) -> Self {
+        Self::cmp(col, CmpOp::Gt, lit)
+    }
+
+    pub fn ge(col: Col, lit: impl Into<Lit>) -> Self {
+        Self::cmp(col, CmpOp::Ge, lit)
+    }
+
+    pub fn between(col: Col, lo: impl Into<Lit>, hi: impl Into<Lit>) -> Self {
+        Pred::Between(col, lo.into(), hi.into())
+    }
+
+    pub fn in_list<L: Into<Lit>>(col: Col, lits: impl IntoIterator<Item = L>) -> Self {
+        Pred::In(col, lits.into_iter().map(Into::into).collect())
+    }
+
+    pub fn is_null(col: Col) -> Self {
+        Pred::IsNull(col)
+    }
+
+    pub fn is_not_null(col: Col) -> Self {
+        !Pred::IsNull(col)
+    }
+
+    pub fn and(self, other: Pred) -> Self {
+        Pred::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Pred) -> Self {
+        Pred::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Parses the form `Display` produces, e.g.
+    /// `(value > 0.5) AND (host IN ('a', 'b'))`. Keywords are
+    /// case-insensitive; without parentheses `NOT` binds tightest, then
+    /// `AND`, then `OR`.
+    pub fn parse(text: &str) -> Result<Self> {
+        pred_parser::parse(text)
+    }
+
+    /// Fails if a column is missing from `schema` or compared with a
+    /// literal of the wrong kind. Such predicates still evaluate, to
+    /// unknown.
+    pub fn check(&self, schema: &Schema) -> Result<()> {
+        let dtype = |col: &Col| {
+            schema
+                .index_of(col.name())
+                .map(|idx| schema.field(idx).dtype)
+                .ok_or_else(|| Error::Unsupported(format!("unknown column: {}", col)))
+        };
+        let check_lit = |col: &Col, lit: &Lit| -> Result<()> {
+            let dtype = dtype(col)?;
+            let ok = match lit {
+                Lit::Int(_) | Lit::Float(_) => dtype != DataType::Utf8,
+                Lit::Str(_) => dtype == DataType::Utf8,
+            };
+            if ok {
+                Ok(())
+            } else {
+                Err(Error::Unsupported(format!(
+                    "cannot compare {:?} column {} with {}",
+                    dtype, col, lit
+                )))
+            }
+        };
         match self {
-            Pred::GtF64(col, threshold) => eval_gt_f64(*col, *threshold, batch),
-            Pred::LtI64(col, threshold) => eval_lt_i64(*col, *threshold, batch),
+            Pred::Cmp(col, _, lit) => check_lit(col, lit),
+            Pred::Between(col, lo, hi) => check_lit(col, lo).and(check_lit(col, hi)),
+            Pred::In(col, lits) => lits.iter().try_for_each(|lit| check_lit(col, lit)),
+            Pred::IsNull(col) => dtype(col).map(|_| ()),
+            Pred::PrefixStr(col, prefix) => check_lit(col, &Lit::Str(prefix.clone())),
+            Pred::InSeries(col, _) => match dtype(col)? {
+                DataType::I64 | DataType::U32 => Ok(()),
+                dtype => Err(Error::Unsupported(format!(
+                    "series set on {:?} column {}",
+                    dtype, col
+                ))),
+            },
+            Pred::And(left, right) | Pred::Or(left, right) => {
+                left.check(schema)?;
+                right.check(schema)
+            }
+            Pred::Not(pred) => pred.check(schema),
+        }
+    }
+
+    /// Returns false only when no row in the chunk can satisfy the predicate.
+    pub fn may_match_chunk(&self, meta: &ChunkMeta) -> bool {
+        let stats = |col: &Col| meta.col_named(col.name()).and_then(|c| c.stats.as_ref());
+        self.may_be(meta, &stats, meta.series_bloom.as_ref(), true)
+    }
+
+    /// Returns false only when no row in block `block` can satisfy the predicate.
+    pub fn may_match_block(&self, meta: &ChunkMeta, block: usize) -> bool {
+        let stats = |col: &Col| {
+            meta.col_named(col.name())
+                .and_then(|c| c.blocks.get(block))
+                .and_then(|b| b.stats.as_ref())
+        };
+        self.may_be(meta, &stats, None, true)
+    }
+
+    /// Whether some row covered by `stats` may evaluate to `want`; rows
+    /// that evaluate to unknown count for neither.
+    fn may_be<'a>(
+        &self,
+        meta: &ChunkMeta,
+        stats: &impl Fn(&Col) -> Option<&'a ColumnStats>,
+        bloom: Option<&BloomFilter>,
+        want: bool,
+    ) -> bool {
+        match self {
+            Pred::And(left, right) if want => {
+                left.may_be(meta, stats, bloom, true) && right.may_be(meta, stats, bloom, true)
+            }
             Pred::And(left, right) => {
-                let left_mask = left.eval_batch(batch);
-                let right_mask = right.eval_batch(batch);
-                left_mask
-                    .into_iter()
-                    .zip(right_mask)
-                    .map(|(l, r)| l && r)
-                    .collect()
+                left.may_be(meta, stats, bloom, false) || right.may_be(meta, stats, bloom, false)
+            }
+            Pred::Or(left, right) if want => {
+                left.may_be(meta, stats, bloom, true) || right.may_be(meta, stats, bloom, true)
+            }
+            Pred::Or(left, right) => {
+                left.may_be(meta, stats, bloom, false) && right.may_be(meta, stats, bloom, false)
             }
+            Pred::Not(pred) => pred.may_be(meta, stats, bloom, !want),
+            Pred::IsNull(col) => match stats(col) {
+                Some(s) if want => s.null_count > 0,
+                Some(s) => s.valid_count() > 0,
+                None => true,
+            },
+            _ => self.leaf_may_be(meta, stats, bloom, want),
         }
     }
+
+    fn leaf_may_be<'a>(
+        &self,
+        meta: &ChunkMeta,
+        stats: &impl Fn(&Col) -> Option<&'a ColumnStats>,
+        bloom: Option<&BloomFilter>,
+        want: bool,
+    ) -> bool {
+        let col = self.leaf_col();
+        let s = match stats(col) {
+            Some(s) if s.valid_count() == 0 => return false,
+            Some(s) => s,
+            None => return true,
+        };
+        // Utf8 blocks hold dictionary codes and their stats give the code
+        // range, so test the dictionary entries with a code in that range.
+        if let Some(dictionary) = meta
+            .col_named(col.name())
+            .and_then(|c| c.dictionary.as_ref())
+        {
+            let codes = s.min.as_i64()..=s.max.as_i64();
+            return dictionary
+                .iter()
+                .enumerate()
+                .filter(|(code, _)| codes.contains(&(*code as i64)))
+                .any(|(_, value)| self.test(Val::Str(value)) == Some(want));
+        }
+
+        let (min, max) = match (s.min, s.max) {
+            (StatValue::Int(min), StatValue::Int(max)) => (Val::Int(min), Val::Int(max)),
+            (min, max) => (Val::Float(min.as_f64()), Val::Float(max.as_f64())),
+        };
+        // Stats skip NaN, which is unequal to anything and compares false
+        // otherwise, so a float column may always hold such a row.
+        let is_float = matches!(min, Val::Float(_));
+        let range_may = |op: CmpOp, lit: &Lit| range_may_hold(op, min, max, lit);
+        // The bloom filter covers the chunk's series ids only.
+        let series_bloom = bloom.filter(|_| col.name() == SERIES_ID_COLUMN);
+        let bloom_may = |lit: &Lit| match (series_bloom, lit) {
+            (Some(bloom), Lit::Int(id)) => {
+                u32::try_from(*id).map_or(true, |id| bloom.may_contain(id))
+            }
+            _ => true,
+        };
+        match self {
+            Pred::Cmp(_, CmpOp::Eq, lit) if want => {
+                range_may(CmpOp::Eq, lit) == Some(true) && bloom_may(lit)
+            }
+            Pred::Cmp(_, op, lit) => match range_may(*op, lit) {
+                None => false,
+                Some(may) if want => may || (is_float && *op == CmpOp::Ne),
+                Some(_) if is_float => true,
+                Some(_) => range_may(op.negate(), lit) == Some(true),
+            },
+            Pred::Between(_, lo, hi) => {
+                match (range_may(CmpOp::Ge, lo), range_may(CmpOp::Le, hi)) {
+                    (Some(above), Some(below)) if want => above && below,
+                    (Some(_), Some(_)) if is_float => true,
+                    (Some(_), Some(_)) => {
+                        range_may(CmpOp::Lt, lo) == Some(true)
+                            || range_may(CmpOp::Gt, hi) == Some(true)
+                    }
+                    _ => false,
+                }
+            }
+            Pred::In(_, lits) if lits.is_empty() => !want,
+            Pred::In(_, lits) => {
+                let mut comparable = lits
+                    .iter()
+                    .filter_map(|lit| Some((lit, range_may(CmpOp::Eq, lit)?)));
+                if want {
+                    comparable.any(|(lit, may)| may && bloom_may(lit))
+                } else if is_float {
+                    comparable.next().is_some()
+                } else {
+                    let mut any = false;
+                    let all_may_differ = comparable.all(|(lit, _)| {
+                        any = true;
+                        range_may(CmpOp::Ne, lit) == Some(true)
+                    });
+                    any && all_may_differ
+                }
+            }
+            Pred::InSeries(_, set) => {
+                let (min, max) = match (min, max) {
+                    (Val::Int(min), Val::Int(max)) => (min, max),
+                    _ => return false,
+                };
+                if max < 0 || min > u32::MAX as i64 {
+                    return !want;
+                }
+                let (lo, hi) = (min.max(0) as u32, max.min(u32::MAX as i64) as u32);
+                if !want {
+                    let span = hi as u64 - lo as u64 + 1;
+                    return min < 0
+                        || max > u32::MAX as i64
+                        || set.range_cardinality(lo..=hi) < span;
+                }
+                match series_bloom {
+                    Some(bloom) => set.range(lo..=hi).any(|id| bloom.may_contain(id)),
+                    None => set.range(lo..=hi).next().is_some(),
+                }
+            }
+            // String predicates on a numeric column are unknown.
+            _ => false,
+        }
+    }
+
+    /// Names of the columns the predicate reads, each once, in order of
+    /// first use.
+    pub fn columns(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_columns(&mut names);
+        names
+    }
+
+    fn collect_columns<'p>(&'p self, names: &mut Vec<&'p str>) {
+        match self {
+            Pred::And(left, right) | Pred::Or(left, right) => {
+                left.collect_columns(names);
+                right.collect_columns(names);
+            }
+            Pred::Not(pred) => pred.collect_columns(names),
+            leaf => {
+                let name = leaf.leaf_col().name();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+
+    /// The series-set conjuncts of this predicate: the `InSeries` terms a
+    /// row must satisfy for the whole predicate to hold.
+    pub fn series_sets(&self) -> Vec<&Pred> {
+        match self {
+            Pred::InSeries(..) => vec![self],
+            Pred::And(left, right) => {
+                let mut sets = left.series_sets();
+                sets.extend(right.series_sets());
+                sets
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// True for the rows the predicate holds for; null and unknown rows
+    /// are false.
+    pub fn eval_batch(&self, batch: &RecordBatch) -> Vec<bool> {
+        let all = SelectionVector::all(batch.len());
+        self.select(batch, &all).to_mask(batch.len())
+    }
+
+    /// The rows of `selection` the predicate holds for. Each term of an
+    /// `And` only looks at the rows that passed the terms before it.
+    pub fn select(&self, batch: &RecordBatch, selection: &SelectionVector) -> SelectionVector {
+        self.select_where(batch, selection, true)
+    }
+
+    /// The rows of `selection` for which the predicate is `want`; unknown
+    /// rows are never returned.
+    fn select_where(
+        &self,
+        batch: &RecordBatch,
+        selection: &SelectionVector,
+        want: bool,
+    ) -> SelectionVector {
+        match self {
+            // All terms true, or any term false, depending on `want`:
+            // narrow for the first, widen for the second.
+            Pred::And(left, right) if want => {
+                let left = left.select_where(batch, selection, true);
+                right.select_where(batch, &left, true)
+            }
+            Pred::Or(left, right) if !want => {
+                let left = left.select_where(batch, selection, false);
+                right.select_where(batch, &left, false)
+            }
+            Pred::And(left, right) | Pred::Or(left, right) => {
+                let found = left.select_where(batch, selection, want);
+                let rest = selection.difference(&found);
+                found.union(&right.select_where(batch, &rest, want))
+            }
+            Pred::Not(pred) => pred.select_where(batch, selection, !want),
+            Pred::IsNull(col) => match (
+                batch.column_by_name(col.name()),
+                batch.validity_by_name(col.name()),
+            ) {
+                // A missing column reads as all null.
+                (None, _) if want => selection.clone(),
+                (Some(_), None) if !want => selection.clone(),
+                (None, _) | (Some(_), None) => SelectionVector::new(),
+                (Some(_), Some(validity)) => {
+                    select_rows(selection, |row| validity.get(row) != want)
+                }
+            },
+            _ => self.select_leaf(batch, selection, want),
+        }
+    }
+
+    /// Runs a comparison kernel over the whole column when every row is
+    /// selected, and over just the selected rows otherwise.
+    fn select_leaf(
+        &self,
+        batch: &RecordBatch,
+        selection: &SelectionVector,
+        want: bool,
+    ) -> SelectionVector {
+        let col = self.leaf_col();
+        let column = match batch.column_by_name(col.name()) {
+            Some(column) if !selection.is_empty() => column,
+            _ => return SelectionVector::new(),
+        };
+        let kernel = match Kernel::compile(self, column.dtype()) {
+            Some(kernel) => kernel,
+            None => return SelectionVector::new(),
+        };
+        let dense = selection.len() == column.len();
+        let results = kernel.eval(column, (!dense).then_some(selection.rows()));
+        let validity = batch.validity_by_name(col.name());
+        let rows = selection
+            .rows()
+            .iter()
+            .zip(results)
+            .filter(|&(&row, result)| {
+                result == want && validity.map_or(true, |v| v.get(row as usize))
+            })
+            .map(|(&row, _)| row)
+            .collect();
+        SelectionVector::from_sorted(rows)
+    }
+
+    fn leaf_col(&self) -> &Col {
+        match self {
+            Pred::Cmp(col, ..)
+            | Pred::Between(col, ..)
+            | Pred::In(col, _)
+            | Pred::IsNull(col)
+            | Pred::PrefixStr(col, _)
+            | Pred::InSeries(col, _) => col,
+            Pred::And(..) | Pred::Or(..) | Pred::Not(_) => unreachable!("not a leaf"),
+        }
+    }
+
+    /// A leaf predicate on one non-null value; `None` if the types do not
+    /// compare.
+    fn test(&self, v: Val<'_>) -> Option<bool> {
+        match self {
+            Pred::Cmp(_, op, lit) => Some(op.holds(compare(v, lit)?)),
+            Pred::Between(_, lo, hi) => {
+                Some(CmpOp::Ge.holds(compare(v, lo)?) && CmpOp::Le.holds(compare(v, hi)?))
+            }
+            Pred::In(_, lits) if lits.is_empty() => Some(false),
+            Pred::In(_, lits) => {
+                let mut comparable = false;
+                for lit in lits {
+                    if let Some(ord) = compare(v, lit) {
+                        if CmpOp::Eq.holds(ord) {
+                            return Some(true);
+                        }
+                        comparable = true;
+                    }
+                }
+                comparable.then_some(false)
+            }
+            Pred::PrefixStr(_, prefix) => match v {
+                Val::Str(s) => Some(s.starts_with(prefix.as_str())),
+                _ => None,
+            },
+            Pred::InSeries(_, set) => match v {
+                Val::Int(id) => Some(u32::try_from(id).is_ok_and(|id| set.contains(id))),
+                _ => None,
+            },
+            Pred::IsNull(_) => Some(false),
+            Pred::And(..) | Pred::Or(..) | Pred::Not(_) => unreachable!("not a leaf"),
+        }
+    }
+}
+
+impl std::ops::Not for Pred {
+    type Output = Pred;
+
+    fn not(self) -> Pred {
+        Pred::Not(Box::new(self))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Val<'a> {
+    Int(i64),
+    Float(f64),
+    Str(&'a str),
+}
+
+/// `None` if the kinds do not compare, `Some(None)` if unordered (NaN).
+fn compare(v: Val<'_>, lit: &Lit) -> Option<Option<Ordering>> {
+    Some(match (v, lit) {
+        (Val::Int(a), Lit::Int(b)) => Some(a.cmp(b)),
+        (Val::Int(a), Lit::Float(b)) => cmp_int_float(a, *b),
+        (Val::Float(a), Lit::Int(b)) => cmp_int_float(*b, a).map(Ordering::reverse),
+        (Val::Float(a), Lit::Float(b)) => a.partial_cmp(b),
+        (Val::Str(a), Lit::Str(b)) => Some(a.cmp(b.as_str())),
+        _ => return None,
+    })
+}
+
+/// Exact, unlike comparing `a as f64`, which rounds above 2^53.
+pub(crate) fn cmp_int_float(a: i64, b: f64) -> Option<Ordering> {
+    // 2^63 is exact in f64 and every float from there up exceeds any i64.
+    const TWO_63: f64 = 9_223_372_036_854_775_808.0;
+    if b.is_nan() {
+        return None;
+    }
+    if b >= TWO_63 {
+        return Some(Ordering::Less);
+    }
+    if b < -TWO_63 {
+        return Some(Ordering::Greater);
+    }
+    let whole = b.trunc();
+    let fraction = if b > whole {
+        Ordering::Less
+    } else if b < whole {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    };
+    Some(a.cmp(&(whole as i64)).then(fraction))
+}
+
+fn select_rows(selection: &SelectionVector, keep: impl Fn(usize) -> bool) -> SelectionVector {
+    let rows = selection
+        .rows()
+        .iter()
+        .copied()
+        .filter(|&row| keep(row as usize))
+        .collect();
+    SelectionVector::from_sorted(rows)
+}
+
+/// Whether `op lit` may hold for some value in `[min, max]`; `None` if the
+/// kinds do not compare. Unordered bounds (all NaN) may hold anything.
+fn range_may_hold(op: CmpOp, min: Val<'_>, max: Val<'_>, lit: &Lit) -> Option<bool> {
+    let (lo, hi) = match (compare(min, lit)?, compare(max, lit)?) {
+        (Some(lo), Some(hi)) => (lo, hi),
+        _ => return Some(true),
+    };
+    Some(match op {
+        CmpOp::Eq => lo != Ordering::Greater && hi != Ordering::Less,
+        CmpOp::Ne => !(lo == Ordering::Equal && hi == Ordering::Equal),
+        CmpOp::Lt => lo == Ordering::Less,
+        CmpOp::Le => lo != Ordering::Greater,
+        CmpOp::Gt => hi == Ordering::Greater,
+        CmpOp::Ge => hi != Ordering::Less,
+    })
 }
 
 impl fmt::Display for Col {
+    /// Names that are not plain identifiers, or are keywords, are written
+    /// in double quotes.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Col::Ts => write!(f, "ts"),
-            Col::SeriesId => write!(f, "series_id"),
-            Col::Value => write!(f, "value"),
+        let name = self.name();
+        if pred_parser::is_plain_ident(name) {
+            f.write_str(name)
+        } else {
+            write!(f, "\"{}\"", name.replace('"', "\"\""))
         }
     }
 }
 
-impl fmt::Display for Pred {
+impl fmt::Display for Lit {
+    /// Floats always carry a `.`, an exponent, `inf` or `NaN`, so they
+    /// parse back as floats; strings are single-quoted with `''` escapes.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Pred::GtF64(col, value) => write!(f, "{} > {}", col, value),
-            Pred::LtI64(col, value) => write!(f, "{} < {}", col, value),
-            Pred::And(left, right) => write!(f, "({}) AND ({})", left, right),
+            Lit::Int(v) => write!(f, "{}", v),
+            Lit::Float(v) => write!(f, "{:?}", v),
+            Lit::Str(v) => write!(f, "'{}'", v.replace('\'', "''")),
         }
     }
 }
 
-fn eval_gt_f64(col: Col, threshold: f64, batch: &RecordBatch) -> Vec<bool> {
-    let mut mask = Vec::with_capacity(batch.len());
-    for i in 0..batch.len() {
-        let value = match col {
-            Col::Ts => batch.ts[i] as f64,
-            Col::SeriesId => batch.series_id[i] as f64,
-            Col::Value => batch.value[i],
-        };
-        mask.push(value > threshold);
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
     }
-    mask
 }
 
-fn eval_lt_i64(col: Col, threshold: i64, batch: &RecordBatch) -> Vec<bool> {
-    let mut mask = Vec::with_capacity(batch.len());
-    for i in 0..batch.len() {
-        let value = match col {
-            Col::Ts => batch.ts[i],
-            Col::SeriesId => batch.series_id[i] as i64,
-            Col::Value => batch.value[i] as i64,
-        };
-        mask.push(value < threshold);
+impl fmt::Display for Pred {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pred::Cmp(col, op, lit) => write!(f, "{} {} {}", col, op, lit),
+            Pred::Between(col, lo, hi) => write!(f, "{} BETWEEN {} AND {}", col, lo, hi),
+            Pred::In(col, lits) => {
+                let lits: Vec<String> = lits.iter().map(|lit| lit.to_string()).collect();
+                write!(f, "{} IN ({})", col, lits.join(", "))
+            }
+            Pred::IsNull(col) => write!(f, "{} IS NULL", col),
+            Pred::PrefixStr(col, prefix) => {
+                write!(f, "starts_with({}, {})", col, Lit::Str(prefix.clone()))
+            }
+            Pred::InSeries(col, set) => write!(f, "{} IN {{{}}}", col, describe_ids(set)),
+            Pred::And(left, right) => write!(f, "({}) AND ({})", left, right),
+            Pred::Or(left, right) => write!(f, "({}) OR ({})", left, right),
+            Pred::Not(pred) => match pred.as_ref() {
+                Pred::IsNull(col) => write!(f, "{} IS NOT NULL", col),
+                pred => write!(f, "NOT ({})", pred),
+            },
+        }
+    }
+}
+
+/// Ids in order, with runs of three or more written as `first..=last`.
+fn describe_ids(set: &RoaringBitmap) -> String {
+    let mut parts = Vec::new();
+    let mut ids = set.iter().peekable();
+    while let Some(first) = ids.next() {
+        let mut last = first;
+        while let Some(next) = last.checked_add(1) {
+            if ids.next_if_eq(&next).is_none() {
+                break;
+            }
+            last = next;
+        }
+        match last - first {
+            0 => parts.push(first.to_string()),
+            1 => parts.extend([first.to_string(), last.to_string()]),
+            _ => parts.push(format!("{}..={}", first, last)),
+        }
     }
-    mask
+    parts.join(", ")
 }
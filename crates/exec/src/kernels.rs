@@ -0,0 +1,175 @@
+//! Typed comparison kernels. A leaf predicate is compiled once per batch
+//! against its column's type into a `Kernel`, which then runs as a tight
+//! loop over the column: over every row when all are selected, so the
+//! compiler can vectorize it, or over the selected rows only.
+
+use std::cmp::Ordering;
+
+use datamodel::batch::Column;
+use datamodel::schema::DataType;
+use roaring::RoaringBitmap;
+
+use crate::expr::{cmp_int_float, CmpOp, Lit, Pred};
+
+pub(crate) enum Kernel<'p> {
+    Const(bool),
+    /// An i64 or u32 column against an integer.
+    Int(CmpOp, i64),
+    Float(CmpOp, f64),
+    /// A float column against an integer that has no exact f64.
+    FloatVsInt(CmpOp, i64),
+    Str(CmpOp, &'p str),
+    Prefix(&'p str),
+    Series(&'p RoaringBitmap),
+    All(Vec<Kernel<'p>>),
+    Any(Vec<Kernel<'p>>),
+}
+
+impl<'p> Kernel<'p> {
+    /// Compiles a leaf predicate for a column of type `dtype`; `None` if
+    /// the types do not compare, which makes every row unknown.
+    pub(crate) fn compile(pred: &'p Pred, dtype: DataType) -> Option<Self> {
+        match pred {
+            Pred::Cmp(_, op, lit) => Self::cmp(*op, lit, dtype),
+            Pred::Between(_, lo, hi) => Some(Kernel::All(vec![
+                Self::cmp(CmpOp::Ge, lo, dtype)?,
+                Self::cmp(CmpOp::Le, hi, dtype)?,
+            ])),
+            Pred::In(_, lits) if lits.is_empty() => Some(Kernel::Const(false)),
+            Pred::In(_, lits) => {
+                let any: Vec<_> = lits
+                    .iter()
+                    .filter_map(|lit| Self::cmp(CmpOp::Eq, lit, dtype))
+                    .collect();
+                (!any.is_empty()).then_some(Kernel::Any(any))
+            }
+            Pred::PrefixStr(_, prefix) => {
+                (dtype == DataType::Utf8).then_some(Kernel::Prefix(prefix))
+            }
+            Pred::InSeries(_, set) => {
+                matches!(dtype, DataType::I64 | DataType::U32).then_some(Kernel::Series(set))
+            }
+            Pred::IsNull(_) | Pred::And(..) | Pred::Or(..) | Pred::Not(_) => {
+                unreachable!("not a comparison")
+            }
+        }
+    }
+
+    fn cmp(op: CmpOp, lit: &'p Lit, dtype: DataType) -> Option<Self> {
+        Some(match (dtype, lit) {
+            (DataType::I64 | DataType::U32, Lit::Int(v)) => Kernel::Int(op, *v),
+            (DataType::I64 | DataType::U32, Lit::Float(v)) => int_vs_float(op, *v),
+            (DataType::F64, Lit::Float(v)) => Kernel::Float(op, *v),
+            (DataType::F64, Lit::Int(v)) if is_exact_f64(*v) => Kernel::Float(op, *v as f64),
+            (DataType::F64, Lit::Int(v)) => Kernel::FloatVsInt(op, *v),
+            (DataType::Utf8, Lit::Str(v)) => Kernel::Str(op, v),
+            _ => return None,
+        })
+    }
+
+    /// The result for each row of `column`, or for each of `rows` if given.
+    /// Values in null slots are compared like any other; callers drop them.
+    pub(crate) fn eval(&self, column: &Column, rows: Option<&[u32]>) -> Vec<bool> {
+        match (self, column) {
+            (Kernel::Const(value), _) => vec![*value; rows.map_or(column.len(), <[u32]>::len)],
+            (Kernel::Int(op, rhs), Column::I64(values)) => {
+                cmp_rows(values, rows, *op, *rhs, |v| *v)
+            }
+            (Kernel::Int(op, rhs), Column::U32(values)) => {
+                cmp_rows(values, rows, *op, *rhs, |v| *v as i64)
+            }
+            (Kernel::Float(op, rhs), Column::F64(values)) => {
+                cmp_rows(values, rows, *op, *rhs, |v| *v)
+            }
+            (Kernel::FloatVsInt(op, rhs), Column::F64(values)) => map_rows(values, rows, |v| {
+                op.holds(cmp_int_float(*rhs, *v).map(Ordering::reverse))
+            }),
+            (Kernel::Str(op, rhs), Column::Utf8(values)) => {
+                cmp_rows(values, rows, *op, *rhs, String::as_str)
+            }
+            (Kernel::Prefix(prefix), Column::Utf8(values)) => {
+                map_rows(values, rows, |v| v.starts_with(prefix))
+            }
+            (Kernel::Series(set), Column::I64(values)) => map_rows(values, rows, |v| {
+                u32::try_from(*v).is_ok_and(|id| set.contains(id))
+            }),
+            (Kernel::Series(set), Column::U32(values)) => {
+                map_rows(values, rows, |v| set.contains(*v))
+            }
+            (Kernel::All(kernels), _) => combine(kernels, column, rows, |a, b| a && b),
+            (Kernel::Any(kernels), _) => combine(kernels, column, rows, |a, b| a || b),
+            _ => unreachable!("kernel compiled for another column type"),
+        }
+    }
+}
+
+/// An integer column against a float: rewritten as an exact integer
+/// comparison, e.g. `ts < 2.5` as `ts <= 2`.
+fn int_vs_float(op: CmpOp, rhs: f64) -> Kernel<'static> {
+    // 2^63 is exact in f64 and every float from there up exceeds any i64.
+    const TWO_63: f64 = 9_223_372_036_854_775_808.0;
+    if rhs.is_nan() {
+        Kernel::Const(op.holds(None))
+    } else if rhs >= TWO_63 {
+        Kernel::Const(op.holds(Some(Ordering::Less)))
+    } else if rhs < -TWO_63 {
+        Kernel::Const(op.holds(Some(Ordering::Greater)))
+    } else if rhs.fract() == 0.0 {
+        Kernel::Int(op, rhs as i64)
+    } else {
+        let floor = rhs.floor() as i64;
+        match op {
+            CmpOp::Eq => Kernel::Const(false),
+            CmpOp::Ne => Kernel::Const(true),
+            CmpOp::Lt | CmpOp::Le => Kernel::Int(CmpOp::Le, floor),
+            CmpOp::Gt | CmpOp::Ge => Kernel::Int(CmpOp::Gt, floor),
+        }
+    }
+}
+
+fn is_exact_f64(v: i64) -> bool {
+    let f = v as f64;
+    // `as i64` saturates, so rule out 2^63 before converting back.
+    f < 9_223_372_036_854_775_808.0 && f as i64 == v
+}
+
+/// Dispatches on `op` outside the loop so each arm is a plain comparison.
+fn cmp_rows<'v, T, K: PartialOrd>(
+    values: &'v [T],
+    rows: Option<&[u32]>,
+    op: CmpOp,
+    rhs: K,
+    key: impl Fn(&'v T) -> K,
+) -> Vec<bool> {
+    match op {
+        CmpOp::Eq => map_rows(values, rows, |v| key(v) == rhs),
+        CmpOp::Ne => map_rows(values, rows, |v| key(v) != rhs),
+        CmpOp::Lt => map_rows(values, rows, |v| key(v) < rhs),
+        CmpOp::Le => map_rows(values, rows, |v| key(v) <= rhs),
+        CmpOp::Gt => map_rows(values, rows, |v| key(v) > rhs),
+        CmpOp::Ge => map_rows(values, rows, |v| key(v) >= rhs),
+    }
+}
+
+fn map_rows<'v, T>(values: &'v [T], rows: Option<&[u32]>, f: impl Fn(&'v T) -> bool) -> Vec<bool> {
+    match rows {
+        None => values.iter().map(f).collect(),
+        Some(rows) => rows.iter().map(|&row| f(&values[row as usize])).collect(),
+    }
+}
+
+fn combine(
+    kernels: &[Kernel<'_>],
+    column: &Column,
+    rows: Option<&[u32]>,
+    f: impl Fn(bool, bool) -> bool,
+) -> Vec<bool> {
+    let (first, rest) = kernels.split_first().expect("kernel list is never empty");
+    let mut out = first.eval(column, rows);
+    for kernel in rest {
+        for (acc, value) in out.iter_mut().zip(kernel.eval(column, rows)) {
+            *acc = f(*acc, value);
+        }
+    }
+    out
+}
@@ -1,3 +1,6 @@
 pub mod expr;
 pub mod agg;
+mod kernels;
 pub mod operators;
+mod pred_parser;
+pub mod selection;
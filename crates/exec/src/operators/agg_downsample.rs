@@ -1,15 +1,35 @@
+use std::collections::HashMap;
+
 use common::{Error, Result};
-use datamodel::batch::RecordBatch;
+use datamodel::schema::{TS_COLUMN, VALUE_COLUMN};
 
-use crate::agg::{AggResult, AggRow};
+use crate::agg::{ts_and_values, window_start, AggResult, AggRow, Aggregates, WindowAcc};
 use crate::operators::Operator;
+use crate::selection::SelectedBatch;
+
+/// How `AggDownsampleOp` keeps its window state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    /// Streams over input sorted by `ts`, holding only the open window. A
+    /// window that comes back after another started is emitted again.
+    #[default]
+    Sorted,
+    /// Any input order: a run of rows in one window is merged into a hash
+    /// table of windows, which are emitted in order once input ends.
+    Unsorted,
+}
 
 pub struct AggDownsampleOp {
     child: Box<dyn Operator>,
     window: i64,
+    origin: i64,
+    mode: WindowMode,
+    aggs: Aggregates,
     current_window_start: Option<i64>,
-    current_acc: Option<AggRow>,
+    current_acc: Option<WindowAcc>,
     output: Vec<AggRow>,
+    /// Finished windows by start, in `WindowMode::Unsorted`.
+    windows: HashMap<i64, WindowAcc>,
 }
 
 impl AggDownsampleOp {
@@ -20,43 +40,80 @@ impl AggDownsampleOp {
         Ok(Self {
             child,
             window,
+            origin: 0,
+            mode: WindowMode::default(),
+            aggs: Aggregates::summary(),
             current_window_start: None,
             current_acc: None,
             output: Vec::new(),
+            windows: HashMap::new(),
         })
     }
 
+    pub fn with_mode(mut self, mode: WindowMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The aggregates each output row carries; count, sum, min and max by
+    /// default.
+    pub fn with_aggregates(mut self, aggs: Aggregates) -> Self {
+        self.aggs = aggs;
+        self
+    }
+
+    /// Aligns windows so one starts at `origin` instead of at 0.
+    pub fn with_origin(mut self, origin: i64) -> Self {
+        self.origin = origin;
+        self
+    }
+
     pub fn execute_all(&mut self) -> Result<AggResult> {
         self.reset_state();
-        while let Some(batch) = self.child.next_batch()? {
-            self.consume_batch(&batch)?;
+        while let Some(input) = self.child.next_selected()? {
+            self.consume_batch(&input)?;
         }
         self.flush_current()?;
-        let rows = std::mem::take(&mut self.output);
+        let mut rows = std::mem::take(&mut self.output);
+        if self.mode == WindowMode::Unsorted {
+            let aggs = &self.aggs;
+            rows.extend(self.windows.drain().map(|(_, acc)| aggs.finish(acc)));
+            rows.sort_by_key(|row| row.window_start);
+        }
         Ok(AggResult { rows })
     }
 
-    fn consume_batch(&mut self, batch: &RecordBatch) -> Result<()> {
-        if batch.ts.len() != batch.value.len() {
-            return Err(Error::Corrupt("ts/value length mismatch".into()));
-        }
+    /// Reads the selected rows in place; a filter below never copies them.
+    fn consume_batch(&mut self, input: &SelectedBatch) -> Result<()> {
+        let batch = input.batch();
+        let (ts_values, values) = ts_and_values(batch)?;
 
-        for (ts, value) in batch.ts.iter().zip(batch.value.iter()) {
-            let window_start = (*ts / self.window) * self.window;
+        // Null values are left out of every aggregate, like SQL's COUNT(value)
+        // and friends; a window whose values are all null emits no row.
+        let ts_validity = batch.validity_by_name(TS_COLUMN);
+        let value_validity = batch.validity_by_name(VALUE_COLUMN);
+        for row in input.rows() {
+            let (ts, value) = (&ts_values[row], &values[row]);
+            if !ts_validity.map_or(true, |v| v.get(row))
+                || !value_validity.map_or(true, |v| v.get(row))
+            {
+                continue;
+            }
+            let window_start = window_start(*ts, self.window, self.origin);
             match self.current_window_start {
                 None => {
                     self.current_window_start = Some(window_start);
-                    self.current_acc = Some(new_acc(window_start, *value));
+                    self.current_acc = Some(self.aggs.start(window_start, *ts, *value));
                 }
                 Some(current_start) => {
                     if window_start == current_start {
                         if let Some(acc) = self.current_acc.as_mut() {
-                            add_value(acc, *value)?;
+                            self.aggs.update(acc, *ts, *value)?;
                         }
                     } else {
                         self.flush_current()?;
                         self.current_window_start = Some(window_start);
-                        self.current_acc = Some(new_acc(window_start, *value));
+                        self.current_acc = Some(self.aggs.start(window_start, *ts, *value));
                     }
                 }
             }
@@ -66,7 +123,15 @@ impl AggDownsampleOp {
 
     fn flush_current(&mut self) -> Result<()> {
         if let Some(acc) = self.current_acc.take() {
-            self.output.push(acc);
+            match self.mode {
+                WindowMode::Sorted => self.output.push(self.aggs.finish(acc)),
+                WindowMode::Unsorted => match self.windows.get_mut(&acc.window_start) {
+                    Some(existing) => self.aggs.merge(existing, acc)?,
+                    None => {
+                        self.windows.insert(acc.window_start, acc);
+                    }
+                },
+            }
             self.current_window_start = None;
         }
         Ok(())
@@ -76,30 +141,6 @@ impl AggDownsampleOp {
         self.current_window_start = None;
         self.current_acc = None;
         self.output.clear();
+        self.windows.clear();
     }
 }
-
-fn new_acc(window_start: i64, value: f64) -> AggRow {
-    AggRow {
-        window_start,
-        count: 1,
-        sum: value,
-        min: value,
-        max: value,
-    }
-}
-
-fn add_value(acc: &mut AggRow, value: f64) -> Result<()> {
-    acc.count = acc
-        .count
-        .checked_add(1)
-        .ok_or_else(|| Error::Unsupported("count overflow".into()))?;
-    acc.sum += value;
-    if value < acc.min {
-        acc.min = value;
-    }
-    if value > acc.max {
-        acc.max = value;
-    }
-    Ok(())
-}
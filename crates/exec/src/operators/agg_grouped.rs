@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use common::{Error, Result};
+use datamodel::batch::Column;
+use datamodel::schema::{SERIES_ID_COLUMN, TS_COLUMN, VALUE_COLUMN};
+
+use crate::agg::{
+    ts_and_values, window_start, Aggregates, GroupKey, GroupedAggResult, GroupedAggRow, WindowAcc,
+};
+use crate::operators::Operator;
+use crate::selection::SelectedBatch;
+
+/// The column a grouped aggregate splits its input by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupBy {
+    Series,
+    /// A `Utf8` tag column, by name.
+    Tag(String),
+}
+
+/// Downsamples each group separately: one accumulator per group and
+/// window, kept in a hash table, so input needs no particular order.
+pub struct GroupedDownsampleOp {
+    child: Box<dyn Operator>,
+    window: i64,
+    origin: i64,
+    group_by: GroupBy,
+    aggs: Aggregates,
+    /// Group ids by key; `keys` maps them back.
+    series_groups: HashMap<u32, usize>,
+    tag_groups: HashMap<String, usize>,
+    keys: Vec<GroupKey>,
+    windows: HashMap<(usize, i64), WindowAcc>,
+}
+
+impl GroupedDownsampleOp {
+    pub fn new(child: Box<dyn Operator>, window: i64, group_by: GroupBy) -> Result<Self> {
+        if window <= 0 {
+            return Err(Error::Unsupported("window must be > 0".into()));
+        }
+        Ok(Self {
+            child,
+            window,
+            origin: 0,
+            group_by,
+            aggs: Aggregates::summary(),
+            series_groups: HashMap::new(),
+            tag_groups: HashMap::new(),
+            keys: Vec::new(),
+            windows: HashMap::new(),
+        })
+    }
+
+    /// The aggregates each output row carries; count, sum, min and max by
+    /// default.
+    pub fn with_aggregates(mut self, aggs: Aggregates) -> Self {
+        self.aggs = aggs;
+        self
+    }
+
+    /// Aligns windows so one starts at `origin` instead of at 0.
+    pub fn with_origin(mut self, origin: i64) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn execute_all(&mut self) -> Result<GroupedAggResult> {
+        self.reset_state();
+        while let Some(input) = self.child.next_selected()? {
+            self.consume_batch(&input)?;
+        }
+        let mut rows: Vec<GroupedAggRow> = self
+            .windows
+            .drain()
+            .map(|((group, _), acc)| GroupedAggRow {
+                key: self.keys[group].clone(),
+                agg: self.aggs.finish(acc),
+            })
+            .collect();
+        rows.sort_by(|a, b| (&a.key, a.agg.window_start).cmp(&(&b.key, b.agg.window_start)));
+        self.reset_state();
+        Ok(GroupedAggResult { rows })
+    }
+
+    /// Rows with a null `ts`, value or group key are left out, as in
+    /// `AggDownsampleOp`.
+    fn consume_batch(&mut self, input: &SelectedBatch) -> Result<()> {
+        let batch = input.batch();
+        let (ts_values, values) = ts_and_values(batch)?;
+        let key_name = match &self.group_by {
+            GroupBy::Series => SERIES_ID_COLUMN,
+            GroupBy::Tag(name) => name.as_str(),
+        };
+        let key_idx = batch
+            .schema()
+            .index_of(key_name)
+            .ok_or_else(|| Error::Unsupported(format!("unknown column: {}", key_name)))?;
+        let key_column = batch.column(key_idx);
+        match (&self.group_by, key_column) {
+            (GroupBy::Series, Column::U32(_)) | (GroupBy::Tag(_), Column::Utf8(_)) => {}
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "cannot group by {:?} column {}",
+                    key_column.dtype(),
+                    key_name
+                )))
+            }
+        }
+        if key_column.len() != batch.len() {
+            return Err(Error::Corrupt(format!(
+                "group key column {} has {} rows, batch has {}",
+                key_name,
+                key_column.len(),
+                batch.len()
+            )));
+        }
+
+        let key_validity = batch.validity(key_idx);
+        let ts_validity = batch.validity_by_name(TS_COLUMN);
+        let value_validity = batch.validity_by_name(VALUE_COLUMN);
+        for row in input.rows() {
+            if !key_validity.map_or(true, |v| v.get(row))
+                || !ts_validity.map_or(true, |v| v.get(row))
+                || !value_validity.map_or(true, |v| v.get(row))
+            {
+                continue;
+            }
+            let group = match key_column {
+                Column::U32(ids) => self.series_group(ids[row]),
+                Column::Utf8(tags) => self.tag_group(&tags[row]),
+                _ => unreachable!("key column type checked above"),
+            };
+            let window_start = window_start(ts_values[row], self.window, self.origin);
+            let (ts, value) = (ts_values[row], values[row]);
+            match self.windows.get_mut(&(group, window_start)) {
+                Some(acc) => self.aggs.update(acc, ts, value)?,
+                None => {
+                    let acc = self.aggs.start(window_start, ts, value);
+                    self.windows.insert((group, window_start), acc);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn series_group(&mut self, id: u32) -> usize {
+        let keys = &mut self.keys;
+        *self.series_groups.entry(id).or_insert_with(|| {
+            keys.push(GroupKey::Series(id));
+            keys.len() - 1
+        })
+    }
+
+    /// Looks the tag up by `&str`, so only a new group copies it.
+    fn tag_group(&mut self, tag: &str) -> usize {
+        if let Some(&group) = self.tag_groups.get(tag) {
+            return group;
+        }
+        self.keys.push(GroupKey::Tag(tag.to_string()));
+        self.tag_groups.insert(tag.to_string(), self.keys.len() - 1);
+        self.keys.len() - 1
+    }
+
+    fn reset_state(&mut self) {
+        self.series_groups.clear();
+        self.tag_groups.clear();
+        self.keys.clear();
+        self.windows.clear();
+    }
+}
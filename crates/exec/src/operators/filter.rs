@@ -5,6 +5,7 @@ use common::Result;
 use datamodel::batch::RecordBatch;
 
 use crate::expr::Pred;
+use crate::selection::SelectedBatch;
 
 use super::{OpStats, Operator};
 
@@ -30,44 +31,24 @@ impl FilterOp {
 
 impl Operator for FilterOp {
     fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
-        let batch = match self.child.next_batch()? {
-            Some(batch) => batch,
+        Ok(self.next_selected()?.map(SelectedBatch::materialize))
+    }
+
+    /// Narrows the child's selection; stacked filters never copy rows.
+    fn next_selected(&mut self) -> Result<Option<SelectedBatch>> {
+        let input = match self.child.next_selected()? {
+            Some(input) => input,
             None => return Ok(None),
         };
 
-        let mask = self.pred.eval_batch(&batch);
-        let has_ts = batch.ts.len() == batch.len();
-        let has_series = batch.series_id.len() == batch.len();
-        let has_value = batch.value.len() == batch.len();
-        let mut ts = Vec::new();
-        let mut series_id = Vec::new();
-        let mut value = Vec::new();
-
-        for (i, keep) in mask.iter().enumerate() {
-            if *keep {
-                if has_ts {
-                    ts.push(batch.ts[i]);
-                }
-                if has_series {
-                    series_id.push(batch.series_id[i]);
-                }
-                if has_value {
-                    value.push(batch.value[i]);
-                }
-            }
-        }
-
-        let filtered = RecordBatch {
-            ts,
-            series_id,
-            value,
-        };
+        let selection = self.pred.select(input.batch(), input.selection());
         let mut stats = self.stats.borrow_mut();
-        stats.input_rows += batch.len();
-        stats.output_rows += filtered.len();
+        stats.input_rows += input.len();
+        stats.output_rows += selection.len();
         stats.num_batches += 1;
 
-        Ok(Some(filtered))
+        let (batch, _) = input.into_parts();
+        Ok(Some(SelectedBatch::with_selection(batch, selection)))
     }
 
     fn explain(&self, indent: usize) -> String {
@@ -1,17 +1,33 @@
-pub mod filter;
 pub mod agg_downsample;
+pub mod agg_grouped;
+pub mod filter;
 pub mod project;
 pub mod scan;
 
+use crate::selection::SelectedBatch;
+
 #[derive(Debug, Default, Clone)]
 pub struct OpStats {
     pub input_rows: usize,
     pub output_rows: usize,
     pub num_batches: usize,
     pub bytes_read: u64,
+    /// Rows a scan dropped for its predicate, whether stats ruled them out
+    /// or they were read and failed it.
+    pub rows_pruned: usize,
+    /// Column bytes a scan did not read thanks to its predicate, counted
+    /// like `bytes_read`.
+    pub bytes_pruned: u64,
 }
 
 pub trait Operator {
     fn next_batch(&mut self) -> common::Result<Option<datamodel::batch::RecordBatch>>;
+
+    /// Like `next_batch`, but may hand back rows a filter dropped, masked
+    /// out by the selection, rather than copying the rest into a new batch.
+    fn next_selected(&mut self) -> common::Result<Option<SelectedBatch>> {
+        Ok(self.next_batch()?.map(SelectedBatch::new))
+    }
+
     fn explain(&self, indent: usize) -> String;
 }
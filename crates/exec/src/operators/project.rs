@@ -4,28 +4,21 @@ use std::rc::Rc;
 use common::Result;
 use datamodel::batch::RecordBatch;
 
+use crate::selection::SelectedBatch;
+
 use super::{OpStats, Operator};
 
 pub struct ProjectOp {
     child: Box<dyn Operator>,
-    keep_ts: bool,
-    keep_series: bool,
-    keep_value: bool,
+    cols: Vec<String>,
     stats: Rc<RefCell<OpStats>>,
 }
 
 impl ProjectOp {
-    pub fn new(
-        child: Box<dyn Operator>,
-        keep_ts: bool,
-        keep_series: bool,
-        keep_value: bool,
-    ) -> Self {
+    pub fn new(child: Box<dyn Operator>, cols: &[&str]) -> Self {
         Self {
             child,
-            keep_ts,
-            keep_series,
-            keep_value,
+            cols: cols.iter().map(|name| name.to_string()).collect(),
             stats: Rc::new(RefCell::new(OpStats::default())),
         }
     }
@@ -37,50 +30,43 @@ impl ProjectOp {
 
 impl Operator for ProjectOp {
     fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
-        let batch = match self.child.next_batch()? {
-            Some(batch) => batch,
-            None => return Ok(None),
-        };
-        let input_rows = batch.len();
+        Ok(self.next_selected()?.map(SelectedBatch::materialize))
+    }
 
-        let ts = if self.keep_ts { batch.ts } else { Vec::new() };
-        let series_id = if self.keep_series {
-            batch.series_id
-        } else {
-            Vec::new()
-        };
-        let value = if self.keep_value {
-            batch.value
-        } else {
-            Vec::new()
+    /// Projects before anything is materialized, so only the kept columns
+    /// are ever copied.
+    fn next_selected(&mut self) -> Result<Option<SelectedBatch>> {
+        let input = match self.child.next_selected()? {
+            Some(input) => input,
+            None => return Ok(None),
         };
+        let rows = input.len();
 
-        let output_len = if self.keep_ts {
-            ts.len()
-        } else if self.keep_series {
-            series_id.len()
-        } else if self.keep_value {
-            value.len()
+        let cols: Vec<&str> = self.cols.iter().map(String::as_str).collect();
+        let (batch, selection) = input.into_parts();
+        let projected = batch.project(&cols)?;
+        // A batch without columns has no rows, so there is nothing left to
+        // select.
+        let output = if projected.columns().is_empty() {
+            SelectedBatch::new(projected)
         } else {
-            0
-        };
-
-        let projected = RecordBatch {
-            ts,
-            series_id,
-            value,
+            SelectedBatch::with_selection(projected, selection)
         };
         let mut stats = self.stats.borrow_mut();
-        stats.input_rows += input_rows;
-        stats.output_rows += output_len;
+        stats.input_rows += rows;
+        stats.output_rows += output.len();
         stats.num_batches += 1;
 
-        Ok(Some(projected))
+        Ok(Some(output))
     }
 
     fn explain(&self, indent: usize) -> String {
         let pad = " ".repeat(indent);
-        let cols = describe_cols(self.keep_ts, self.keep_series, self.keep_value);
+        let cols = if self.cols.is_empty() {
+            "none".to_string()
+        } else {
+            self.cols.join(",")
+        };
         let mut out = format!("{pad}Project(cols={})", cols);
         let child = self.child.explain(indent + 2);
         out.push('\n');
@@ -88,20 +74,3 @@ impl Operator for ProjectOp {
         out
     }
 }
-
-fn describe_cols(keep_ts: bool, keep_series: bool, keep_value: bool) -> String {
-    let mut parts = Vec::new();
-    if keep_ts {
-        parts.push("ts");
-    }
-    if keep_series {
-        parts.push("series_id");
-    }
-    if keep_value {
-        parts.push("value");
-    }
-    if parts.is_empty() {
-        return "none".to_string();
-    }
-    parts.join(",")
-}
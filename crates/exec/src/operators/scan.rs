@@ -1,54 +1,52 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use common::{Error, Result};
-use datamodel::batch::RecordBatch;
-use storage::reader::{open_chunk, open_meta, ChunkFile};
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::{Schema, TS_COLUMN, VALUE_COLUMN};
+use storage::meta::ChunkMeta;
+use storage::reader::{open_chunk_with_options, ChunkFile, ReadOptions};
 
 use crate::expr::Pred;
+use crate::selection::SelectionVector;
 
 use super::{OpStats, Operator};
 
-#[derive(Debug, Clone, Copy)]
+/// Columns a scan reads, by name.
+#[derive(Debug, Clone)]
 pub struct Cols {
-    pub ts: bool,
-    pub series_id: bool,
-    pub value: bool,
+    /// `None` reads every column in the chunk schema.
+    names: Option<Vec<String>>,
 }
 
 impl Cols {
     pub fn all() -> Self {
-        Self {
-            ts: true,
-            series_id: true,
-            value: true,
-        }
+        Self { names: None }
     }
 
     pub fn ts_value() -> Self {
+        Self::names(&[TS_COLUMN, VALUE_COLUMN])
+    }
+
+    pub fn names(names: &[&str]) -> Self {
         Self {
-            ts: true,
-            series_id: false,
-            value: true,
+            names: Some(names.iter().map(|name| name.to_string()).collect()),
         }
     }
 
-    fn describe(&self) -> String {
-        let mut parts = Vec::new();
-        if self.ts {
-            parts.push("ts");
+    fn resolve(&self, schema: &Schema) -> Result<Schema> {
+        match &self.names {
+            None => Ok(schema.clone()),
+            Some(names) => {
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                schema.project(&names)
+            }
         }
-        if self.series_id {
-            parts.push("series_id");
-        }
-        if self.value {
-            parts.push("value");
-        }
-        if parts.is_empty() {
-            return "none".to_string();
-        }
-        parts.join(",")
     }
 }
 
@@ -59,50 +57,179 @@ pub struct SeqScan {
     lo: usize,
     hi: usize,
     cur: usize,
+    /// Row ranges within `[lo, hi)` still to be read, in order; blocks the
+    /// predicate rules out are left out.
+    ranges: Vec<Range<usize>>,
+    range_idx: usize,
+    blocks_skipped: usize,
     batch_rows: usize,
     skipped: bool,
     bytes_read: u64,
     pred: Option<Pred>,
-    cols: Cols,
+    /// Output schema and the chunk column backing each of its fields.
+    schema: Arc<Schema>,
+    col_ids: Vec<u16>,
+    /// The chunk columns `pred` reads, which are read first for each batch.
+    pred_schema: Arc<Schema>,
+    pred_col_ids: Vec<u16>,
+    /// Whole-chunk validity of the nullable columns the scan reads, decoded
+    /// before the first batch and sliced for each one after.
+    validity: Option<HashMap<u16, Bitmap>>,
     stats: Rc<RefCell<OpStats>>,
 }
 
 impl SeqScan {
     pub fn open(path: PathBuf, t0: i64, t1: i64, batch_rows: usize, cols: Cols) -> Result<Self> {
+        Self::open_with_options(path, t0, t1, batch_rows, cols, &ReadOptions::default())
+    }
+
+    /// With `options.mmap` set, raw columns are copied straight out of the
+    /// mapping into each batch instead of going through a read buffer. That
+    /// copy remains: batches own their columns, so nothing borrows the
+    /// mapping past the read.
+    pub fn open_with_options(
+        path: PathBuf,
+        t0: i64,
+        t1: i64,
+        batch_rows: usize,
+        cols: Cols,
+        options: &ReadOptions,
+    ) -> Result<Self> {
+        let file = open_chunk_with_options(&path, options)?;
+        Self::from_chunk(file, t0, t1, batch_rows, cols)
+    }
+
+    /// Scans an already open chunk. Clones of one `ChunkFile` share its file
+    /// handle, so several scans over the same chunk need only one open.
+    pub fn from_chunk(
+        file: ChunkFile,
+        t0: i64,
+        t1: i64,
+        batch_rows: usize,
+        cols: Cols,
+    ) -> Result<Self> {
         if batch_rows == 0 {
             return Err(Error::Unsupported("batch_rows must be > 0".into()));
         }
 
-        let meta = open_meta(&path)?;
-        let mut file = open_chunk(&path)?;
-        let mut skipped = false;
-        let (lo, hi) = if t1 <= meta.ts_min || t0 > meta.ts_max {
-            skipped = true;
+        let meta = &file.meta;
+        let schema = cols.resolve(&meta.schema)?;
+        let col_ids = schema
+            .fields()
+            .iter()
+            .map(|field| meta.schema.index_of(&field.name).unwrap() as u16)
+            .collect();
+        let skipped = outside_time_range(meta, t0, t1);
+        let (lo, hi) = if skipped {
             (0, 0)
         } else {
-            let lo = lower_bound_in_file(&mut file, t0)?;
-            let hi = lower_bound_in_file(&mut file, t1)?;
+            let lo = file.lower_bound_ts(t0)?;
+            let hi = file.lower_bound_ts(t1)?;
             (lo, hi)
         };
 
-        Ok(Self {
+        let pred_schema = Arc::new(meta.schema.project(&[])?);
+        let mut scan = Self {
             file,
             t0,
             t1,
             lo,
             hi,
             cur: lo,
+            ranges: Vec::new(),
+            range_idx: 0,
+            blocks_skipped: 0,
             batch_rows,
             skipped,
             bytes_read: 0,
             pred: None,
-            cols,
+            schema: Arc::new(schema),
+            col_ids,
+            pred_schema,
+            pred_col_ids: Vec::new(),
+            validity: None,
             stats: Rc::new(RefCell::new(OpStats::default())),
-        })
+        };
+        scan.plan_ranges();
+        Ok(scan)
     }
 
+    /// The scan returns only the rows `pred` holds for. Chunks and blocks
+    /// whose stats rule it out are not read; otherwise the columns `pred`
+    /// reads come first, and the rest of the output columns only for the
+    /// span of rows that matched.
     pub fn with_predicate(mut self, pred: Option<Pred>) -> Self {
+        let meta = &self.file.meta;
+        let names: Vec<&str> = pred.as_ref().map_or(Vec::new(), |pred| {
+            pred.columns()
+                .into_iter()
+                .filter(|name| meta.schema.index_of(name).is_some())
+                .collect()
+        });
+        self.pred_col_ids = names
+            .iter()
+            .map(|name| meta.schema.index_of(name).unwrap() as u16)
+            .collect();
+        self.pred_schema = Arc::new(
+            meta.schema
+                .project(&names)
+                .expect("predicate columns are distinct chunk columns"),
+        );
         self.pred = pred;
+        self.plan_ranges();
+        self
+    }
+
+    fn plan_ranges(&mut self) {
+        self.validity = None;
+        self.blocks_skipped = 0;
+        self.ranges.clear();
+        self.range_idx = 0;
+        self.cur = self.lo;
+        let meta = &self.file.meta;
+        // A skipped chunk has `lo == hi` unless the predicate ruled it out.
+        self.skipped = outside_time_range(meta, self.t0, self.t1)
+            || self
+                .pred
+                .as_ref()
+                .is_some_and(|pred| !pred.may_match_chunk(meta));
+        let mut pruned = 0;
+        if self.skipped || self.lo >= self.hi {
+            pruned = self.hi - self.lo;
+        } else if self.pred.is_none() || meta.blocks.is_empty() {
+            self.ranges.push(self.lo..self.hi);
+        } else {
+            let pred = self.pred.as_ref().unwrap();
+            let first = meta.block_of(self.lo);
+            let last = meta.block_of(self.hi - 1);
+            for block in first..=last {
+                let info = &meta.blocks[block];
+                let start = (info.row_offset as usize).max(self.lo);
+                let end = ((info.row_offset + info.row_count) as usize).min(self.hi);
+                if !pred.may_match_block(meta, block) {
+                    self.blocks_skipped += 1;
+                    pruned += end - start;
+                    continue;
+                }
+                match self.ranges.last_mut() {
+                    Some(range) if range.end == start => range.end = end,
+                    _ => self.ranges.push(start..end),
+                }
+            }
+            if let Some(range) = self.ranges.first() {
+                self.cur = range.start;
+            }
+        }
+
+        let width = width(&self.schema) + width(&self.pred_schema)
+            - width_of(&self.pred_schema, &self.schema);
+        let mut stats = self.stats.borrow_mut();
+        stats.rows_pruned = pruned;
+        stats.bytes_pruned = (pruned * width) as u64;
+    }
+
+    pub fn with_checksums(mut self, verify: bool) -> Self {
+        self.file.set_verify_checksums(verify);
         self
     }
 
@@ -114,6 +241,11 @@ impl SeqScan {
         self.bytes_read
     }
 
+    /// Blocks in the scanned time range that the predicate let the scan skip.
+    pub fn blocks_skipped(&self) -> usize {
+        self.blocks_skipped
+    }
+
     pub fn range(&self) -> (usize, usize) {
         (self.lo, self.hi)
     }
@@ -127,73 +259,210 @@ impl SeqScan {
     }
 }
 
-impl Operator for SeqScan {
-    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
-        if self.skipped || self.cur >= self.hi {
-            return Ok(None);
+impl SeqScan {
+    /// The next rows to read, at most `batch_rows` of them.
+    fn next_slice(&mut self) -> Option<Range<usize>> {
+        let range_end = loop {
+            match self.ranges.get(self.range_idx) {
+                None => return None,
+                Some(range) if self.cur < range.end => break range.end,
+                Some(_) => {
+                    self.range_idx += 1;
+                    if let Some(next) = self.ranges.get(self.range_idx) {
+                        self.cur = next.start;
+                    }
+                }
+            }
+        };
+        let start = self.cur;
+        self.cur = (start + self.batch_rows).min(range_end);
+        Some(start..self.cur)
+    }
+
+    fn read_columns(
+        &self,
+        col_ids: &[u16],
+        rows: Range<usize>,
+    ) -> Result<(Vec<Column>, Vec<Option<Bitmap>>)> {
+        let mut columns = Vec::with_capacity(col_ids.len());
+        let mut validity = Vec::with_capacity(col_ids.len());
+        for &col_id in col_ids {
+            columns.push(self.file.read_column(col_id, rows.start, rows.end)?);
+            validity.push(self.validity_of(col_id, rows.clone()));
+        }
+        Ok((columns, validity))
+    }
+
+    fn load_validity(&mut self) -> Result<()> {
+        if self.validity.is_some() {
+            return Ok(());
         }
+        let mut validity = HashMap::new();
+        for &col_id in self.col_ids.iter().chain(&self.pred_col_ids) {
+            if validity.contains_key(&col_id) {
+                continue;
+            }
+            if let Some(bitmap) = self.file.read_chunk_validity(col_id)? {
+                validity.insert(col_id, bitmap);
+            }
+        }
+        self.validity = Some(validity);
+        Ok(())
+    }
 
-        let end = (self.cur + self.batch_rows).min(self.hi);
-        let mut bytes = 0u64;
-        let ts = if self.cols.ts {
-            bytes += (end - self.cur) as u64 * 8;
-            self.file.read_range_i64(0, self.cur, end)?
-        } else {
-            Vec::new()
-        };
-        let series_id = if self.cols.series_id {
-            bytes += (end - self.cur) as u64 * 4;
-            self.file.read_range_u32(1, self.cur, end)?
-        } else {
-            Vec::new()
-        };
-        let value = if self.cols.value {
-            bytes += (end - self.cur) as u64 * 8;
-            self.file.read_range_f64(2, self.cur, end)?
-        } else {
-            Vec::new()
+    /// Validity of `rows`, or `None` when none of them is null.
+    fn validity_of(&self, col_id: u16, rows: Range<usize>) -> Option<Bitmap> {
+        let bitmap = self.validity.as_ref()?.get(&col_id)?.slice(rows);
+        (bitmap.null_count() > 0).then_some(bitmap)
+    }
+
+    /// Reads the predicate columns of `rows`, then the other output columns
+    /// from the first to the last matching row, and keeps the matches.
+    /// `None` if no row matched.
+    fn read_matching(
+        &self,
+        pred: &Pred,
+        rows: Range<usize>,
+        stats: &mut OpStats,
+    ) -> Result<Option<RecordBatch>> {
+        let len = rows.len();
+        let (columns, validity) = self.read_columns(&self.pred_col_ids, rows.clone())?;
+        let pred_batch =
+            RecordBatch::try_new_with_validity(self.pred_schema.clone(), columns, validity)?;
+        let selection = pred.select(&pred_batch, &SelectionVector::all(len));
+        stats.bytes_read += (len * width(&self.pred_schema)) as u64;
+        stats.rows_pruned += len - selection.len();
+
+        let rest_width = width(&self.schema) - width_of(&self.schema, &self.pred_schema);
+        let (first, last) = match (selection.rows().first(), selection.rows().last()) {
+            (Some(&first), Some(&last)) => (first as usize, last as usize),
+            _ => {
+                stats.bytes_pruned += (len * rest_width) as u64;
+                return Ok(None);
+            }
         };
-        self.bytes_read = self.bytes_read.saturating_add(bytes);
-        self.cur = end;
+        let span = rows.start + first..rows.start + last + 1;
+        stats.bytes_read += (span.len() * rest_width) as u64;
+        stats.bytes_pruned += ((len - span.len()) * rest_width) as u64;
 
-        let batch = RecordBatch {
-            ts,
-            series_id,
-            value,
+        let keep = |column: &Column, bitmap: Option<&Bitmap>, offset: u32| {
+            if selection.len() == column.len() {
+                return (column.clone(), bitmap.cloned());
+            }
+            let rows: Vec<u32> = selection.rows().iter().map(|row| row - offset).collect();
+            (column.take(&rows), bitmap.map(|bitmap| bitmap.take(&rows)))
         };
-        let mut stats = self.stats.borrow_mut();
-        stats.output_rows += batch.len();
-        stats.num_batches += 1;
-        stats.bytes_read = stats.bytes_read.saturating_add(bytes);
+        let mut columns = Vec::with_capacity(self.col_ids.len());
+        let mut validity = Vec::with_capacity(self.col_ids.len());
+        for &col_id in &self.col_ids {
+            let (column, bitmap) = match self.pred_col_ids.iter().position(|&id| id == col_id) {
+                Some(idx) => keep(pred_batch.column(idx), pred_batch.validity(idx), 0),
+                None => {
+                    let column = self.file.read_column(col_id, span.start, span.end)?;
+                    let bitmap = self.validity_of(col_id, span.clone());
+                    keep(&column, bitmap.as_ref(), first as u32)
+                }
+            };
+            columns.push(column);
+            validity.push(bitmap);
+        }
+        Ok(Some(RecordBatch::try_new_with_validity(
+            self.schema.clone(),
+            columns,
+            validity,
+        )?))
+    }
+}
 
-        Ok(Some(batch))
+impl Operator for SeqScan {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        if self.skipped {
+            return Ok(None);
+        }
+        let stats = self.stats.clone();
+        let mut stats = stats.borrow_mut();
+        let bytes_before = stats.bytes_read;
+        let batch = loop {
+            let rows = match self.next_slice() {
+                Some(rows) => rows,
+                None => break None,
+            };
+            self.load_validity()?;
+            let batch = match &self.pred {
+                Some(pred) => self.read_matching(pred, rows, &mut stats)?,
+                None => {
+                    stats.bytes_read += (rows.len() * width(&self.schema)) as u64;
+                    let (columns, validity) = self.read_columns(&self.col_ids, rows)?;
+                    Some(RecordBatch::try_new_with_validity(
+                        self.schema.clone(),
+                        columns,
+                        validity,
+                    )?)
+                }
+            };
+            if batch.is_some() {
+                break batch;
+            }
+        };
+        self.bytes_read = self
+            .bytes_read
+            .saturating_add(stats.bytes_read - bytes_before);
+        if let Some(batch) = &batch {
+            stats.output_rows += batch.len();
+            stats.num_batches += 1;
+        }
+        Ok(batch)
     }
 
     fn explain(&self, indent: usize) -> String {
         let pad = " ".repeat(indent);
+        let pred = match &self.pred {
+            Some(pred) => format!(", pred={}", pred),
+            None => String::new(),
+        };
         format!(
-            "{pad}SeqScan(range=[{}, {}), slice_range=[{}, {}), cols={}, batch_rows={})",
+            "{pad}SeqScan(range=[{}, {}), slice_range=[{}, {}), cols={}, batch_rows={}{pred})",
             self.t0,
             self.t1,
             self.lo,
             self.hi,
-            self.cols.describe(),
+            describe_cols(&self.schema),
             self.batch_rows
         )
     }
 }
 
-fn lower_bound_in_file(chunk: &mut ChunkFile, target: i64) -> Result<usize> {
-    let mut left = 0;
-    let mut right = chunk.meta.row_count as usize;
-    while left < right {
-        let mid = left + (right - left) / 2;
-        let ts_mid = chunk.read_ts_at(mid)?;
-        if ts_mid < target {
-            left = mid + 1;
-        } else {
-            right = mid;
-        }
+fn outside_time_range(meta: &ChunkMeta, t0: i64, t1: i64) -> bool {
+    t1 <= meta.ts_min || t0 > meta.ts_max
+}
+
+/// Bytes per row of the columns in `schema`.
+fn width(schema: &Schema) -> usize {
+    schema
+        .fields()
+        .iter()
+        .map(|field| field.dtype.width())
+        .sum()
+}
+
+/// Bytes per row of the columns in `schema` that `other` also has.
+fn width_of(schema: &Schema, other: &Schema) -> usize {
+    schema
+        .fields()
+        .iter()
+        .filter(|field| other.index_of(&field.name).is_some())
+        .map(|field| field.dtype.width())
+        .sum()
+}
+
+fn describe_cols(schema: &Schema) -> String {
+    if schema.is_empty() {
+        return "none".to_string();
     }
-    Ok(left)
+    let names: Vec<&str> = schema
+        .fields()
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect();
+    names.join(",")
 }
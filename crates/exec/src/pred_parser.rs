@@ -0,0 +1,427 @@
+use std::sync::Arc;
+
+use common::error::{Error, Result};
+use roaring::RoaringBitmap;
+
+use crate::expr::{CmpOp, Col, Lit, Pred};
+
+const KEYWORDS: [&str; 7] = ["AND", "OR", "NOT", "IN", "BETWEEN", "IS", "NULL"];
+
+/// Whether `name` can be written as a column without quotes.
+pub(crate) fn is_plain_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first_ok = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    first_ok
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.iter().any(|kw| kw.eq_ignore_ascii_case(name))
+}
+
+pub(crate) fn parse(text: &str) -> Result<Pred> {
+    let tokens = lex(text)?;
+    let mut parser = Parser {
+        text,
+        tokens,
+        pos: 0,
+    };
+    let pred = parser.or()?;
+    match parser.peek() {
+        None => Ok(pred),
+        Some(_) => Err(parser.error("expected end of input")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    /// A double-quoted column name.
+    Quoted(String),
+    Str(String),
+    /// Unsigned digits, kept as text so `-` can be applied before parsing.
+    Number(String),
+    Op(CmpOp),
+    Minus,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    /// `..=` in a series set.
+    Through,
+}
+
+fn lex(text: &str) -> Result<Vec<(usize, Token)>> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        let c = bytes[pos];
+        let token = match c {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                pos += 1;
+                continue;
+            }
+            b'(' => Token::LParen,
+            b')' => Token::RParen,
+            b'{' => Token::LBrace,
+            b'}' => Token::RBrace,
+            b',' => Token::Comma,
+            b'-' => Token::Minus,
+            b'.' if text[pos..].starts_with("..=") => {
+                pos += 2;
+                Token::Through
+            }
+            b'=' if text[pos..].starts_with("==") => {
+                pos += 1;
+                Token::Op(CmpOp::Eq)
+            }
+            b'=' => Token::Op(CmpOp::Eq),
+            b'!' if text[pos..].starts_with("!=") => {
+                pos += 1;
+                Token::Op(CmpOp::Ne)
+            }
+            b'<' if text[pos..].starts_with("<>") => {
+                pos += 1;
+                Token::Op(CmpOp::Ne)
+            }
+            b'<' if text[pos..].starts_with("<=") => {
+                pos += 1;
+                Token::Op(CmpOp::Le)
+            }
+            b'<' => Token::Op(CmpOp::Lt),
+            b'>' if text[pos..].starts_with(">=") => {
+                pos += 1;
+                Token::Op(CmpOp::Ge)
+            }
+            b'>' => Token::Op(CmpOp::Gt),
+            b'\'' | b'"' => {
+                let (value, end) = lex_quoted(text, pos)?;
+                tokens.push((
+                    start,
+                    if c == b'\'' {
+                        Token::Str(value)
+                    } else {
+                        Token::Quoted(value)
+                    },
+                ));
+                pos = end;
+                continue;
+            }
+            b'0'..=b'9' => {
+                pos = lex_number(bytes, pos);
+                tokens.push((start, Token::Number(text[start..pos].to_string())));
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_')
+                {
+                    pos += 1;
+                }
+                tokens.push((start, Token::Ident(text[start..pos].to_string())));
+                continue;
+            }
+            _ => return Err(error(text, "unexpected character", start)),
+        };
+        pos += 1;
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// Reads a quoted string starting at `start`, where a doubled quote stands
+/// for one; returns it and the offset past the closing quote.
+fn lex_quoted(text: &str, start: usize) -> Result<(String, usize)> {
+    let quote = text.as_bytes()[start] as char;
+    let mut value = String::new();
+    let mut chars = text[start + 1..].char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c != quote {
+            value.push(c);
+        } else if chars.peek().is_some_and(|&(_, next)| next == quote) {
+            value.push(quote);
+            chars.next();
+        } else {
+            return Ok((value, start + 1 + idx + 1));
+        }
+    }
+    Err(error(text, "unterminated quote", start))
+}
+
+/// Digits with an optional fraction and exponent. A `.` only counts when a
+/// digit follows, so `1..=5` reads as `1`, `..=`, `5`.
+fn lex_number(bytes: &[u8], mut pos: usize) -> usize {
+    let digits = |mut pos: usize| {
+        while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        pos
+    };
+    pos = digits(pos);
+    if bytes.get(pos) == Some(&b'.') && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit) {
+        pos = digits(pos + 1);
+    }
+    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(pos + 1), Some(b'+' | b'-')));
+        if bytes.get(pos + 1 + sign).is_some_and(u8::is_ascii_digit) {
+            pos = digits(pos + 1 + sign);
+        }
+    }
+    pos
+}
+
+fn error(text: &str, msg: &str, offset: usize) -> Error {
+    Error::Unsupported(format!(
+        "bad predicate {:?}: {} at offset {}",
+        text, msg, offset
+    ))
+}
+
+struct Parser<'t> {
+    text: &'t str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        let offset = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.text.len(), |&(offset, _)| offset);
+        error(self.text, msg, offset)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.at_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", keyword)))
+        }
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<()> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", what)))
+        }
+    }
+
+    fn or(&mut self) -> Result<Pred> {
+        let mut pred = self.and()?;
+        while self.eat_keyword("OR") {
+            pred = pred.or(self.and()?);
+        }
+        Ok(pred)
+    }
+
+    fn and(&mut self) -> Result<Pred> {
+        let mut pred = self.not()?;
+        while self.eat_keyword("AND") {
+            pred = pred.and(self.not()?);
+        }
+        Ok(pred)
+    }
+
+    fn not(&mut self) -> Result<Pred> {
+        if self.eat_keyword("NOT") {
+            return Ok(!self.not()?);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Pred> {
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let pred = self.or()?;
+            self.expect(Token::RParen, "')'")?;
+            return Ok(pred);
+        }
+        let is_call = self.at_keyword("starts_with")
+            && matches!(self.tokens.get(self.pos + 1), Some((_, Token::LParen)));
+        if is_call {
+            self.pos += 2;
+            let col = self.col()?;
+            self.expect(Token::Comma, "','")?;
+            let prefix = match self.next() {
+                Some(Token::Str(prefix)) => prefix,
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("expected a string"));
+                }
+            };
+            self.expect(Token::RParen, "')'")?;
+            return Ok(Pred::PrefixStr(col, prefix));
+        }
+
+        let col = self.col()?;
+        if let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            return Ok(Pred::Cmp(col, op, self.lit()?));
+        }
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            let pred = Pred::IsNull(col);
+            return Ok(if negated { !pred } else { pred });
+        }
+        let negated = self.eat_keyword("NOT");
+        let pred = if self.eat_keyword("BETWEEN") {
+            let lo = self.lit()?;
+            self.expect_keyword("AND")?;
+            Pred::Between(col, lo, self.lit()?)
+        } else if self.eat_keyword("IN") {
+            if self.peek() == Some(&Token::LBrace) {
+                self.pos += 1;
+                Pred::InSeries(col, Arc::new(self.ids()?))
+            } else {
+                self.expect(Token::LParen, "'(' or '{'")?;
+                Pred::In(col, self.lits()?)
+            }
+        } else {
+            return Err(self.error("expected an operator"));
+        };
+        Ok(if negated { !pred } else { pred })
+    }
+
+    fn col(&mut self) -> Result<Col> {
+        match self.peek() {
+            Some(Token::Ident(name)) if is_plain_ident(name) => {
+                let col = Col::from_name(name);
+                self.pos += 1;
+                Ok(col)
+            }
+            Some(Token::Quoted(name)) => {
+                let col = Col::from_name(name);
+                self.pos += 1;
+                Ok(col)
+            }
+            _ => Err(self.error("expected a column")),
+        }
+    }
+
+    /// A comma-separated list up to `)`, which may be empty.
+    fn lits(&mut self) -> Result<Vec<Lit>> {
+        let mut lits = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+            return Ok(lits);
+        }
+        loop {
+            lits.push(self.lit()?);
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::RParen) => return Ok(lits),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("expected ',' or ')'"));
+                }
+            }
+        }
+    }
+
+    fn lit(&mut self) -> Result<Lit> {
+        let negative = self.peek() == Some(&Token::Minus);
+        if negative {
+            self.pos += 1;
+        }
+        let sign = if negative { "-" } else { "" };
+        let lit = match self.peek() {
+            Some(Token::Str(s)) if !negative => Some(Lit::Str(s.clone())),
+            Some(Token::Number(digits)) => {
+                let text = format!("{}{}", sign, digits);
+                if digits.bytes().all(|b| b.is_ascii_digit()) {
+                    text.parse().ok().map(Lit::Int)
+                } else {
+                    text.parse().ok().map(Lit::Float)
+                }
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("inf") => {
+                Some(Lit::Float(if negative {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                }))
+            }
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("nan") && !negative => {
+                Some(Lit::Float(f64::NAN))
+            }
+            _ => None,
+        };
+        match lit {
+            Some(lit) => {
+                self.pos += 1;
+                Ok(lit)
+            }
+            None => Err(self.error("expected a literal")),
+        }
+    }
+
+    /// Series ids and `first..=last` runs up to `}`.
+    fn ids(&mut self) -> Result<RoaringBitmap> {
+        let mut set = RoaringBitmap::new();
+        if self.peek() == Some(&Token::RBrace) {
+            self.pos += 1;
+            return Ok(set);
+        }
+        loop {
+            let first = self.id()?;
+            if self.peek() == Some(&Token::Through) {
+                self.pos += 1;
+                let last = self.id()?;
+                set.insert_range(first..=last);
+            } else {
+                set.insert(first);
+            }
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::RBrace) => return Ok(set),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("expected ',' or '}'"));
+                }
+            }
+        }
+    }
+
+    fn id(&mut self) -> Result<u32> {
+        let id = match self.peek() {
+            Some(Token::Number(digits)) => digits.parse().ok(),
+            _ => None,
+        };
+        match id {
+            Some(id) => {
+                self.pos += 1;
+                Ok(id)
+            }
+            None => Err(self.error("expected a series id")),
+        }
+    }
+}
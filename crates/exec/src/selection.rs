@@ -0,0 +1,171 @@
+use datamodel::batch::RecordBatch;
+
+/// The rows of a batch still in play, as ascending row indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectionVector {
+    rows: Vec<u32>,
+}
+
+impl SelectionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rows `0..len`.
+    pub fn all(len: usize) -> Self {
+        Self {
+            rows: (0..len as u32).collect(),
+        }
+    }
+
+    /// The rows whose mask entry is true.
+    pub fn from_mask(mask: &[bool]) -> Self {
+        let rows = mask
+            .iter()
+            .enumerate()
+            .filter(|(_, keep)| **keep)
+            .map(|(row, _)| row as u32)
+            .collect();
+        Self { rows }
+    }
+
+    /// Panics unless `rows` is strictly ascending.
+    pub fn from_rows(rows: Vec<u32>) -> Self {
+        assert!(
+            rows.windows(2).all(|pair| pair[0] < pair[1]),
+            "selection rows must be strictly ascending"
+        );
+        Self { rows }
+    }
+
+    /// For rows already known to be ascending, e.g. a subset of another
+    /// selection kept in order.
+    pub(crate) fn from_sorted(rows: Vec<u32>) -> Self {
+        debug_assert!(rows.windows(2).all(|pair| pair[0] < pair[1]));
+        Self { rows }
+    }
+
+    pub fn rows(&self) -> &[u32] {
+        &self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.rows.iter().map(|&row| row as usize)
+    }
+
+    /// A `len`-row mask with the selected rows set.
+    pub fn to_mask(&self, len: usize) -> Vec<bool> {
+        let mut mask = vec![false; len];
+        for row in self.iter() {
+            mask[row] = true;
+        }
+        mask
+    }
+
+    pub fn union(&self, other: &SelectionVector) -> SelectionVector {
+        let mut rows = Vec::with_capacity(self.len() + other.len());
+        let (mut a, mut b) = (self.rows.iter().peekable(), other.rows.iter().peekable());
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some(&&x), Some(&&y)) if x == y => {
+                    b.next();
+                    a.next()
+                }
+                (Some(&&x), Some(&&y)) if x < y => a.next(),
+                (Some(_), Some(_)) => b.next(),
+                (Some(_), None) => a.next(),
+                (None, Some(_)) => b.next(),
+                (None, None) => break,
+            };
+            rows.extend(next);
+        }
+        Self { rows }
+    }
+
+    /// The rows of `self` not in `other`.
+    pub fn difference(&self, other: &SelectionVector) -> SelectionVector {
+        let mut other = other.rows.iter().peekable();
+        let rows = self
+            .rows
+            .iter()
+            .copied()
+            .filter(|&row| {
+                while other.next_if(|&&o| o < row).is_some() {}
+                other.peek() != Some(&&row)
+            })
+            .collect();
+        Self { rows }
+    }
+}
+
+/// A batch with the rows that are still selected. Operators that can work
+/// on selected rows pass these along instead of copying the survivors of
+/// each filter into a new batch.
+#[derive(Debug, Clone)]
+pub struct SelectedBatch {
+    batch: RecordBatch,
+    selection: SelectionVector,
+}
+
+impl SelectedBatch {
+    /// Every row selected.
+    pub fn new(batch: RecordBatch) -> Self {
+        let selection = SelectionVector::all(batch.len());
+        Self { batch, selection }
+    }
+
+    /// Panics if the selection names a row past the end of the batch.
+    pub fn with_selection(batch: RecordBatch, selection: SelectionVector) -> Self {
+        assert!(
+            selection
+                .rows()
+                .last()
+                .map_or(true, |&row| (row as usize) < batch.len()),
+            "selection out of bounds"
+        );
+        Self { batch, selection }
+    }
+
+    pub fn batch(&self) -> &RecordBatch {
+        &self.batch
+    }
+
+    pub fn selection(&self) -> &SelectionVector {
+        &self.selection
+    }
+
+    pub fn into_parts(self) -> (RecordBatch, SelectionVector) {
+        (self.batch, self.selection)
+    }
+
+    /// The number of selected rows.
+    pub fn len(&self) -> usize {
+        self.selection.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selection.is_empty()
+    }
+
+    /// Selected row indices into `batch()`, ascending.
+    pub fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        self.selection.iter()
+    }
+
+    /// Copies out the selected rows; free when every row is selected.
+    pub fn materialize(self) -> RecordBatch {
+        if self.selection.len() == self.batch.len() {
+            self.batch
+        } else {
+            self.batch.take(self.selection.rows())
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+
+use common::error::{Error, Result};
+use datamodel::batch::RecordBatch;
+use exec::agg::{AggRegistry, AggRow, AggregateFunction};
+use exec::operators::agg_downsample::{AggDownsampleOp, WindowMode};
+use exec::operators::agg_grouped::{GroupBy, GroupedDownsampleOp};
+use exec::operators::Operator;
+use storage::stats::ColumnStats;
+
+#[test]
+fn builtins_compute_selected_aggregates() -> Result<()> {
+    // One window of 2, 4, 4, 4, 5, 5, 7, 9 with ts counting down, so
+    // `first` is the last row and `last` the first.
+    let values = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+    let ts = (0..8).rev().collect();
+    let batch = RecordBatch::time_series(ts, vec![0; 8], values);
+
+    let names = [
+        "count", "sum", "avg", "min", "max", "first", "last", "variance", "stddev",
+    ];
+    let aggs = AggRegistry::with_builtins().select(&names)?;
+    let result = AggDownsampleOp::new(source(vec![batch]), 100)?
+        .with_aggregates(aggs)
+        .execute_all()?;
+    let row = &result.rows[0];
+    assert_eq!(row.names(), names);
+    assert_eq!(row.count, 8);
+    let sample_variance: f64 = 32.0 / 7.0;
+    assert_eq!(
+        row.values,
+        vec![
+            Some(8.0),
+            Some(40.0),
+            Some(5.0),
+            Some(2.0),
+            Some(9.0),
+            Some(9.0),
+            Some(2.0),
+            Some(sample_variance),
+            Some(sample_variance.sqrt()),
+        ]
+    );
+    assert_eq!(row.get("avg"), Some(5.0));
+    assert_eq!(row.get("median"), None);
+
+    // A single value has no sample variance.
+    let one = RecordBatch::time_series(vec![0], vec![0], vec![3.0]);
+    let aggs = AggRegistry::with_builtins().select(&["variance", "avg"])?;
+    let result = AggDownsampleOp::new(source(vec![one]), 100)?
+        .with_aggregates(aggs)
+        .execute_all()?;
+    assert_eq!(result.rows[0].values, vec![None, Some(3.0)]);
+
+    let registry = AggRegistry::with_builtins();
+    assert!(matches!(
+        registry.select(&["avg", "p99"]),
+        Err(Error::Unsupported(_))
+    ));
+    assert!(matches!(
+        registry.select(&["avg", "avg"]),
+        Err(Error::Unsupported(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn merged_states_match_a_single_pass() -> Result<()> {
+    // Scrambled rows across batches, so unsorted windows merge partial
+    // states, against the same rows in order.
+    let order: Vec<i64> = (0..3000).map(|i| i * 7919 % 3000).collect();
+    let value = |ts: i64| ((ts * 37) % 101) as f64 - 50.0;
+    let batches: Vec<RecordBatch> = order
+        .chunks(64)
+        .map(|ts| {
+            let values = ts.iter().map(|&t| value(t)).collect();
+            RecordBatch::time_series(ts.to_vec(), vec![0; ts.len()], values)
+        })
+        .collect();
+    let sorted = RecordBatch::time_series(
+        (0..3000).collect(),
+        vec![0; 3000],
+        (0..3000).map(value).collect(),
+    );
+
+    let names = ["count", "avg", "min", "max", "first", "last", "variance"];
+    let aggs = AggRegistry::with_builtins().select(&names)?;
+    let expected = AggDownsampleOp::new(source(vec![sorted]), 500)?
+        .with_aggregates(aggs.clone())
+        .execute_all()?;
+    let result = AggDownsampleOp::new(source(batches), 500)?
+        .with_mode(WindowMode::Unsorted)
+        .with_aggregates(aggs)
+        .execute_all()?;
+    assert_eq!(result.rows.len(), 6);
+    for (row, want) in result.rows.iter().zip(&expected.rows) {
+        assert_eq!(row.window_start, want.window_start);
+        for name in ["count", "min", "max", "first", "last"] {
+            assert_eq!(row.get(name), want.get(name), "{}", name);
+        }
+        for name in ["avg", "variance"] {
+            let (got, want) = (row.get(name).unwrap(), want.get(name).unwrap());
+            assert!((got - want).abs() < 1e-9 * want.abs().max(1.0), "{}", name);
+        }
+    }
+    Ok(())
+}
+
+/// Max minus min, as a team would add it.
+struct Spread;
+
+impl AggregateFunction for Spread {
+    type State = Option<(f64, f64)>;
+
+    fn init(&self) -> Self::State {
+        None
+    }
+
+    fn update(&self, state: &mut Self::State, _ts: i64, value: f64) {
+        self.merge(state, Some((value, value)));
+    }
+
+    fn merge(&self, state: &mut Self::State, other: Self::State) {
+        *state = match (*state, other) {
+            (Some((lo, hi)), Some((other_lo, other_hi))) => {
+                Some((lo.min(other_lo), hi.max(other_hi)))
+            }
+            (state, other) => state.or(other),
+        };
+    }
+
+    fn finalize(&self, state: &Self::State) -> Option<f64> {
+        state.map(|(lo, hi)| hi - lo)
+    }
+}
+
+#[test]
+fn custom_aggregates_register_by_name() -> Result<()> {
+    let mut registry = AggRegistry::with_builtins();
+    registry.register("spread", Spread)?;
+    assert!(registry.contains("spread"));
+    assert!(matches!(
+        registry.register("avg", Spread),
+        Err(Error::Unsupported(_))
+    ));
+
+    let batch = RecordBatch::time_series(
+        vec![0, 1, 2, 10, 11],
+        vec![1, 2, 1, 1, 1],
+        vec![3.0, 10.0, -1.0, 6.0, 6.5],
+    );
+    let aggs = registry.select(&["spread", "count"])?;
+    let result = GroupedDownsampleOp::new(source(vec![batch]), 10, GroupBy::Series)?
+        .with_aggregates(aggs)
+        .execute_all()?;
+    let spreads: Vec<(i64, Option<f64>)> = result
+        .rows
+        .iter()
+        .map(|row| (row.agg.window_start, row.agg.get("spread")))
+        .collect();
+    assert_eq!(
+        spreads,
+        vec![(0, Some(4.0)), (10, Some(0.5)), (0, Some(0.0))]
+    );
+
+    // Stats answer count, sum, avg, min and max, but not a custom aggregate.
+    let stats = ColumnStats::from_f64(&[1.0, 2.0, 6.0]);
+    let row = registry
+        .select(&["avg", "max"])?
+        .row_from_stats(0, &stats)
+        .unwrap();
+    assert_eq!(row.values, vec![Some(3.0), Some(6.0)]);
+    assert!(registry
+        .select(&["avg", "spread"])?
+        .row_from_stats(0, &stats)
+        .is_none());
+    assert_eq!(AggRow::from_stats(0, &stats).unwrap().get("sum"), Some(9.0));
+    Ok(())
+}
+
+#[test]
+fn min_and_max_agree_with_stats_on_nan() -> Result<()> {
+    let cases = [
+        vec![f64::NAN, 1.0, 2.0],
+        vec![2.0, f64::NAN, 1.0],
+        vec![f64::NAN, f64::NAN],
+    ];
+    let same = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) => a == b || (a.is_nan() && b.is_nan()),
+        _ => a.is_none() && b.is_none(),
+    };
+    for values in cases {
+        let stats = AggRow::from_stats(0, &ColumnStats::from_f64(&values)).unwrap();
+        // One row per batch, so the window also merges across batches.
+        let batches = values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| RecordBatch::time_series(vec![i as i64], vec![0], vec![v]))
+            .collect();
+        let aggs = AggRegistry::with_builtins().select(&["min", "max"])?;
+        let result = AggDownsampleOp::new(source(batches), 100)?
+            .with_aggregates(aggs)
+            .execute_all()?;
+        let row = &result.rows[0];
+        for name in ["min", "max"] {
+            assert!(
+                same(row.get(name), stats.get(name)),
+                "{name} of {values:?}: {:?} from data, {:?} from stats",
+                row.get(name),
+                stats.get(name)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Replays batches held in memory.
+struct BatchSource {
+    batches: VecDeque<RecordBatch>,
+}
+
+impl Operator for BatchSource {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        Ok(self.batches.pop_front())
+    }
+
+    fn explain(&self, indent: usize) -> String {
+        format!("{}BatchSource", " ".repeat(indent))
+    }
+}
+
+fn source(batches: Vec<RecordBatch>) -> Box<dyn Operator> {
+    Box::new(BatchSource {
+        batches: batches.into(),
+    })
+}
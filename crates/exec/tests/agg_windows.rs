@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+
+use common::error::{Error, Result};
+use datamodel::batch::RecordBatch;
+use exec::agg::{AggResult, GroupKey};
+use exec::operators::agg_downsample::{AggDownsampleOp, WindowMode};
+use exec::operators::agg_grouped::{GroupBy, GroupedDownsampleOp};
+use exec::operators::Operator;
+
+#[test]
+fn windows_round_down_from_origin() -> Result<()> {
+    let ts = vec![-150, -101, -100, -1, 0, 99, 100];
+    let batch = RecordBatch::time_series(ts.clone(), vec![0; 7], vec![1.0; 7]);
+
+    // Truncating division would put -1 in the window starting at 0.
+    let result = AggDownsampleOp::new(source(vec![batch.clone()]), 100)?.execute_all()?;
+    assert_eq!(
+        windows(&result),
+        vec![(-200, 2), (-100, 2), (0, 2), (100, 1)]
+    );
+
+    let result = AggDownsampleOp::new(source(vec![batch.clone()]), 100)?
+        .with_origin(-1)
+        .execute_all()?;
+    assert_eq!(
+        windows(&result),
+        vec![(-201, 1), (-101, 2), (-1, 2), (99, 2)]
+    );
+    // Any origin in the same residue class aligns the same way.
+    let result = AggDownsampleOp::new(source(vec![batch.clone()]), 100)?
+        .with_origin(1_000_099)
+        .execute_all()?;
+    assert_eq!(
+        windows(&result),
+        vec![(-201, 1), (-101, 2), (-1, 2), (99, 2)]
+    );
+
+    let result = GroupedDownsampleOp::new(source(vec![batch]), 100, GroupBy::Series)?
+        .with_origin(50)
+        .execute_all()?;
+    let starts: Vec<(GroupKey, i64)> = result
+        .rows
+        .iter()
+        .map(|row| (row.key.clone(), row.agg.window_start))
+        .collect();
+    assert_eq!(
+        starts,
+        vec![
+            (GroupKey::Series(0), -150),
+            (GroupKey::Series(0), -50),
+            (GroupKey::Series(0), 50),
+        ]
+    );
+
+    let extreme = RecordBatch::time_series(vec![i64::MIN, i64::MAX], vec![0; 2], vec![1.0; 2]);
+    let result = AggDownsampleOp::new(source(vec![extreme]), 1000)?.execute_all()?;
+    assert_eq!(windows(&result)[0].0, i64::MIN);
+    assert!(windows(&result)[1].0 > i64::MAX - 1000);
+    Ok(())
+}
+
+#[test]
+fn unsorted_mode_merges_duplicate_windows() -> Result<()> {
+    // Rows 0..2000 in a scrambled order across four batches.
+    let order: Vec<i64> = (0..2000).map(|i| i * 7919 % 2000).collect();
+    let batches: Vec<RecordBatch> = order
+        .chunks(500)
+        .map(|ts| {
+            let value = ts.iter().map(|&t| (t % 10) as f64).collect();
+            RecordBatch::time_series(ts.to_vec(), vec![0; ts.len()], value)
+        })
+        .collect();
+    let sorted = RecordBatch::time_series(
+        (0..2000).collect(),
+        vec![0; 2000],
+        (0..2000).map(|t| (t % 10) as f64).collect(),
+    );
+
+    let expected = AggDownsampleOp::new(source(vec![sorted]), 100)?.execute_all()?;
+    assert_eq!(expected.rows.len(), 20);
+
+    // Streaming state fragments each window into many rows.
+    let fragmented = AggDownsampleOp::new(source(batches.clone()), 100)?.execute_all()?;
+    assert!(fragmented.rows.len() > 20);
+
+    let result = AggDownsampleOp::new(source(batches), 100)?
+        .with_mode(WindowMode::Unsorted)
+        .execute_all()?;
+    assert_eq!(result.rows.len(), expected.rows.len());
+    for (row, want) in result.rows.iter().zip(&expected.rows) {
+        assert_eq!(row.window_start, want.window_start);
+        assert_eq!(row.count, want.count);
+        // Whole-number values, so the sum is exact in any order.
+        assert_eq!(row.values, want.values);
+    }
+    Ok(())
+}
+
+#[test]
+fn missing_ts_or_value_is_an_error() -> Result<()> {
+    let batch = RecordBatch::time_series(vec![0, 1, 2], vec![0; 3], vec![1.0; 3]);
+    for cols in [&["series_id"][..], &["ts", "series_id"], &["value"]] {
+        let input = batch.clone().project(cols)?;
+        let mut op = AggDownsampleOp::new(source(vec![input.clone()]), 100)?;
+        assert!(matches!(op.execute_all(), Err(Error::Corrupt(_))));
+        if input.column_by_name("series_id").is_some() {
+            let mut op = GroupedDownsampleOp::new(source(vec![input]), 100, GroupBy::Series)?;
+            assert!(matches!(op.execute_all(), Err(Error::Corrupt(_))));
+        }
+    }
+    Ok(())
+}
+
+fn windows(result: &AggResult) -> Vec<(i64, u32)> {
+    result
+        .rows
+        .iter()
+        .map(|row| (row.window_start, row.count))
+        .collect()
+}
+
+/// Replays batches held in memory.
+struct BatchSource {
+    batches: VecDeque<RecordBatch>,
+}
+
+impl Operator for BatchSource {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        Ok(self.batches.pop_front())
+    }
+
+    fn explain(&self, indent: usize) -> String {
+        format!("{}BatchSource", " ".repeat(indent))
+    }
+}
+
+fn source(batches: Vec<RecordBatch>) -> Box<dyn Operator> {
+    Box::new(BatchSource {
+        batches: batches.into(),
+    })
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::{Error, Result};
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::{DataType, Field, Schema};
+use exec::agg::GroupKey;
+use exec::expr::{Col, Pred};
+use exec::operators::agg_grouped::{GroupBy, GroupedDownsampleOp};
+use exec::operators::filter::FilterOp;
+use exec::operators::scan::{Cols, SeqScan};
+use storage::writer::write_chunk;
+
+#[test]
+fn per_series_windows_match_reference() -> Result<()> {
+    let (dir, path) = temp_paths("series");
+    fs::create_dir_all(&dir)?;
+    let batch = make_batch(3000)?;
+    write_chunk(&path, &batch)?;
+
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 256, Cols::all())?;
+    let result = GroupedDownsampleOp::new(Box::new(scan), 100, GroupBy::Series)?.execute_all()?;
+
+    // (series_id, window_start) -> (count, sum, min, max), skipping nulls.
+    let mut expected: BTreeMap<(u32, i64), (u32, f64, f64, f64)> = BTreeMap::new();
+    for row in 0..batch.len() {
+        if !batch.is_valid(2, row) {
+            continue;
+        }
+        let value = batch.value()[row];
+        let key = (batch.series_id()[row], batch.ts()[row] / 100 * 100);
+        let acc = expected.entry(key).or_insert((0, 0.0, value, value));
+        acc.0 += 1;
+        acc.1 += value;
+        acc.2 = acc.2.min(value);
+        acc.3 = acc.3.max(value);
+    }
+
+    assert_eq!(result.rows.len(), expected.len());
+    for (row, ((series_id, window_start), (count, sum, min, max))) in
+        result.rows.iter().zip(expected)
+    {
+        assert_eq!(row.key, GroupKey::Series(series_id));
+        assert_eq!(row.agg.window_start, window_start);
+        assert_eq!(row.agg.count, count);
+        assert_eq!(row.agg.get("sum"), Some(sum));
+        assert_eq!(row.agg.get("min"), Some(min));
+        assert_eq!(row.agg.get("max"), Some(max));
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn groups_by_tag_over_selected_rows() -> Result<()> {
+    let (dir, path) = temp_paths("tag");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &make_batch(3000)?)?;
+
+    // The hosts' rows interleave, so every batch feeds both groups.
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 256, Cols::all())?;
+    let filter = FilterOp::new(Box::new(scan), Pred::lt(Col::SeriesId, 2));
+    let group_by = GroupBy::Tag("host".into());
+    let result = GroupedDownsampleOp::new(Box::new(filter), 1000, group_by)?.execute_all()?;
+    let keys: Vec<(GroupKey, i64)> = result
+        .rows
+        .iter()
+        .map(|row| (row.key.clone(), row.agg.window_start))
+        .collect();
+    let mut expected = Vec::new();
+    for host in ["a", "b"] {
+        for window_start in [0, 1000, 2000] {
+            expected.push((GroupKey::Tag(host.into()), window_start));
+        }
+    }
+    assert_eq!(keys, expected);
+    // Series 0 and 1 are hosts "a" and "b"; each has 125 rows a window,
+    // less the null values.
+    for row in &result.rows {
+        assert!(row.agg.count > 100 && row.agg.count < 125);
+    }
+
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 256, Cols::ts_value())?;
+    let mut op = GroupedDownsampleOp::new(Box::new(scan), 1000, GroupBy::Series)?;
+    assert!(matches!(op.execute_all(), Err(Error::Unsupported(_))));
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 256, Cols::all())?;
+    let mut op = GroupedDownsampleOp::new(Box::new(scan), 1000, GroupBy::Tag("ts".into()))?;
+    assert!(matches!(op.execute_all(), Err(Error::Unsupported(_))));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+/// `series_id` cycles through 0..8, `host` is "a".."d" for series id mod 4,
+/// and every seventh value is null.
+fn make_batch(len: usize) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("series_id", DataType::U32),
+        Field::new("value", DataType::F64),
+        Field::new("host", DataType::Utf8),
+    ])?;
+    let hosts = ["a", "b", "c", "d"];
+    let mut valid = Bitmap::new();
+    for i in 0..len {
+        valid.push(i % 7 != 3);
+    }
+    RecordBatch::try_new_with_validity(
+        Arc::new(schema),
+        vec![
+            Column::I64((0..len as i64).collect()),
+            Column::U32((0..len as u32).map(|i| i % 8).collect()),
+            Column::F64((0..len).map(|i| (i % 13) as f64).collect()),
+            Column::Utf8((0..len).map(|i| hosts[i % 8 % 4].to_string()).collect()),
+        ],
+        vec![None, None, Some(valid), None],
+    )
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_exec_grouped_agg_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
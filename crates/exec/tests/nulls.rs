@@ -0,0 +1,142 @@
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::{Error, Result};
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::Schema;
+use exec::expr::{Col, Pred};
+use exec::operators::agg_downsample::AggDownsampleOp;
+use exec::operators::filter::FilterOp;
+use exec::operators::scan::{Cols, SeqScan};
+use exec::operators::Operator;
+use storage::reader::open_meta;
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn predicates_skip_nulls() -> Result<()> {
+    let batch = make_batch(8)?;
+    // Rows 3 and 7 are null and hold 100.0, which would otherwise pass.
+    assert_eq!(
+        Pred::gt(Col::Value, 2.5).eval_batch(&batch),
+        vec![false, false, false, false, true, true, true, false]
+    );
+    let both = Pred::And(
+        Box::new(Pred::gt(Col::Value, 2.5)),
+        Box::new(Pred::lt(Col::Ts, 60)),
+    );
+    assert_eq!(
+        both.eval_batch(&batch),
+        vec![false, false, false, false, true, true, false, false]
+    );
+
+    let kept = batch.filter(&both.eval_batch(&batch));
+    assert_eq!(kept.len(), 2);
+    assert!(kept.validity(2).is_none());
+    Ok(())
+}
+
+#[test]
+fn scan_filter_and_downsample_skip_nulls() -> Result<()> {
+    let batch = make_batch(4000)?;
+    let (dir, path) = temp_paths("scan");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 1000,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+
+    // Block 2 only holds nulls, so no value predicate can match it.
+    let meta = open_meta(&path)?;
+    assert!(!Pred::gt(Col::Value, -1.0).may_match_block(&meta, 2));
+    assert!(Pred::gt(Col::Value, -1.0).may_match_block(&meta, 1));
+
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 256, Cols::all())?;
+    let mut filter = FilterOp::new(Box::new(scan), Pred::gt(Col::Value, -1.0));
+    let mut rows = 0;
+    while let Some(out) = filter.next_batch()? {
+        assert!(out.validity(2).is_none());
+        rows += out.len();
+    }
+    assert_eq!(rows, expected_valid(&batch, 0..4000));
+
+    let scan = SeqScan::open(path, 0, i64::MAX, 300, Cols::all())?;
+    let mut agg = AggDownsampleOp::new(Box::new(scan), 10_000)?;
+    let result = agg.execute_all()?;
+    // Each window covers 1000 rows; the third window has no valid values.
+    let starts: Vec<i64> = result.rows.iter().map(|row| row.window_start).collect();
+    assert_eq!(starts, vec![0, 10_000, 30_000]);
+    for row in &result.rows {
+        let first = row.window_start as usize / 10;
+        assert_eq!(
+            row.count as usize,
+            expected_valid(&batch, first..first + 1000)
+        );
+        assert!(row.get("min").unwrap() >= 0.0);
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn scan_decodes_validity_once() -> Result<()> {
+    let batch = make_batch(4000)?;
+    let (dir, path) = temp_paths("once");
+    fs::create_dir_all(&dir)?;
+    write_chunk_with_options(&path, &batch, &WriteOptions::default())?;
+
+    let mut scan = SeqScan::open(path.clone(), 0, i64::MAX, 256, Cols::all())?;
+    let mut nulls = scan.next_batch()?.unwrap().null_count(2);
+    // Once the scan has started, clobbering the bitmap on disk goes unseen.
+    let validity = open_meta(&path)?.col(2).unwrap().validity.unwrap();
+    let mut file = OpenOptions::new().write(true).open(&path)?;
+    file.seek(SeekFrom::Start(validity.offset))?;
+    file.write_all(&vec![0xff; validity.len as usize])?;
+    drop(file);
+    while let Some(out) = scan.next_batch()? {
+        nulls += out.null_count(2);
+    }
+    assert_eq!(nulls, 4000 - expected_valid(&batch, 0..4000));
+
+    let mut scan = SeqScan::open(path, 0, i64::MAX, 256, Cols::all())?;
+    assert!(matches!(scan.next_batch(), Err(Error::Corrupt(_))));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+/// Every fourth value is null and holds 100.0, as are rows 2000..3000.
+fn make_batch(len: usize) -> Result<RecordBatch> {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+    let mut valid = Bitmap::new();
+
+    for i in 0..len {
+        let is_valid = i % 4 != 3 && !(2000..3000).contains(&i);
+        ts.push(i as i64 * 10);
+        series_id.push(0);
+        value.push(if is_valid { i as f64 } else { 100.0 });
+        valid.push(is_valid);
+    }
+
+    RecordBatch::try_new_with_validity(
+        Arc::new(Schema::time_series()),
+        vec![Column::I64(ts), Column::U32(series_id), Column::F64(value)],
+        vec![None, None, Some(valid)],
+    )
+}
+
+fn expected_valid(batch: &RecordBatch, rows: std::ops::Range<usize>) -> usize {
+    rows.filter(|&row| batch.is_valid(2, row)).count()
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("tsdb_exec_nulls_{}_{}", tag, std::process::id()));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::{Error, Result};
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::Schema;
+use exec::expr::{Col, Lit, Pred, RoaringBitmap};
+use storage::reader::open_meta;
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn comparisons_are_exact_across_types() -> Result<()> {
+    const TWO_53: i64 = 1 << 53;
+    let batch = RecordBatch::time_series(
+        vec![0, 1, 2, 3, TWO_53 + 1],
+        vec![0, 1, 2, 3, 4],
+        vec![0.0, 1.0, 2.0, 3.0, 4.5],
+    );
+    // Truncating 2.5 to 2 would drop row 2.
+    assert_eq!(
+        Pred::lt(Col::Ts, 2.5).eval_batch(&batch),
+        vec![true, true, true, false, false]
+    );
+    // 2^53 + 1 is not representable as f64; converting it would compare equal.
+    assert_eq!(
+        Pred::eq(Col::Ts, TWO_53 as f64).eval_batch(&batch),
+        vec![false; 5]
+    );
+    assert_eq!(
+        Pred::gt(Col::Ts, TWO_53 as f64).eval_batch(&batch),
+        vec![false, false, false, false, true]
+    );
+    assert_eq!(
+        Pred::ge(Col::Value, 3).eval_batch(&batch),
+        vec![false, false, false, true, true]
+    );
+    assert_eq!(
+        Pred::gt(Col::SeriesId, -1).eval_batch(&batch),
+        vec![true; 5]
+    );
+    assert_eq!(
+        Pred::le(Col::SeriesId, f64::INFINITY).eval_batch(&batch),
+        vec![true; 5]
+    );
+    // Mismatched types are unknown, so neither the comparison nor its
+    // negation holds.
+    assert_eq!(Pred::eq(Col::Value, "3").eval_batch(&batch), vec![false; 5]);
+    assert_eq!(
+        (!Pred::eq(Col::Value, "3")).eval_batch(&batch),
+        vec![false; 5]
+    );
+
+    let schema = Schema::time_series();
+    assert!(Pred::lt(Col::Ts, 2.5).check(&schema).is_ok());
+    assert!(Pred::eq(Col::Value, "3").check(&schema).is_err());
+    assert!(Pred::is_null(Col::Named("host".into()))
+        .check(&schema)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn boolean_algebra_uses_three_valued_logic() -> Result<()> {
+    // value: 0, 1, 2, null, 4, NaN, 6, null
+    let batch = make_batch()?;
+    let gt = || Pred::gt(Col::Value, 2.5);
+
+    assert_eq!(
+        gt().eval_batch(&batch),
+        vec![false, false, false, false, true, false, true, false]
+    );
+    // NaN > 2.5 is false, so its negation holds; nulls stay unknown.
+    assert_eq!(
+        (!gt()).eval_batch(&batch),
+        vec![true, true, true, false, false, true, false, false]
+    );
+    assert_eq!(
+        Pred::ne(Col::Value, 4.0).eval_batch(&batch),
+        vec![true, true, true, false, false, true, true, false]
+    );
+    assert_eq!(
+        Pred::is_null(Col::Value).eval_batch(&batch),
+        vec![false, false, false, true, false, false, false, true]
+    );
+    assert_eq!(
+        Pred::is_not_null(Col::Value).eval_batch(&batch),
+        vec![true, true, true, false, true, true, true, false]
+    );
+
+    // Row 3 is null but `ts < 35` holds, so the `Or` does too.
+    let either = gt().or(Pred::lt(Col::Ts, 35));
+    assert_eq!(
+        either.eval_batch(&batch),
+        vec![true, true, true, true, true, false, true, false]
+    );
+    // Row 7 is unknown OR false, which stays unknown under `Not`.
+    assert_eq!(
+        (!either).eval_batch(&batch),
+        vec![false, false, false, false, false, true, false, false]
+    );
+    // Row 7 is unknown AND false, which is false, so its negation holds.
+    let both = gt().and(Pred::lt(Col::Ts, 55));
+    assert_eq!(
+        (!both).eval_batch(&batch),
+        vec![true, true, true, false, false, true, true, true]
+    );
+
+    assert_eq!(
+        Pred::between(Col::Value, 1, 4).eval_batch(&batch),
+        vec![false, true, true, false, true, false, false, false]
+    );
+    assert_eq!(
+        Pred::in_list(Col::Value, [Lit::Int(0), Lit::Float(6.0)]).eval_batch(&batch),
+        vec![true, false, false, false, false, false, true, false]
+    );
+    let none = Pred::in_list(Col::Value, Vec::<Lit>::new());
+    assert_eq!(none.eval_batch(&batch), vec![false; 8]);
+    assert_eq!(
+        (!none).eval_batch(&batch),
+        vec![true, true, true, false, true, true, true, false]
+    );
+    Ok(())
+}
+
+#[test]
+fn negations_and_disjunctions_prune_blocks() -> Result<()> {
+    // ts = 10 * row; each 1000-row block spans 10_000 of time.
+    let ts: Vec<i64> = (0..4000).map(|i| i * 10).collect();
+    let series_id = (0..4000).map(|i| i % 4).collect();
+    let value = (0..4000).map(|i| i as f64).collect();
+    let batch = RecordBatch::time_series(ts, series_id, value);
+    let (dir, path) = temp_paths("prune");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 1000,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+    let meta = open_meta(&path)?;
+    let kept =
+        |pred: Pred| -> Vec<bool> { (0..4).map(|b| pred.may_match_block(&meta, b)).collect() };
+
+    assert_eq!(
+        kept(!Pred::lt(Col::Ts, 10_000)),
+        vec![false, true, true, true]
+    );
+    assert_eq!(
+        kept(Pred::lt(Col::Ts, 5000).or(Pred::ge(Col::Ts, 35_000))),
+        vec![true, false, false, true]
+    );
+    assert_eq!(
+        kept(Pred::between(Col::Ts, 12_000, 18_000)),
+        vec![false, true, false, false]
+    );
+    assert_eq!(
+        kept(!Pred::between(Col::Ts, 10_000, 19_990)),
+        vec![true, false, true, true]
+    );
+    assert_eq!(
+        kept(Pred::in_list(Col::Ts, [-5, 25_000])),
+        vec![false, false, true, false]
+    );
+    assert_eq!(
+        kept(Pred::in_list(Col::Ts, [0, 25_000])),
+        vec![true, false, true, false]
+    );
+    // Stats skip NaN, so a negated float comparison cannot prune.
+    assert_eq!(kept(!Pred::ge(Col::Value, 0.0)), vec![true; 4]);
+
+    assert!(!Pred::gt(Col::Ts, 39_990.5).may_match_chunk(&meta));
+    assert!(Pred::gt(Col::Ts, 39_989.5).may_match_chunk(&meta));
+    assert!(!Pred::is_null(Col::Value).may_match_chunk(&meta));
+    assert!(Pred::is_not_null(Col::Value).may_match_chunk(&meta));
+    assert!(!Pred::eq(Col::Ts, "0").may_match_chunk(&meta));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn display_round_trips_through_parse() -> Result<()> {
+    let set: RoaringBitmap = [1, 3, 4, 5, 6, 9, 10].into_iter().collect();
+    let preds = vec![
+        Pred::gt(Col::Value, 0.1),
+        Pred::le(Col::Ts, i64::MIN),
+        Pred::ne(Col::Value, f64::NEG_INFINITY),
+        Pred::eq(Col::Value, 1e20),
+        Pred::eq(Col::Named("host".into()), "it's"),
+        Pred::between(Col::Ts, -5, 10),
+        Pred::in_list(Col::Named("in".into()), ["a", "b"]),
+        Pred::in_list(Col::Named("my col".into()), Vec::<Lit>::new()),
+        Pred::is_not_null(Col::Named("\"q\"".into())),
+        Pred::PrefixStr(Col::Named("host".into()), "us-".into()),
+        Pred::InSeries(Col::SeriesId, Arc::new(set)),
+        Pred::InSeries(Col::SeriesId, Arc::new((u32::MAX - 3..=u32::MAX).collect())),
+        Pred::InSeries(Col::SeriesId, Arc::new([0, u32::MAX].into_iter().collect())),
+        !(Pred::is_null(Col::Value).or(Pred::lt(Col::Ts, 5).and(!Pred::gt(Col::Value, 2.0)))),
+    ];
+    for pred in preds {
+        let text = pred.to_string();
+        assert_eq!(Pred::parse(&text)?, pred, "{}", text);
+    }
+    assert_eq!(
+        Pred::InSeries(Col::SeriesId, Arc::new((0..20).collect())).to_string(),
+        "series_id IN {0..=19}"
+    );
+    assert_eq!(
+        Pred::InSeries(Col::SeriesId, Arc::new((u32::MAX - 2..=u32::MAX).collect())).to_string(),
+        "series_id IN {4294967293..=4294967295}"
+    );
+    assert_eq!(
+        Pred::between(Col::Ts, 0, 9)
+            .or(!Pred::is_null(Col::Value))
+            .to_string(),
+        "(ts BETWEEN 0 AND 9) OR (value IS NOT NULL)"
+    );
+
+    // Keywords are case-insensitive and NOT binds tighter than AND, which
+    // binds tighter than OR.
+    let parsed = Pred::parse("value > 1 and not ts between 0 and 10 or host not in ('a')")?;
+    let expected = Pred::gt(Col::Value, 1)
+        .and(!Pred::between(Col::Ts, 0, 10))
+        .or(!Pred::in_list(Col::Named("host".into()), ["a"]));
+    assert_eq!(parsed, expected);
+    assert!(matches!(
+        Pred::parse("value = nan")?,
+        Pred::Cmp(Col::Value, _, Lit::Float(v)) if v.is_nan()
+    ));
+
+    assert!(matches!(
+        Pred::parse("value >"),
+        Err(Error::Unsupported(msg)) if msg.ends_with("at offset 7")
+    ));
+    assert!(Pred::parse("value > 1)").is_err());
+    assert!(Pred::parse("and = 1").is_err());
+    assert!(Pred::parse("host = 'open").is_err());
+    Ok(())
+}
+
+/// Row 5 holds NaN; rows 3 and 7 are null and hold 100.0.
+fn make_batch() -> Result<RecordBatch> {
+    let ts = (0..8).map(|i| i * 10).collect();
+    let mut value = Vec::new();
+    let mut valid = Bitmap::new();
+    for i in 0..8 {
+        let is_valid = i % 4 != 3;
+        value.push(match i {
+            5 => f64::NAN,
+            _ if !is_valid => 100.0,
+            _ => i as f64,
+        });
+        valid.push(is_valid);
+    }
+    RecordBatch::try_new_with_validity(
+        Arc::new(Schema::time_series()),
+        vec![Column::I64(ts), Column::U32(vec![0; 8]), Column::F64(value)],
+        vec![None, None, Some(valid)],
+    )
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_exec_pred_algebra_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
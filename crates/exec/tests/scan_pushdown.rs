@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::{DataType, Field, Schema};
+use exec::expr::{Col, Pred};
+use exec::operators::filter::FilterOp;
+use exec::operators::project::ProjectOp;
+use exec::operators::scan::{Cols, SeqScan};
+use exec::operators::Operator;
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn pushed_predicates_match_scan_then_filter() -> Result<()> {
+    let (dir, path) = temp_paths("match");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &make_batch(4000)?)?;
+
+    let preds = vec![
+        Pred::gt(Col::Value, 1500),
+        // `host` is read for the predicate but not returned.
+        Pred::eq(Col::Named("host".into()), "b").and(Pred::lt(Col::Ts, 2500)),
+        Pred::is_null(Col::Value).or(Pred::eq(Col::SeriesId, 3)),
+        !Pred::between(Col::Value, 100, 3900),
+        Pred::ge(Col::Ts, 0),
+        Pred::eq(Col::Named("missing".into()), 1),
+        Pred::is_null(Col::Named("missing".into())),
+    ];
+    for pred in preds {
+        let pushed = SeqScan::open(path.clone(), 0, 3600, 256, Cols::ts_value())?
+            .with_predicate(Some(pred.clone()));
+        let stats = pushed.stats_handle();
+        let pushed = collect(Box::new(pushed))?;
+
+        let scan = SeqScan::open(path.clone(), 0, 3600, 256, Cols::all())?;
+        let filter = FilterOp::new(Box::new(scan), pred.clone());
+        let expected = collect(Box::new(ProjectOp::new(Box::new(filter), &["ts", "value"])))?;
+
+        let rows = |batches: &[RecordBatch]| -> Vec<(i64, Option<f64>)> {
+            batches
+                .iter()
+                .flat_map(|batch| {
+                    (0..batch.len()).map(move |row| {
+                        let value = batch.is_valid(1, row).then(|| batch.value()[row]);
+                        (batch.ts()[row], value)
+                    })
+                })
+                .collect()
+        };
+        let (got, want) = (rows(&pushed), rows(&expected));
+        assert_eq!(got.len(), want.len(), "{}", pred);
+        // NaN breaks `==`, so compare bit patterns.
+        for (got, want) in got.iter().zip(&want) {
+            assert_eq!(got.0, want.0, "{}", pred);
+            assert_eq!(
+                got.1.map(f64::to_bits),
+                want.1.map(f64::to_bits),
+                "{}",
+                pred
+            );
+        }
+        assert!(pushed.iter().all(|batch| !batch.is_empty()), "{}", pred);
+        let stats = stats.borrow();
+        assert_eq!(stats.output_rows, want.len(), "{}", pred);
+        assert_eq!(stats.rows_pruned, 3600 - want.len(), "{}", pred);
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn late_materialization_reads_less() -> Result<()> {
+    let (dir, path) = temp_paths("late");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &make_batch(4000)?)?;
+    // ts, value: 16 bytes a row; series_id: 4.
+    let full = 4000 * 16;
+
+    // Matches only the last row of each batch, so the output columns are
+    // read for that row alone.
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 8, Cols::ts_value())?
+        .with_predicate(Some(Pred::eq(Col::SeriesId, 7)));
+    let stats = scan.stats_handle();
+    let out = collect(Box::new(scan))?;
+    assert_eq!(out.len(), 4000 / 8);
+    let stats = stats.borrow();
+    assert_eq!(stats.num_batches, out.len());
+    assert_eq!(stats.bytes_read, 4000 * 4 + 500 * 16);
+    assert_eq!(stats.bytes_read + stats.bytes_pruned, 4000 * 4 + full);
+
+    // Stats rule out all but the second block without reading it.
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 500, Cols::ts_value())?
+        .with_predicate(Some(Pred::between(Col::Ts, 1100, 1200)));
+    assert_eq!(scan.blocks_skipped(), 3);
+    let explain = scan.explain(0);
+    assert!(
+        explain.ends_with("pred=ts BETWEEN 1100 AND 1200)"),
+        "{}",
+        explain
+    );
+    let stats = scan.stats_handle();
+    let ts: Vec<i64> = collect(Box::new(scan))?
+        .iter()
+        .flat_map(|batch| batch.ts().to_vec())
+        .collect();
+    assert_eq!(ts, (1100..=1200).collect::<Vec<_>>());
+    let stats = stats.borrow();
+    assert_eq!(stats.rows_pruned, 4000 - 101);
+    assert!(stats.bytes_read <= 1000 * 16);
+    assert_eq!(stats.bytes_read + stats.bytes_pruned, full);
+
+    // A predicate nothing matches yields no batches at all.
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 500, Cols::ts_value())?
+        .with_predicate(Some(Pred::eq(Col::Named("host".into()), "z")));
+    let stats = scan.stats_handle();
+    assert!(collect(Box::new(scan))?.is_empty());
+    let stats = stats.borrow();
+    assert_eq!((stats.num_batches, stats.output_rows), (0, 0));
+    assert_eq!(stats.rows_pruned, 4000);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn write_chunk(path: &Path, batch: &RecordBatch) -> Result<()> {
+    let options = WriteOptions {
+        block_rows: 1000,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(path, batch, &options)
+}
+
+/// `ts` counts up, `series_id` cycles through 0..8, `value` is the row
+/// number except for a NaN every 50 rows and a null every 9, and `host`
+/// cycles through "a".."c".
+fn make_batch(len: usize) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("series_id", DataType::U32),
+        Field::new("value", DataType::F64),
+        Field::new("host", DataType::Utf8),
+    ])?;
+    let mut value = Vec::with_capacity(len);
+    let mut valid = Bitmap::new();
+    for i in 0..len {
+        value.push(if i % 50 == 0 { f64::NAN } else { i as f64 });
+        valid.push(i % 9 != 4);
+    }
+    let hosts = ["a", "b", "c"];
+    RecordBatch::try_new_with_validity(
+        Arc::new(schema),
+        vec![
+            Column::I64((0..len as i64).collect()),
+            Column::U32((0..len as u32).map(|i| i % 8).collect()),
+            Column::F64(value),
+            Column::Utf8((0..len).map(|i| hosts[i % 3].to_string()).collect()),
+        ],
+        vec![None, None, Some(valid), None],
+    )
+}
+
+fn collect(mut op: Box<dyn Operator>) -> Result<Vec<RecordBatch>> {
+    let mut out = Vec::new();
+    while let Some(batch) = op.next_batch()? {
+        out.push(batch);
+    }
+    Ok(out)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_exec_scan_pushdown_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
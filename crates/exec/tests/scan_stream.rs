@@ -1,17 +1,23 @@
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use common::config::DEFAULT_CHUNK_ROWS;
 use common::error::Result;
-use datamodel::batch::RecordBatch;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::schema::{DataType, Field, Schema};
+use exec::expr::{Col, Pred};
+use exec::operators::filter::FilterOp;
+use exec::operators::project::ProjectOp;
 use exec::operators::scan::{Cols, SeqScan};
 use exec::operators::Operator;
+use storage::reader::{open_chunk, ReadOptions};
 use storage::writer::write_chunk;
 
 #[test]
 fn stream_scan_stitches_batches() -> Result<()> {
     let batch = make_batch(DEFAULT_CHUNK_ROWS);
-    let (dir, path) = temp_paths();
+    let (dir, path) = temp_paths("stitch");
     fs::create_dir_all(&dir)?;
 
     write_chunk(&path, &batch)?;
@@ -22,9 +28,9 @@ fn stream_scan_stitches_batches() -> Result<()> {
     let mut value = Vec::new();
 
     while let Some(batch) = scan.next_batch()? {
-        ts.extend_from_slice(&batch.ts);
-        series_id.extend_from_slice(&batch.series_id);
-        value.extend_from_slice(&batch.value);
+        ts.extend_from_slice(batch.ts());
+        series_id.extend_from_slice(batch.series_id());
+        value.extend_from_slice(batch.value());
     }
 
     assert_eq!(ts.len(), 1000);
@@ -37,6 +43,106 @@ fn stream_scan_stitches_batches() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn mmap_scan_matches_file_scan() -> Result<()> {
+    let batch = make_batch(DEFAULT_CHUNK_ROWS);
+    let (dir, path) = temp_paths("mmap");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk(&path, &batch)?;
+    let options = ReadOptions {
+        mmap: true,
+        ..ReadOptions::default()
+    };
+    let mut mmap_scan =
+        SeqScan::open_with_options(path.clone(), 500, 9000, 1000, Cols::all(), &options)?;
+    let mut file_scan = SeqScan::open(path, 500, 9000, 1000, Cols::all())?;
+
+    let mut rows = 0;
+    while let Some(mapped) = mmap_scan.next_batch()? {
+        let read = file_scan.next_batch()?.unwrap();
+        assert_eq!(mapped.ts(), read.ts());
+        assert_eq!(mapped.series_id(), read.series_id());
+        assert_eq!(mapped.value(), read.value());
+        rows += mapped.len();
+    }
+    assert!(file_scan.next_batch()?.is_none());
+    assert_eq!(rows, 8500);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn scans_share_one_open_chunk() -> Result<()> {
+    let batch = make_batch(DEFAULT_CHUNK_ROWS);
+    let (dir, path) = temp_paths("shared");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk(&path, &batch)?;
+    let chunk = open_chunk(&path)?;
+    let mut early = SeqScan::from_chunk(chunk.clone(), 0, 4000, 700, Cols::all())?;
+    let mut late = SeqScan::from_chunk(chunk, 4000, 8000, 900, Cols::ts_value())?;
+
+    let mut early_ts = Vec::new();
+    let mut late_ts = Vec::new();
+    loop {
+        let a = early.next_batch()?;
+        let b = late.next_batch()?;
+        if a.is_none() && b.is_none() {
+            break;
+        }
+        if let Some(a) = a {
+            early_ts.extend_from_slice(a.ts());
+        }
+        if let Some(b) = b {
+            late_ts.extend_from_slice(b.ts());
+        }
+    }
+    assert_eq!(early_ts, batch.ts()[..4000].to_vec());
+    assert_eq!(late_ts, batch.ts()[4000..8000].to_vec());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn named_columns_scan() -> Result<()> {
+    let len = 2000;
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("host", DataType::U32),
+        Field::new("cpu", DataType::F64),
+    ])?;
+    let ts: Vec<i64> = (0..len as i64).collect();
+    let host: Vec<u32> = (0..len as u32).map(|i| i % 3).collect();
+    let cpu: Vec<f64> = (0..len).map(|i| (i % 100) as f64).collect();
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Column::I64(ts), Column::U32(host), Column::F64(cpu)],
+    )?;
+    let (dir, path) = temp_paths("named");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &batch)?;
+
+    let scan = SeqScan::open(path.clone(), 0, 1000, 256, Cols::names(&["ts", "cpu"]))?;
+    let filter = FilterOp::new(Box::new(scan), Pred::gt(Col::Named("cpu".into()), 89.5));
+    let mut project = ProjectOp::new(Box::new(filter), &["cpu"]);
+
+    let mut rows = 0;
+    while let Some(out) = project.next_batch()? {
+        assert_eq!(out.schema().len(), 1);
+        assert!(out.column(0).as_f64().unwrap().iter().all(|&v| v >= 90.0));
+        rows += out.len();
+    }
+    assert_eq!(rows, 100);
+
+    assert!(SeqScan::open(path, 0, 1000, 256, Cols::names(&["value"])).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
 fn make_batch(len: usize) -> RecordBatch {
     let mut ts = Vec::with_capacity(len);
     let mut series_id = Vec::with_capacity(len);
@@ -48,16 +154,13 @@ fn make_batch(len: usize) -> RecordBatch {
         value.push((i as f64).sin());
     }
 
-    RecordBatch {
-        ts,
-        series_id,
-        value,
-    }
+    RecordBatch::time_series(ts, series_id, value)
 }
 
-fn temp_paths() -> (PathBuf, PathBuf) {
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
     let dir = std::env::temp_dir().join(format!(
-        "tsdb_exec_scan_stream_{}_{}",
+        "tsdb_exec_scan_stream_{}_{}_{}",
+        tag,
         std::process::id(),
         0x5EEDu64
     ));
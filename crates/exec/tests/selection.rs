@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::{DataType, Field, Schema};
+use exec::expr::{Col, Pred, RoaringBitmap};
+use exec::operators::agg_downsample::AggDownsampleOp;
+use exec::operators::filter::FilterOp;
+use exec::operators::project::ProjectOp;
+use exec::operators::scan::{Cols, SeqScan};
+use exec::operators::Operator;
+use exec::selection::{SelectedBatch, SelectionVector};
+use storage::writer::write_chunk;
+
+#[test]
+fn kernels_match_row_by_row_reference() -> Result<()> {
+    let batch = make_batch(1000)?;
+    let ts = batch.ts().to_vec();
+    let series_id = batch.series_id().to_vec();
+    let value = batch.value().to_vec();
+    let host = batch
+        .column_by_name("host")
+        .unwrap()
+        .as_utf8()
+        .unwrap()
+        .to_vec();
+    let valid = |row: usize| batch.is_valid(2, row);
+    let ids: RoaringBitmap = [1, 4, 6].into_iter().collect();
+
+    type Reference<'a> = Box<dyn Fn(usize) -> bool + 'a>;
+    let cases: Vec<(Pred, Reference)> = vec![
+        (Pred::lt(Col::Ts, 2.5), Box::new(|r| ts[r] <= 2)),
+        (Pred::ge(Col::Ts, -0.5), Box::new(|_| true)),
+        (Pred::ne(Col::SeriesId, 3), Box::new(|r| series_id[r] != 3)),
+        (
+            Pred::gt(Col::SeriesId, 3.5),
+            Box::new(|r| series_id[r] >= 4),
+        ),
+        (
+            Pred::gt(Col::Value, 500),
+            Box::new(|r| valid(r) && value[r] > 500.0),
+        ),
+        // NaN is unequal to everything, but null is unknown.
+        (
+            Pred::ne(Col::Value, 10.0),
+            Box::new(|r| valid(r) && value[r] != 10.0),
+        ),
+        (
+            Pred::between(Col::Value, 100, 200.5),
+            Box::new(|r| valid(r) && (100.0..=200.5).contains(&value[r])),
+        ),
+        (
+            Pred::in_list(Col::Named("host".into()), ["b", "c"]),
+            Box::new(|r| host[r] == "b" || host[r] == "c"),
+        ),
+        (
+            Pred::PrefixStr(Col::Named("host".into()), "a".into()),
+            Box::new(|r| host[r].starts_with('a')),
+        ),
+        (
+            Pred::InSeries(Col::SeriesId, Arc::new(ids.clone())),
+            Box::new(|r| ids.contains(series_id[r])),
+        ),
+        (
+            !Pred::lt(Col::Value, 300).or(Pred::eq(Col::SeriesId, 0)),
+            Box::new(|r| series_id[r] != 0 && valid(r) && (value[r] >= 300.0 || value[r].is_nan())),
+        ),
+        (
+            Pred::is_null(Col::Value).and(Pred::le(Col::Ts, 500)),
+            Box::new(|r| !valid(r) && ts[r] <= 500),
+        ),
+    ];
+
+    // Every third row, so each leaf also runs over a sparse selection.
+    let sparse = SelectionVector::from_rows((0..1000).step_by(3).collect());
+    for (pred, reference) in &cases {
+        let expected: Vec<bool> = (0..1000).map(reference).collect();
+        assert_eq!(pred.eval_batch(&batch), expected, "{}", pred);
+        let expected: Vec<u32> = sparse
+            .rows()
+            .iter()
+            .copied()
+            .filter(|&row| expected[row as usize])
+            .collect();
+        assert_eq!(pred.select(&batch, &sparse).rows(), expected, "{}", pred);
+    }
+    Ok(())
+}
+
+#[test]
+fn selection_vectors_combine() {
+    let a = SelectionVector::from_rows(vec![1, 3, 5, 7]);
+    let b = SelectionVector::from_rows(vec![0, 3, 4, 7, 9]);
+    assert_eq!(a.union(&b).rows(), [0, 1, 3, 4, 5, 7, 9]);
+    assert_eq!(a.difference(&b).rows(), [1, 5]);
+    assert_eq!(b.difference(&a).rows(), [0, 4, 9]);
+    assert_eq!(
+        SelectionVector::from_mask(&a.to_mask(8)),
+        a,
+        "mask round trip"
+    );
+    assert!(SelectionVector::all(0).is_empty());
+}
+
+#[test]
+fn filters_pass_selections_downstream() -> Result<()> {
+    let (dir, path) = temp_paths("pipeline");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &make_batch(4000)?)?;
+
+    let pipeline = || -> Result<ProjectOp> {
+        let scan = SeqScan::open(path.clone(), 0, i64::MAX, 500, Cols::all())?;
+        let filter = FilterOp::new(Box::new(scan), Pred::ge(Col::Value, 1000));
+        let filter = FilterOp::new(Box::new(filter), Pred::ne(Col::SeriesId, 2));
+        Ok(ProjectOp::new(Box::new(filter), &["ts", "value"]))
+    };
+
+    // The stacked filters hand over the scan's batches untouched, with only
+    // the selection narrowed, and the projection copies no dropped rows.
+    let mut selected = pipeline()?;
+    let mut batches: Vec<SelectedBatch> = Vec::new();
+    while let Some(input) = selected.next_selected()? {
+        assert_eq!(input.batch().schema().len(), 2);
+        batches.push(input);
+    }
+    let scanned: usize = batches.iter().map(|input| input.batch().len()).sum();
+    let kept: usize = batches.iter().map(SelectedBatch::len).sum();
+    assert_eq!(scanned, 4000);
+    assert!(kept < 3000);
+    let mut materialized = pipeline()?;
+    for input in batches {
+        let expected = materialized.next_batch()?.unwrap();
+        let out = input.materialize();
+        assert_eq!(out.columns(), expected.columns());
+        for (row, value) in out.value().iter().enumerate() {
+            assert!(out.is_valid(1, row) && *value >= 1000.0);
+        }
+    }
+    assert!(materialized.next_batch()?.is_none());
+
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 500, Cols::all())?;
+    let filter = FilterOp::new(Box::new(scan), Pred::lt(Col::SeriesId, 2));
+    let stats = filter.stats_handle();
+    let result = AggDownsampleOp::new(Box::new(filter), 10_000)?.execute_all()?;
+    let counted: u32 = result.rows.iter().map(|row| row.count).sum();
+    let stats = stats.borrow();
+    assert_eq!(stats.input_rows, 4000);
+    assert!(stats.output_rows < 4000);
+    // Null values are left out of the aggregate.
+    assert!(counted as usize <= stats.output_rows);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn projections_may_keep_no_columns() -> Result<()> {
+    let (dir, path) = temp_paths("no_cols");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &make_batch(1000)?)?;
+
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 300, Cols::all())?;
+    let filter = FilterOp::new(Box::new(scan), Pred::lt(Col::SeriesId, 2));
+    let mut project = ProjectOp::new(Box::new(filter), &[]);
+    let stats = project.stats_handle();
+    let mut batches = 0;
+    while let Some(input) = project.next_selected()? {
+        assert!(input.batch().columns().is_empty());
+        assert!(input.is_empty());
+        batches += 1;
+    }
+    assert_eq!(batches, 4);
+    assert_eq!(stats.borrow().input_rows, 250);
+    assert_eq!(stats.borrow().output_rows, 0);
+
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 300, Cols::all())?;
+    let out = ProjectOp::new(Box::new(scan), &[]).next_batch()?.unwrap();
+    assert!(out.columns().is_empty() && out.is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+/// `ts` counts up, `series_id` cycles through 0..8, `value` is the row
+/// number except for a NaN every 50 rows and a null every 7, and `host`
+/// cycles through "a".."d".
+fn make_batch(len: usize) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("series_id", DataType::U32),
+        Field::new("value", DataType::F64),
+        Field::new("host", DataType::Utf8),
+    ])?;
+    let mut value = Vec::with_capacity(len);
+    let mut valid = Bitmap::new();
+    for i in 0..len {
+        value.push(if i % 50 == 0 { f64::NAN } else { i as f64 });
+        valid.push(i % 7 != 3);
+    }
+    let hosts = ["a", "b", "c", "d"];
+    RecordBatch::try_new_with_validity(
+        Arc::new(schema),
+        vec![
+            Column::I64((0..len as i64).collect()),
+            Column::U32((0..len as u32).map(|i| i % 8).collect()),
+            Column::F64(value),
+            Column::Utf8((0..len).map(|i| hosts[i % 4].to_string()).collect()),
+        ],
+        vec![None, None, Some(valid), None],
+    )
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_exec_selection_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
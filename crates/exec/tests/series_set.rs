@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::RecordBatch;
+use exec::expr::{Col, Pred, RoaringBitmap};
+use exec::operators::filter::FilterOp;
+use exec::operators::scan::{Cols, SeqScan};
+use exec::operators::Operator;
+use storage::reader::open_meta;
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn series_set_eval_and_display() {
+    let batch = make_batch(8);
+    let set: RoaringBitmap = [1, 3, 12].into_iter().collect();
+    let pred = Pred::InSeries(Col::SeriesId, Arc::new(set));
+    assert_eq!(
+        pred.eval_batch(&batch),
+        vec![false, true, false, true, false, false, false, false]
+    );
+    assert_eq!(pred.to_string(), "series_id IN {1, 3, 12}");
+
+    let many: RoaringBitmap = (0..20).collect();
+    assert_eq!(
+        Pred::InSeries(Col::Ts, Arc::new(many)).to_string(),
+        "ts IN {0..=19}"
+    );
+    // Floats never match.
+    let all: RoaringBitmap = (0..10).collect();
+    let on_value = Pred::InSeries(Col::Value, Arc::new(all));
+    assert_eq!(on_value.eval_batch(&batch), vec![false; 8]);
+}
+
+#[test]
+fn scan_skips_blocks_without_wanted_series() -> Result<()> {
+    let batch = make_batch(4096);
+    let (dir, path) = temp_paths("skip");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 1024,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+
+    let meta = open_meta(&path)?;
+    let set: RoaringBitmap = [12, 15].into_iter().collect();
+    let pred = Pred::InSeries(Col::SeriesId, Arc::new(set));
+    let kept: Vec<bool> = (0..4).map(|b| pred.may_match_block(&meta, b)).collect();
+    assert_eq!(kept, vec![false, true, false, false]);
+    assert!(pred.may_match_chunk(&meta));
+    let missing: RoaringBitmap = [40, 1000].into_iter().collect();
+    assert!(!Pred::InSeries(Col::SeriesId, Arc::new(missing)).may_match_chunk(&meta));
+
+    let full = SeqScan::open(path.clone(), 0, i64::MAX, 500, Cols::all())?;
+    let full_stats = full.stats_handle();
+    drain(Box::new(full))?;
+
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 500, Cols::all())?
+        .with_predicate(Some(pred.clone()));
+    assert_eq!(scan.blocks_skipped(), 3);
+    let scan_stats = scan.stats_handle();
+    let mut filter = FilterOp::new(Box::new(scan), pred.clone());
+    let mut rows = 0;
+    while let Some(out) = filter.next_batch()? {
+        assert!(out.series_id().iter().all(|&id| id == 12 || id == 15));
+        rows += out.len();
+    }
+    let expected = pred.eval_batch(&batch).iter().filter(|&&m| m).count();
+    assert_eq!(rows, expected);
+    // The scan already drops the other series of the kept block.
+    let scan_stats = scan_stats.borrow();
+    assert_eq!(scan_stats.output_rows, expected);
+    assert_eq!(scan_stats.rows_pruned, 4096 - expected);
+    assert_eq!(scan_stats.num_batches, 3);
+    assert!(scan_stats.bytes_read * 4 <= full_stats.borrow().bytes_read);
+    assert_eq!(
+        scan_stats.bytes_read + scan_stats.bytes_pruned,
+        full_stats.borrow().bytes_read
+    );
+
+    // Series-set conjuncts inside an `And` still prune; the time range
+    // cuts the kept block short.
+    let both = Pred::And(Box::new(Pred::gt(Col::Value, 0.0)), Box::new(pred.clone()));
+    let scan = SeqScan::open(path, 1500, 3000, 500, Cols::all())?.with_predicate(Some(both));
+    assert_eq!(scan.blocks_skipped(), 1);
+    let ts: Vec<i64> = collect(Box::new(scan))?
+        .iter()
+        .flat_map(|b| b.ts().to_vec())
+        .collect();
+    let expected: Vec<i64> = (1500..2048)
+        .filter(|i| i % 10 == 2 || i % 10 == 5)
+        .collect();
+    assert_eq!(ts, expected);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn bloom_filter_skips_chunks_for_point_lookups() -> Result<()> {
+    let (dir, path) = temp_paths("bloom");
+    fs::create_dir_all(&dir)?;
+    // Even series only, so odd ids fall inside the chunk's series range.
+    let ts: Vec<i64> = (0..2000).collect();
+    let series_id = (0..2000).map(|i| (i % 100) * 2).collect();
+    let batch = RecordBatch::time_series(ts, series_id, vec![0.0; 2000]);
+    write_chunk_with_options(&path, &batch, &WriteOptions::default())?;
+
+    let lookup = |id: u32| {
+        let set: RoaringBitmap = [id].into_iter().collect();
+        Some(Pred::InSeries(Col::SeriesId, Arc::new(set)))
+    };
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 512, Cols::all())?;
+    let mut scan = scan.with_predicate(lookup(51));
+    assert!(scan.skipped());
+    assert!(scan.next_batch()?.is_none());
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 512, Cols::all())?;
+    let mut scan = scan.with_predicate(lookup(52));
+    assert!(!scan.skipped());
+    assert!(scan.next_batch()?.is_some());
+
+    // Equality and IN lists on series_id consult the filter too.
+    let skipped = |pred: Pred| -> Result<bool> {
+        let scan = SeqScan::open(path.clone(), 0, i64::MAX, 512, Cols::all())?;
+        Ok(scan.with_predicate(Some(pred)).skipped())
+    };
+    assert!(skipped(Pred::eq(Col::SeriesId, 51))?);
+    assert!(!skipped(Pred::eq(Col::SeriesId, 52))?);
+    assert!(skipped(Pred::in_list(Col::SeriesId, [51, 53]))?);
+    assert!(!skipped(Pred::in_list(Col::SeriesId, [51, 52]))?);
+    assert!(!skipped(!Pred::eq(Col::SeriesId, 51))?);
+
+    let options = WriteOptions {
+        series_bloom_fpp: None,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+    let scan = SeqScan::open(path.clone(), 0, i64::MAX, 512, Cols::all())?;
+    assert!(!scan.with_predicate(lookup(51)).skipped());
+    assert!(!skipped(Pred::eq(Col::SeriesId, 51))?);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+/// Block `b` of 1024 rows holds series `10 * (b % 3)..10 * (b % 3) + 10`.
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+    for i in 0..len {
+        ts.push(i as i64);
+        series_id.push((i / 1024 % 3 * 10 + i % 10) as u32);
+        value.push(i as f64);
+    }
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn drain(mut op: Box<dyn Operator>) -> Result<()> {
+    while op.next_batch()?.is_some() {}
+    Ok(())
+}
+
+fn collect(mut op: Box<dyn Operator>) -> Result<Vec<RecordBatch>> {
+    let mut out = Vec::new();
+    while let Some(batch) = op.next_batch()? {
+        out.push(batch);
+    }
+    Ok(out)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_exec_series_set_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
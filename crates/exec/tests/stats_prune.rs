@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::PathBuf;
+
+use common::error::Result;
+use datamodel::batch::RecordBatch;
+use exec::agg::AggRow;
+use exec::expr::{Col, Pred};
+use storage::reader::open_meta;
+use storage::stats::StatValue;
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn stats_prune_chunks_and_blocks() -> Result<()> {
+    let batch = make_batch(4096);
+    let (dir, path) = temp_paths();
+    fs::create_dir_all(&dir)?;
+
+    let options = WriteOptions {
+        block_rows: 1024,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+    let meta = open_meta(&path)?;
+
+    let value = meta.col(2).unwrap().stats.unwrap();
+    assert_eq!(value.count, 4096);
+    assert_eq!(value.min, StatValue::Float(0.0));
+    assert_eq!(value.max, StatValue::Float(4095.0));
+    let series = meta.col(1).unwrap();
+    assert_eq!(series.stats.unwrap().distinct, 4);
+    assert_eq!(series.blocks[2].stats.unwrap().distinct, 1);
+
+    assert!(Pred::gt(Col::Value, 4000.0).may_match_chunk(&meta));
+    assert!(!Pred::gt(Col::Value, 4095.0).may_match_chunk(&meta));
+    assert!(!Pred::lt(Col::Ts, 0).may_match_chunk(&meta));
+
+    let gt = Pred::gt(Col::Value, 2047.5);
+    let kept: Vec<bool> = (0..4).map(|b| gt.may_match_block(&meta, b)).collect();
+    assert_eq!(kept, vec![false, false, true, true]);
+
+    let both = Pred::And(
+        Box::new(Pred::gt(Col::Value, 1023.0)),
+        Box::new(Pred::lt(Col::Ts, 2000)),
+    );
+    let kept: Vec<bool> = (0..4).map(|b| both.may_match_block(&meta, b)).collect();
+    assert_eq!(kept, vec![false, true, false, false]);
+
+    let row = AggRow::from_stats(0, &value).unwrap();
+    assert_eq!(row.count, 4096);
+    assert_eq!(row.get("sum"), Some(batch.value().iter().sum::<f64>()));
+    assert_eq!(row.get("min"), Some(0.0));
+    assert_eq!(row.get("max"), Some(4095.0));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(i as i64);
+        series_id.push(i as u32 / 1024);
+        value.push(i as f64);
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths() -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_exec_stats_prune_{}_{}",
+        std::process::id(),
+        0x57A7u64
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::schema::{DataType, Field, Schema};
+use exec::expr::{Col, Pred};
+use exec::operators::filter::FilterOp;
+use exec::operators::scan::{Cols, SeqScan};
+use exec::operators::Operator;
+use storage::reader::open_meta;
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn string_predicates_eval() -> Result<()> {
+    let batch = make_batch(6)?;
+    let host = || Col::Named("host".into());
+
+    assert_eq!(
+        Pred::eq(host(), "us-web-1").eval_batch(&batch),
+        vec![true, false, false, false, true, false]
+    );
+    let any = Pred::in_list(host(), ["us-web-2", "eu-db-1"]);
+    assert_eq!(
+        any.eval_batch(&batch),
+        vec![false, true, false, true, false, true]
+    );
+    assert_eq!(
+        Pred::PrefixStr(host(), "us-".into()).eval_batch(&batch),
+        vec![true, true, false, false, true, true]
+    );
+    // String predicates never match a numeric column.
+    assert_eq!(Pred::eq(Col::Value, "1").eval_batch(&batch), vec![false; 6]);
+    assert_eq!(any.to_string(), "host IN ('us-web-2', 'eu-db-1')");
+    assert_eq!(
+        Pred::PrefixStr(host(), "us-".into()).to_string(),
+        "starts_with(host, 'us-')"
+    );
+    Ok(())
+}
+
+#[test]
+fn string_predicates_prune_and_filter() -> Result<()> {
+    let batch = make_batch(4000)?;
+    let (dir, path) = temp_paths("prune");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 1000,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+
+    let meta = open_meta(&path)?;
+    let host = || Col::Named("host".into());
+    assert!(!Pred::eq(host(), "ap-web-1").may_match_chunk(&meta));
+    // Blocks 0 and 1 only see the first four hosts; `eu-web-9` first
+    // appears in block 2.
+    let late = Pred::eq(host(), "eu-web-9");
+    let kept: Vec<bool> = (0..4).map(|b| late.may_match_block(&meta, b)).collect();
+    assert_eq!(kept, vec![false, false, true, true]);
+    let prefix = Pred::PrefixStr(host(), "eu-".into());
+    assert!(prefix.may_match_block(&meta, 0));
+    assert!(!Pred::eq(Col::Value, "x").may_match_chunk(&meta));
+
+    let scan = SeqScan::open(path, 0, i64::MAX, 512, Cols::all())?;
+    let mut filter = FilterOp::new(Box::new(scan), late);
+    let mut rows = 0;
+    while let Some(out) = filter.next_batch()? {
+        let hosts = out.column_by_name("host").unwrap().as_utf8().unwrap();
+        assert!(hosts.iter().all(|h| h == "eu-web-9"));
+        rows += out.len();
+    }
+    assert_eq!(rows, 400);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+/// Rows cycle through four hosts; from row 2000 every fifth row is `eu-web-9`.
+fn make_batch(len: usize) -> Result<RecordBatch> {
+    const HOSTS: [&str; 4] = ["us-web-1", "us-web-2", "eu-web-1", "eu-db-1"];
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("host", DataType::Utf8),
+        Field::new("value", DataType::F64),
+    ])?;
+    let mut ts = Vec::with_capacity(len);
+    let mut host = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+    for i in 0..len {
+        ts.push(i as i64);
+        if i >= 2000 && i % 5 == 0 {
+            host.push("eu-web-9".to_string());
+        } else {
+            host.push(HOSTS[i % 4].to_string());
+        }
+        value.push(i as f64);
+    }
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Column::I64(ts), Column::Utf8(host), Column::F64(value)],
+    )
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_exec_string_preds_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
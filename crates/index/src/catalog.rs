@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use common::error::{Error, Result};
+use datamodel::labels::Labels;
+use datamodel::types::SeriesId;
+
+use crate::record_log::{put_text, Decoder, RecordLog};
+
+pub const MAGIC: [u8; 4] = *b"TSSC";
+pub const VERSION: u16 = 2;
+const WHAT: &str = "catalog";
+
+/// Assigns every distinct label set a `SeriesId`, in order of first use.
+///
+/// The catalog file holds one record per series, the encoded label set.
+/// Ids are the record index, so new series are only ever appended.
+pub struct SeriesCatalog {
+    path: PathBuf,
+    log: RecordLog,
+    ids: HashMap<Labels, SeriesId>,
+    labels: Vec<Labels>,
+}
+
+impl SeriesCatalog {
+    /// Opens the catalog at `path`, creating an empty one if it is missing.
+    pub fn open(path: &Path) -> Result<Self> {
+        let (log, records) = RecordLog::open(path, MAGIC, VERSION, WHAT)?;
+        let mut catalog = Self {
+            path: path.to_path_buf(),
+            log,
+            ids: HashMap::new(),
+            labels: Vec::new(),
+        };
+        for record in records {
+            let labels = decode_labels(&record)?;
+            let id = catalog.labels.len() as SeriesId;
+            if catalog.ids.insert(labels.clone(), id).is_some() {
+                return Err(Error::Corrupt(format!("duplicate series: {}", labels)));
+            }
+            catalog.labels.push(labels);
+        }
+        Ok(catalog)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The id of `labels`, assigning and persisting a new one if needed.
+    pub fn get_or_create(&mut self, labels: &Labels) -> Result<SeriesId> {
+        Ok(self.get_or_create_all([labels])?[0])
+    }
+
+    /// Like `get_or_create` for many label sets, with a single sync for all
+    /// new series.
+    pub fn get_or_create_all<'l>(
+        &mut self,
+        labels: impl IntoIterator<Item = &'l Labels>,
+    ) -> Result<Vec<SeriesId>> {
+        let mut ids = Vec::new();
+        let mut new_labels = Vec::new();
+        let mut new_ids = HashMap::new();
+        let mut records = Vec::new();
+        for set in labels {
+            if let Some(&id) = self.ids.get(set).or_else(|| new_ids.get(set)) {
+                ids.push(id);
+                continue;
+            }
+            let id = SeriesId::try_from(self.labels.len() + new_labels.len())
+                .map_err(|_| Error::Unsupported("series id space exhausted".into()))?;
+            encode_record(&mut records, set)?;
+            new_ids.insert(set.clone(), id);
+            new_labels.push(set.clone());
+            ids.push(id);
+        }
+        if !records.is_empty() {
+            self.log.append(&records)?;
+            self.ids.extend(new_ids);
+            self.labels.extend(new_labels);
+        }
+        Ok(ids)
+    }
+
+    pub fn lookup(&self, labels: &Labels) -> Option<SeriesId> {
+        self.ids.get(labels).copied()
+    }
+
+    /// Reverse lookup, for presenting results by label set.
+    pub fn labels(&self, id: SeriesId) -> Option<&Labels> {
+        self.labels.get(id as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SeriesId, &Labels)> + '_ {
+        self.labels
+            .iter()
+            .enumerate()
+            .map(|(id, labels)| (id as SeriesId, labels))
+    }
+}
+
+fn encode_record(out: &mut Vec<u8>, labels: &Labels) -> Result<()> {
+    let count =
+        u16::try_from(labels.len()).map_err(|_| Error::Unsupported("too many labels".into()))?;
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&count.to_le_bytes());
+    for (name, value) in labels.iter() {
+        put_text(&mut payload, name, "label")?;
+        put_text(&mut payload, value, "label")?;
+    }
+    RecordLog::push_record(out, &payload);
+    Ok(())
+}
+
+fn decode_labels(payload: &[u8]) -> Result<Labels> {
+    let mut decoder = Decoder::new(payload, WHAT);
+    let count = decoder.u16()?;
+    let mut pairs = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = decoder.text()?;
+        let value = decoder.text()?;
+        pairs.push((name, value));
+    }
+    decoder.finish()?;
+    Labels::new(pairs).map_err(|_| Error::Corrupt("invalid label set".into()))
+}
@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use common::error::Result;
+use datamodel::batch::RecordBatch;
+use datamodel::labels::Labels;
+use datamodel::schema::Schema;
+use storage::writer::{ChunkWriter, WriteOptions};
+
+use crate::catalog::SeriesCatalog;
+
+/// One point of the series identified by `labels`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub labels: Labels,
+    pub ts: i64,
+    pub value: f64,
+}
+
+/// Writes labeled samples into a `Schema::time_series()` chunk, taking each
+/// series id from the catalog. New series are persisted in the catalog
+/// before any chunk can refer to them.
+pub struct SampleWriter<'a> {
+    catalog: &'a mut SeriesCatalog,
+    writer: ChunkWriter,
+}
+
+impl<'a> SampleWriter<'a> {
+    pub fn create(
+        path: &Path,
+        catalog: &'a mut SeriesCatalog,
+        options: WriteOptions,
+    ) -> Result<Self> {
+        let writer = ChunkWriter::create(path, Schema::time_series(), options)?;
+        Ok(Self { catalog, writer })
+    }
+
+    pub fn append(&mut self, samples: &[Sample]) -> Result<()> {
+        let series_id = self
+            .catalog
+            .get_or_create_all(samples.iter().map(|s| &s.labels))?;
+        let ts = samples.iter().map(|s| s.ts).collect();
+        let value = samples.iter().map(|s| s.value).collect();
+        self.writer
+            .append(&RecordBatch::time_series(ts, series_id, value))
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.writer.rows()
+    }
+
+    pub fn finish(self) -> Result<()> {
+        self.writer.finish()
+    }
+}
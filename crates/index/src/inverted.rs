@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use common::error::{Error, Result};
+use datamodel::labels::Labels;
+use datamodel::types::SeriesId;
+
+use crate::catalog::SeriesCatalog;
+use crate::matcher::{MatchOp, Matcher, Selector};
+use crate::postings::{self, Postings};
+
+/// Maps each `name=value` pair to the sorted ids of the series carrying it.
+///
+/// The index lives in memory and is rebuilt from the catalog on open; series
+/// must be added in increasing id order so postings stay sorted by appending.
+#[derive(Debug, Default)]
+pub struct InvertedIndex {
+    postings: BTreeMap<String, BTreeMap<String, Postings>>,
+    all: Postings,
+}
+
+impl InvertedIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_catalog(catalog: &SeriesCatalog) -> Self {
+        let mut index = Self::new();
+        for (id, labels) in catalog.iter() {
+            // Catalog ids are dense and ascending.
+            index.add(id, labels).unwrap();
+        }
+        index
+    }
+
+    pub fn add(&mut self, id: SeriesId, labels: &Labels) -> Result<()> {
+        if self.all.last().is_some_and(|&last| last >= id) {
+            return Err(Error::Unsupported(format!(
+                "series {} added out of order",
+                id
+            )));
+        }
+        self.all.push(id);
+        for (name, value) in labels.iter() {
+            self.postings
+                .entry(name.to_string())
+                .or_default()
+                .entry(value.to_string())
+                .or_default()
+                .push(id);
+        }
+        Ok(())
+    }
+
+    /// Every indexed series.
+    pub fn all(&self) -> &[SeriesId] {
+        &self.all
+    }
+
+    pub fn postings(&self, name: &str, value: &str) -> &[SeriesId] {
+        self.postings
+            .get(name)
+            .and_then(|values| values.get(value))
+            .map_or(&[], |ids| ids.as_slice())
+    }
+
+    pub fn label_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.postings.keys().map(|name| name.as_str())
+    }
+
+    pub fn label_values(&self, name: &str) -> impl Iterator<Item = &str> + '_ {
+        self.postings
+            .get(name)
+            .into_iter()
+            .flat_map(|values| values.keys().map(|value| value.as_str()))
+    }
+
+    /// The series `matcher` selects.
+    pub fn matching(&self, matcher: &Matcher) -> Postings {
+        if matcher.op() == MatchOp::Eq && !matcher.value().is_empty() {
+            return self.postings(matcher.name(), matcher.value()).to_vec();
+        }
+        let values = self.postings.get(matcher.name());
+        let lists = values.into_iter().flatten();
+        if matcher.matches_missing() {
+            // Series without the label pass too, so start from every series
+            // and drop those whose value fails.
+            let failing = lists
+                .filter(|(value, _)| !matcher.matches(value))
+                .map(|(_, ids)| ids.as_slice());
+            postings::difference(&self.all, &postings::union_all(failing))
+        } else {
+            let passing = lists
+                .filter(|(value, _)| matcher.matches(value))
+                .map(|(_, ids)| ids.as_slice());
+            postings::union_all(passing)
+        }
+    }
+
+    /// The series every matcher selects. Matchers that exclude unlabeled
+    /// series tend to be the most selective, so they are applied first.
+    pub fn select(&self, selector: &Selector) -> Postings {
+        let mut matchers: Vec<&Matcher> = selector.matchers.iter().collect();
+        matchers.sort_by_key(|m| m.matches_missing());
+        let mut result: Option<Postings> = None;
+        for matcher in matchers {
+            let ids = self.matching(matcher);
+            let next = match result {
+                Some(prev) => postings::intersect(&prev, &ids),
+                None => ids,
+            };
+            if next.is_empty() {
+                return next;
+            }
+            result = Some(next);
+        }
+        result.unwrap_or_else(|| self.all.clone())
+    }
+}
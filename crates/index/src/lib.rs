@@ -1,3 +1,9 @@
-//! index crate scaffold.
+//! Series catalog and indexes over chunks.
 
-pub fn placeholder() {}
+pub mod catalog;
+pub mod ingest;
+pub mod inverted;
+pub mod manifest;
+pub mod matcher;
+pub mod postings;
+mod record_log;
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use common::error::{Error, Result};
+use datamodel::schema::{DataType, SERIES_ID_COLUMN};
+use datamodel::types::SeriesId;
+use storage::meta::ChunkMeta;
+use storage::reader::open_meta;
+
+use crate::record_log::{put_text, Decoder, RecordLog};
+
+pub const MAGIC: [u8; 4] = *b"TSCM";
+pub const VERSION: u16 = 2;
+const WHAT: &str = "manifest";
+
+const RECORD_ADD: u8 = 1;
+const RECORD_REMOVE: u8 = 2;
+
+/// What the manifest knows about one chunk file, taken from its meta.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkEntry {
+    pub path: PathBuf,
+    pub row_count: u32,
+    pub ts_min: i64,
+    pub ts_max: i64,
+    /// Smallest and largest `series_id`, when the chunk has that column as
+    /// `u32` and it holds a non-null value.
+    pub series: Option<(SeriesId, SeriesId)>,
+}
+
+impl ChunkEntry {
+    pub fn from_meta(path: PathBuf, meta: &ChunkMeta) -> Self {
+        let is_u32 = meta
+            .schema
+            .index_of(SERIES_ID_COLUMN)
+            .is_some_and(|idx| meta.schema.field(idx).dtype == DataType::U32);
+        let series = meta
+            .col_named(SERIES_ID_COLUMN)
+            .filter(|_| is_u32)
+            .and_then(|col| col.stats.as_ref())
+            .filter(|stats| stats.count > stats.null_count)
+            .map(|stats| {
+                (
+                    stats.min.as_i64() as SeriesId,
+                    stats.max.as_i64() as SeriesId,
+                )
+            });
+        Self {
+            path,
+            row_count: meta.row_count,
+            ts_min: meta.ts_min,
+            ts_max: meta.ts_max,
+            series,
+        }
+    }
+
+    /// Whether the chunk may hold rows in `[t0, t1)`; the same test
+    /// `SeqScan::open` uses to skip a chunk.
+    pub fn overlaps(&self, t0: i64, t1: i64) -> bool {
+        self.row_count > 0 && self.ts_min < t1 && self.ts_max >= t0
+    }
+
+    /// Whether the chunk may hold a series in `lo..=hi`. Chunks without
+    /// series stats always may.
+    pub fn may_contain_series(&self, lo: SeriesId, hi: SeriesId) -> bool {
+        match self.series {
+            Some((min, max)) => min <= hi && lo <= max,
+            None => true,
+        }
+    }
+}
+
+/// A persistent list of chunk files with their time and series ranges, so a
+/// query can find the chunks it needs without opening each one.
+///
+/// Entries are kept sorted by `ts_min` alongside a running maximum of
+/// `ts_max`. Both are monotonic, so the chunks overlapping a time range lie
+/// between two binary searches, and only chunks in that span whose own
+/// `ts_max` is too small are passed over. Chunks written in time order make
+/// the span tight.
+///
+/// The manifest file is a log of add and remove records, replayed on open.
+pub struct ChunkManifest {
+    path: PathBuf,
+    log: RecordLog,
+    entries: Vec<ChunkEntry>,
+    /// The `ts_min` of each entry by path, which narrows the search for it
+    /// to the entries sharing that `ts_min`.
+    ts_min_by_path: HashMap<PathBuf, i64>,
+    /// `max_ts_max[i]` is the largest `ts_max` of `entries[..=i]`.
+    max_ts_max: Vec<i64>,
+}
+
+impl ChunkManifest {
+    /// Opens the manifest at `path`, creating an empty one if it is missing.
+    pub fn open(path: &Path) -> Result<Self> {
+        let (log, records) = RecordLog::open(path, MAGIC, VERSION, WHAT)?;
+        // Replayed into a map and sorted once, with ties in the order the
+        // chunks were added, as `insert` would leave them.
+        let mut live: HashMap<PathBuf, (usize, ChunkEntry)> = HashMap::new();
+        for (seq, record) in records.into_iter().enumerate() {
+            let mut decoder = Decoder::new(&record, WHAT);
+            match decoder.u8()? {
+                RECORD_ADD => {
+                    let entry = decode_entry(&mut decoder)?;
+                    decoder.finish()?;
+                    if live.contains_key(&entry.path) {
+                        return Err(Error::Corrupt(format!(
+                            "chunk added twice: {}",
+                            entry.path.display()
+                        )));
+                    }
+                    live.insert(entry.path.clone(), (seq, entry));
+                }
+                RECORD_REMOVE => {
+                    let path = PathBuf::from(decoder.text()?);
+                    decoder.finish()?;
+                    if live.remove(&path).is_none() {
+                        return Err(Error::Corrupt(format!(
+                            "removed chunk not in manifest: {}",
+                            path.display()
+                        )));
+                    }
+                }
+                kind => return Err(Error::Corrupt(format!("unknown manifest record: {}", kind))),
+            }
+        }
+
+        let mut live: Vec<(usize, ChunkEntry)> = live.into_values().collect();
+        live.sort_by_key(|(seq, entry)| (entry.ts_min, *seq));
+        let entries: Vec<ChunkEntry> = live.into_iter().map(|(_, entry)| entry).collect();
+        let ts_min_by_path = entries
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.ts_min))
+            .collect();
+        let mut manifest = Self {
+            path: path.to_path_buf(),
+            log,
+            entries,
+            ts_min_by_path,
+            max_ts_max: Vec::new(),
+        };
+        manifest.rebuild_max_from(0);
+        Ok(manifest)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records a chunk, reading only its meta. Paths are stored as given.
+    pub fn add_chunk(&mut self, path: &Path) -> Result<()> {
+        let meta = open_meta(path)?;
+        self.add(ChunkEntry::from_meta(path.to_path_buf(), &meta))
+    }
+
+    pub fn add(&mut self, entry: ChunkEntry) -> Result<()> {
+        if self.position(&entry.path).is_some() {
+            return Err(Error::Unsupported(format!(
+                "chunk already in manifest: {}",
+                entry.path.display()
+            )));
+        }
+        let mut payload = vec![RECORD_ADD];
+        encode_entry(&mut payload, &entry)?;
+        let mut record = Vec::new();
+        RecordLog::push_record(&mut record, &payload);
+        self.log.append(&record)?;
+        self.insert(entry);
+        Ok(())
+    }
+
+    /// Drops the chunk at `path`, e.g. once compaction has replaced it.
+    /// Returns false if the manifest did not list it.
+    pub fn remove(&mut self, path: &Path) -> Result<bool> {
+        if self.position(path).is_none() {
+            return Ok(false);
+        }
+        let mut payload = vec![RECORD_REMOVE];
+        put_text(&mut payload, path_text(path)?, "chunk path")?;
+        let mut record = Vec::new();
+        RecordLog::push_record(&mut record, &payload);
+        self.log.append(&record)?;
+        self.take(path);
+        Ok(true)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&ChunkEntry> {
+        self.position(path).map(|idx| &self.entries[idx])
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every chunk, ordered by `ts_min`.
+    pub fn iter(&self) -> impl Iterator<Item = &ChunkEntry> + '_ {
+        self.entries.iter()
+    }
+
+    /// Chunks that may hold rows in `[t0, t1)`, ordered by `ts_min`.
+    pub fn candidates(&self, t0: i64, t1: i64) -> impl Iterator<Item = &ChunkEntry> + '_ {
+        let first = self.max_ts_max.partition_point(|&max| max < t0);
+        let end = self.entries.partition_point(|entry| entry.ts_min < t1);
+        self.entries[first..end.max(first)]
+            .iter()
+            .filter(move |entry| entry.overlaps(t0, t1))
+    }
+
+    /// Like `candidates`, also passing over chunks whose series range misses
+    /// `lo..=hi`.
+    pub fn candidates_for_series(
+        &self,
+        t0: i64,
+        t1: i64,
+        lo: SeriesId,
+        hi: SeriesId,
+    ) -> impl Iterator<Item = &ChunkEntry> + '_ {
+        self.candidates(t0, t1)
+            .filter(move |entry| entry.may_contain_series(lo, hi))
+    }
+
+    fn position(&self, path: &Path) -> Option<usize> {
+        let ts_min = *self.ts_min_by_path.get(path)?;
+        let first = self.entries.partition_point(|entry| entry.ts_min < ts_min);
+        self.entries[first..]
+            .iter()
+            .position(|entry| entry.path == path)
+            .map(|idx| first + idx)
+    }
+
+    fn insert(&mut self, entry: ChunkEntry) {
+        let idx = self
+            .entries
+            .partition_point(|other| other.ts_min <= entry.ts_min);
+        self.ts_min_by_path.insert(entry.path.clone(), entry.ts_min);
+        self.entries.insert(idx, entry);
+        self.rebuild_max_from(idx);
+    }
+
+    fn take(&mut self, path: &Path) -> bool {
+        match self.position(path) {
+            Some(idx) => {
+                self.ts_min_by_path.remove(path);
+                self.entries.remove(idx);
+                self.rebuild_max_from(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn rebuild_max_from(&mut self, idx: usize) {
+        self.max_ts_max.truncate(idx);
+        let mut max = self.max_ts_max.last().copied().unwrap_or(i64::MIN);
+        for entry in &self.entries[idx..] {
+            max = max.max(entry.ts_max);
+            self.max_ts_max.push(max);
+        }
+    }
+}
+
+fn path_text(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| Error::Unsupported(format!("chunk path is not utf-8: {}", path.display())))
+}
+
+fn encode_entry(out: &mut Vec<u8>, entry: &ChunkEntry) -> Result<()> {
+    put_text(out, path_text(&entry.path)?, "chunk path")?;
+    out.extend_from_slice(&entry.row_count.to_le_bytes());
+    out.extend_from_slice(&entry.ts_min.to_le_bytes());
+    out.extend_from_slice(&entry.ts_max.to_le_bytes());
+    match entry.series {
+        Some((min, max)) => {
+            out.push(1);
+            out.extend_from_slice(&min.to_le_bytes());
+            out.extend_from_slice(&max.to_le_bytes());
+        }
+        None => out.push(0),
+    }
+    Ok(())
+}
+
+fn decode_entry(decoder: &mut Decoder<'_>) -> Result<ChunkEntry> {
+    let path = PathBuf::from(decoder.text()?);
+    let row_count = decoder.u32()?;
+    let ts_min = decoder.i64()?;
+    let ts_max = decoder.i64()?;
+    let series = match decoder.u8()? {
+        0 => None,
+        1 => Some((decoder.u32()?, decoder.u32()?)),
+        flag => {
+            return Err(Error::Corrupt(format!(
+                "bad manifest series flag: {}",
+                flag
+            )))
+        }
+    };
+    Ok(ChunkEntry {
+        path,
+        row_count,
+        ts_min,
+        ts_max,
+        series,
+    })
+}
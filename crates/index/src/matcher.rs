@@ -0,0 +1,308 @@
+use std::fmt;
+
+use common::error::{Error, Result};
+use regex::Regex;
+
+/// The label holding a series' metric name, so `cpu{...}` selects on
+/// `__name__="cpu"`.
+pub const METRIC_NAME: &str = "__name__";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchOp {
+    Eq,
+    Ne,
+    Re,
+    NotRe,
+}
+
+impl MatchOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            MatchOp::Eq => "=",
+            MatchOp::Ne => "!=",
+            MatchOp::Re => "=~",
+            MatchOp::NotRe => "!~",
+        }
+    }
+}
+
+/// One `name op "value"` condition on a label. A series without the label
+/// is treated as having it set to `""`, so `env!="dev"` also selects series
+/// with no `env` at all.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    name: String,
+    op: MatchOp,
+    value: String,
+    /// Compiled from `value` for the regex ops, which is why the fields
+    /// are only set through the constructors.
+    regex: Option<Regex>,
+}
+
+impl Matcher {
+    /// Regexes are fully anchored: `web.*` matches `web-1`, not `my-web-1`.
+    pub fn new(name: impl Into<String>, op: MatchOp, value: impl Into<String>) -> Result<Self> {
+        let value = value.into();
+        let regex = match op {
+            MatchOp::Re | MatchOp::NotRe => Some(
+                Regex::new(&format!("^(?:{})$", value))
+                    .map_err(|e| Error::Unsupported(format!("bad regex {:?}: {}", value, e)))?,
+            ),
+            MatchOp::Eq | MatchOp::Ne => None,
+        };
+        Ok(Self {
+            name: name.into(),
+            op,
+            value,
+            regex,
+        })
+    }
+
+    pub fn eq(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::literal(name.into(), MatchOp::Eq, value.into())
+    }
+
+    pub fn ne(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::literal(name.into(), MatchOp::Ne, value.into())
+    }
+
+    fn literal(name: String, op: MatchOp, value: String) -> Self {
+        Self {
+            name,
+            op,
+            value,
+            regex: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn op(&self) -> MatchOp {
+        self.op
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self.op {
+            MatchOp::Eq => value == self.value,
+            MatchOp::Ne => value != self.value,
+            MatchOp::Re => self.regex.as_ref().unwrap().is_match(value),
+            MatchOp::NotRe => !self.regex.as_ref().unwrap().is_match(value),
+        }
+    }
+
+    /// Whether series lacking the label are selected.
+    pub fn matches_missing(&self) -> bool {
+        self.matches("")
+    }
+}
+
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.op == other.op && self.value == other.value
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}\"", self.name, self.op.as_str())?;
+        for c in self.value.chars() {
+            if c == '"' || c == '\\' {
+                f.write_str("\\")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        f.write_str("\"")
+    }
+}
+
+/// A series selector such as `cpu{host=~"web.*",env!="dev"}`: every matcher
+/// must hold.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Selector {
+    pub matchers: Vec<Matcher>,
+}
+
+impl Selector {
+    pub fn new(matchers: Vec<Matcher>) -> Self {
+        Self { matchers }
+    }
+
+    /// Parses `metric`, `metric{matchers}` or `{matchers}`, where matchers
+    /// are `name op "value"` separated by commas. Values are double-quoted
+    /// and may escape `"` and `\` with a backslash; other backslashes are
+    /// kept, so regexes like `"\d+"` need no doubling. `{}` is the empty
+    /// selector, which selects every series.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { text, pos: 0 };
+        let mut matchers = Vec::new();
+        parser.skip_ws();
+        if let Some(metric) = parser.ident(true) {
+            matchers.push(Matcher::eq(METRIC_NAME, metric));
+        }
+        parser.skip_ws();
+        let braced = parser.eat("{");
+        if braced {
+            loop {
+                parser.skip_ws();
+                if parser.eat("}") {
+                    break;
+                }
+                matchers.push(parser.matcher()?);
+                parser.skip_ws();
+                if parser.eat("}") {
+                    break;
+                }
+                if !parser.eat(",") {
+                    return Err(parser.error("expected ',' or '}'"));
+                }
+            }
+        }
+        parser.skip_ws();
+        if parser.pos != text.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        if matchers.is_empty() && !braced {
+            return Err(Error::Unsupported("empty selector".into()));
+        }
+        Ok(Self { matchers })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = &self.matchers[..];
+        if let Some(first) = rest.first() {
+            if first.name == METRIC_NAME && first.op == MatchOp::Eq && is_metric(&first.value) {
+                f.write_str(&first.value)?;
+                rest = &rest[1..];
+                if rest.is_empty() {
+                    return Ok(());
+                }
+            }
+        }
+        f.write_str("{")?;
+        for (i, matcher) in rest.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", matcher)?;
+        }
+        f.write_str("}")
+    }
+}
+
+/// The length of the identifier at the start of `text`; metric names may
+/// also contain `:`.
+fn ident_len(text: &str, metric: bool) -> usize {
+    text.char_indices()
+        .find(|&(i, c)| {
+            let ok = c == '_'
+                || c.is_ascii_alphabetic()
+                || (i > 0 && c.is_ascii_digit())
+                || (metric && c == ':');
+            !ok
+        })
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Whether `name` can be written as a bare metric name before the braces.
+fn is_metric(name: &str) -> bool {
+    !name.is_empty() && ident_len(name, true) == name.len()
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Metric names may also contain `:`, label names may not.
+    fn ident(&mut self, metric: bool) -> Option<&'a str> {
+        let rest = self.rest();
+        let len = ident_len(rest, metric);
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn matcher(&mut self) -> Result<Matcher> {
+        let name = self
+            .ident(false)
+            .ok_or_else(|| self.error("expected label name"))?;
+        self.skip_ws();
+        // `=~` before `=`, so a regex match is not read as equality.
+        let op = if self.eat("=~") {
+            MatchOp::Re
+        } else if self.eat("!~") {
+            MatchOp::NotRe
+        } else if self.eat("!=") {
+            MatchOp::Ne
+        } else if self.eat("=") {
+            MatchOp::Eq
+        } else {
+            return Err(self.error("expected matcher operator"));
+        };
+        self.skip_ws();
+        let value = self.quoted()?;
+        Matcher::new(name, op, value)
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        if !self.eat("\"") {
+            return Err(self.error("expected '\"'"));
+        }
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c @ ('"' | '\\'))) => value.push(c),
+                    Some((_, c)) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn error(&self, what: &str) -> Error {
+        Error::Unsupported(format!(
+            "bad selector {:?}: {} at offset {}",
+            self.text, what, self.pos
+        ))
+    }
+}
@@ -0,0 +1,71 @@
+use std::cmp::Ordering;
+
+use datamodel::types::SeriesId;
+
+/// Series ids in ascending order, without duplicates.
+pub type Postings = Vec<SeriesId>;
+
+pub fn intersect(a: &[SeriesId], b: &[SeriesId]) -> Postings {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::with_capacity(a.len().min(b.len()));
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+pub fn union(a: &[SeriesId], b: &[SeriesId]) -> Postings {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => {
+                out.push(a[i]);
+                i += 1;
+            }
+            Ordering::Greater => {
+                out.push(b[j]);
+                j += 1;
+            }
+            Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out.extend_from_slice(&a[i..]);
+    out.extend_from_slice(&b[j..]);
+    out
+}
+
+/// The ids in `a` that are not in `b`.
+pub fn difference(a: &[SeriesId], b: &[SeriesId]) -> Postings {
+    let mut j = 0;
+    let mut out = Vec::with_capacity(a.len());
+    for &id in a {
+        while j < b.len() && b[j] < id {
+            j += 1;
+        }
+        if j == b.len() || b[j] != id {
+            out.push(id);
+        }
+    }
+    out
+}
+
+/// Unions many lists at once, cheaper than folding `union` pairwise.
+pub fn union_all<'a>(lists: impl IntoIterator<Item = &'a [SeriesId]>) -> Postings {
+    let mut out: Postings = lists.into_iter().flatten().copied().collect();
+    out.sort_unstable();
+    out.dedup();
+    out
+}
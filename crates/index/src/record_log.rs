@@ -0,0 +1,202 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use common::error::{Error, Result};
+use crc32fast::Hasher;
+
+const HEADER_LEN: usize = 6;
+const RECORD_HEADER_LEN: usize = 4 + 4 + 4;
+
+/// An append-only file of checksummed records behind a `magic` and
+/// `version` header; each record is `len: u32`, the payload's `crc32: u32`,
+/// a `crc32: u32` of those eight bytes, and the payload. A torn record at
+/// the end of the file, left by a crash mid-append, is dropped on open;
+/// anything else that fails a checksum is corrupt.
+pub(crate) struct RecordLog {
+    file: File,
+    len: u64,
+    /// Names the file in errors, e.g. "catalog".
+    what: &'static str,
+}
+
+impl RecordLog {
+    /// Opens the log at `path`, creating an empty one if it is missing, and
+    /// returns it with the payloads of its records.
+    pub(crate) fn open(
+        path: &Path,
+        magic: [u8; 4],
+        version: u16,
+        what: &'static str,
+    ) -> Result<(Self, Vec<Vec<u8>>)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+
+        let mut log = Self { file, len: 0, what };
+        if buf.is_empty() {
+            let mut header = Vec::with_capacity(HEADER_LEN);
+            header.extend_from_slice(&magic);
+            header.extend_from_slice(&version.to_le_bytes());
+            log.append(&header)?;
+            return Ok((log, Vec::new()));
+        }
+
+        if buf.len() < HEADER_LEN || buf[..4] != magic {
+            return Err(Error::Corrupt(format!("bad {} magic", what)));
+        }
+        let found = u16::from_le_bytes([buf[4], buf[5]]);
+        if found != version {
+            return Err(Error::Unsupported(format!(
+                "unsupported {} version: {}",
+                what, found
+            )));
+        }
+        let mut payloads = Vec::new();
+        let mut pos = HEADER_LEN;
+        while pos < buf.len() {
+            let payload = match log.read_record(&buf[pos..])? {
+                Some(payload) => payload,
+                None => break,
+            };
+            payloads.push(payload.to_vec());
+            pos += RECORD_HEADER_LEN + payload.len();
+        }
+        if pos < buf.len() {
+            log.file.set_len(pos as u64)?;
+            log.file.sync_all()?;
+        }
+        log.len = pos as u64;
+        Ok((log, payloads))
+    }
+
+    /// Frames `payload` as a record onto `out`, for a later `append`.
+    pub(crate) fn push_record(out: &mut Vec<u8>, payload: &[u8]) {
+        let mut header = Vec::with_capacity(RECORD_HEADER_LEN);
+        header.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        header.extend_from_slice(&crc32(payload).to_le_bytes());
+        header.extend_from_slice(&crc32(&header).to_le_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(payload);
+    }
+
+    /// Appends and syncs `bytes`; on failure the file is cut back so a later
+    /// append does not land after a partial record.
+    pub(crate) fn append(&mut self, bytes: &[u8]) -> Result<()> {
+        let result = self
+            .file
+            .write_all(bytes)
+            .and_then(|_| self.file.sync_data());
+        if let Err(e) = result {
+            let _ = self.file.set_len(self.len);
+            return Err(e.into());
+        }
+        self.len += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// The payload of the record at the start of `buf`, or `None` if it is
+    /// torn: its header is cut short, or its checked header declares a
+    /// payload running past the end of the file. The header checksum keeps
+    /// a damaged length from passing for a torn record and dropping the
+    /// records after it.
+    fn read_record<'b>(&self, buf: &'b [u8]) -> Result<Option<&'b [u8]>> {
+        if buf.len() < RECORD_HEADER_LEN {
+            return Ok(None);
+        }
+        let field = |at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap());
+        if crc32(&buf[..8]) != field(8) {
+            return Err(Error::Corrupt(format!(
+                "{} record header crc mismatch",
+                self.what
+            )));
+        }
+        let len = field(0) as usize;
+        let payload = match buf[RECORD_HEADER_LEN..].get(..len) {
+            Some(payload) => payload,
+            None => return Ok(None),
+        };
+        if crc32(payload) != field(4) {
+            return Err(Error::Corrupt(format!("{} record crc mismatch", self.what)));
+        }
+        Ok(Some(payload))
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Reads fields off the front of a record payload.
+pub(crate) struct Decoder<'a> {
+    rest: &'a [u8],
+    what: &'static str,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(payload: &'a [u8], what: &'static str) -> Self {
+        Self {
+            rest: payload,
+            what,
+        }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.rest.len() < len {
+            return Err(Error::Corrupt(format!("{} record too short", self.what)));
+        }
+        let (bytes, tail) = self.rest.split_at(len);
+        self.rest = tail;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// A `u16`-length-prefixed utf-8 string.
+    pub(crate) fn text(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| Error::Corrupt(format!("{} record text is not utf-8", self.what)))
+    }
+
+    /// Fails unless the whole payload was read.
+    pub(crate) fn finish(self) -> Result<()> {
+        if self.rest.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Corrupt(format!(
+                "{} record length mismatch",
+                self.what
+            )))
+        }
+    }
+}
+
+/// Appends `text` with a `u16` length prefix.
+pub(crate) fn put_text(out: &mut Vec<u8>, text: &str, what: &str) -> Result<()> {
+    let len =
+        u16::try_from(text.len()).map_err(|_| Error::Unsupported(format!("{} too long", what)))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(text.as_bytes());
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use common::error::{Error, Result};
+use datamodel::labels::Labels;
+use index::catalog::SeriesCatalog;
+use index::ingest::{Sample, SampleWriter};
+use storage::reader::open_chunk;
+use storage::writer::WriteOptions;
+
+#[test]
+fn labels_are_order_insensitive() -> Result<()> {
+    let a = Labels::new([("host", "web-1"), ("region", "us")])?;
+    let b = Labels::new([("region", "us"), ("host", "web-1")])?;
+    assert_eq!(a, b);
+    assert_eq!(a.get("region"), Some("us"));
+    assert_eq!(a.get("zone"), None);
+    assert_eq!(a.to_string(), "host=web-1,region=us");
+    assert_eq!(Labels::parse(&a.to_string())?, a);
+    assert!(Labels::new([("host", "a"), ("host", "b")]).is_err());
+    assert!(Labels::parse("host").is_err());
+    Ok(())
+}
+
+#[test]
+fn catalog_interns_and_persists() -> Result<()> {
+    let (dir, path) = temp_paths("persist");
+    fs::create_dir_all(&dir)?;
+
+    let web = Labels::parse("host=web-1,region=us")?;
+    let db = Labels::parse("host=db-1,region=us")?;
+    {
+        let mut catalog = SeriesCatalog::open(&path)?;
+        assert!(catalog.is_empty());
+        assert_eq!(catalog.get_or_create(&web)?, 0);
+        assert_eq!(catalog.get_or_create(&db)?, 1);
+        assert_eq!(
+            catalog.get_or_create(&Labels::parse("region=us,host=web-1")?)?,
+            0
+        );
+        assert_eq!(catalog.labels(1), Some(&db));
+        assert_eq!(catalog.labels(2), None);
+    }
+
+    let mut catalog = SeriesCatalog::open(&path)?;
+    assert_eq!(catalog.len(), 2);
+    assert_eq!(catalog.lookup(&web), Some(0));
+    assert_eq!(catalog.lookup(&db), Some(1));
+    let cache = Labels::parse("host=cache-1")?;
+    let ids = catalog.get_or_create_all([&db, &cache, &cache, &web])?;
+    assert_eq!(ids, vec![1, 2, 2, 0]);
+    let all: Vec<String> = catalog.iter().map(|(_, l)| l.to_string()).collect();
+    assert_eq!(
+        all,
+        vec![
+            "host=web-1,region=us",
+            "host=db-1,region=us",
+            "host=cache-1"
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn torn_tail_is_dropped() -> Result<()> {
+    let (dir, path) = temp_paths("torn");
+    fs::create_dir_all(&dir)?;
+    {
+        let mut catalog = SeriesCatalog::open(&path)?;
+        catalog.get_or_create(&Labels::parse("host=a")?)?;
+        catalog.get_or_create(&Labels::parse("host=b")?)?;
+    }
+    let full = fs::metadata(&path)?.len();
+    // A crash part way through appending a third record.
+    OpenOptions::new()
+        .append(true)
+        .open(&path)?
+        .write_all(&[20, 0, 0, 0, 1, 2])?;
+
+    let mut catalog = SeriesCatalog::open(&path)?;
+    assert_eq!(catalog.len(), 2);
+    assert_eq!(fs::metadata(&path)?.len(), full);
+    assert_eq!(catalog.get_or_create(&Labels::parse("host=c")?)?, 2);
+    drop(catalog);
+    assert_eq!(SeriesCatalog::open(&path)?.len(), 3);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn corrupt_record_is_rejected() -> Result<()> {
+    let (dir, path) = temp_paths("corrupt");
+    fs::create_dir_all(&dir)?;
+    {
+        let mut catalog = SeriesCatalog::open(&path)?;
+        catalog.get_or_create(&Labels::parse("host=a")?)?;
+        catalog.get_or_create(&Labels::parse("host=b")?)?;
+    }
+    // Flip the last byte of the first record's value.
+    let mut bytes = fs::read(&path)?;
+    let first_end = 6 + 12 + 2 + 2 + 4 + 2 + 1;
+    bytes[first_end - 1] ^= 0xff;
+    fs::write(&path, &bytes)?;
+
+    assert!(matches!(SeriesCatalog::open(&path), Err(Error::Corrupt(_))));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn corrupt_middle_record_keeps_later_records() -> Result<()> {
+    let (dir, path) = temp_paths("middle");
+    fs::create_dir_all(&dir)?;
+    {
+        let mut catalog = SeriesCatalog::open(&path)?;
+        for host in ["host=a", "host=b", "host=c"] {
+            catalog.get_or_create(&Labels::parse(host)?)?;
+        }
+    }
+    let bytes = fs::read(&path)?;
+    let second = 6 + 12 + 2 + 2 + 4 + 2 + 1;
+    let to_end = (bytes.len() - second - 12) as u32;
+    let mut flipped_crc = bytes[second + 4..second + 8].to_vec();
+    flipped_crc[0] ^= 0xff;
+
+    // A flipped checksum and a length that is too short, runs exactly to
+    // the end of the file or past it all fail the header check.
+    let edits = [
+        (second + 4, flipped_crc),
+        (second, 3u32.to_le_bytes().to_vec()),
+        (second, to_end.to_le_bytes().to_vec()),
+        (second, (to_end + 1).to_le_bytes().to_vec()),
+    ];
+    for (at, patch) in edits {
+        let mut corrupt = bytes.clone();
+        corrupt[at..at + patch.len()].copy_from_slice(&patch);
+        fs::write(&path, &corrupt)?;
+        assert!(matches!(SeriesCatalog::open(&path), Err(Error::Corrupt(_))));
+        assert_eq!(fs::read(&path)?, corrupt);
+    }
+    // Only a last record cut short is taken for a torn append.
+    fs::write(&path, &bytes[..bytes.len() - 1])?;
+    assert_eq!(SeriesCatalog::open(&path)?.len(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn sample_writer_assigns_series_ids() -> Result<()> {
+    let (dir, path) = temp_paths("writer");
+    fs::create_dir_all(&dir)?;
+    let chunk_path = dir.join("chunk.bin");
+
+    let hosts = ["web-1", "web-2", "db-1"];
+    let samples: Vec<Sample> = (0..300)
+        .map(|i| Sample {
+            labels: Labels::new([("host", hosts[i % 3])]).unwrap(),
+            ts: i as i64 * 10,
+            value: i as f64,
+        })
+        .collect();
+
+    let mut catalog = SeriesCatalog::open(&path)?;
+    let mut writer = SampleWriter::create(&chunk_path, &mut catalog, WriteOptions::default())?;
+    writer.append(&samples[..100])?;
+    writer.append(&samples[100..])?;
+    assert_eq!(writer.rows(), 300);
+    writer.finish()?;
+    drop(catalog);
+
+    let catalog = SeriesCatalog::open(&path)?;
+    assert_eq!(catalog.len(), 3);
+    let chunk = open_chunk(&chunk_path)?;
+    let ids = chunk.read_range_u32(1, 0, 300)?;
+    for (sample, id) in samples.iter().zip(ids) {
+        assert_eq!(catalog.labels(id), Some(&sample.labels));
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir =
+        std::env::temp_dir().join(format!("tsdb_index_catalog_{}_{}", tag, std::process::id()));
+    let path = dir.join("series.cat");
+    (dir, path)
+}
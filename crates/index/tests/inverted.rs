@@ -0,0 +1,134 @@
+use common::error::Result;
+use datamodel::labels::Labels;
+use index::inverted::InvertedIndex;
+use index::matcher::{MatchOp, Matcher, Selector, METRIC_NAME};
+use index::postings::{difference, intersect, union, union_all};
+
+#[test]
+fn postings_set_ops() {
+    let a = [1, 3, 5, 7, 9];
+    let b = [2, 3, 4, 9, 10];
+    assert_eq!(intersect(&a, &b), vec![3, 9]);
+    assert_eq!(union(&a, &b), vec![1, 2, 3, 4, 5, 7, 9, 10]);
+    assert_eq!(difference(&a, &b), vec![1, 5, 7]);
+    assert_eq!(difference(&b, &[]), b.to_vec());
+    assert_eq!(union_all([&a[..], &b[..], &[0, 3][..]]).len(), 9);
+}
+
+#[test]
+fn selector_parse_and_display() -> Result<()> {
+    let sel = Selector::parse(r#"cpu{host=~"web.*", env!="dev"}"#)?;
+    assert_eq!(
+        sel.matchers,
+        vec![
+            Matcher::eq(METRIC_NAME, "cpu"),
+            Matcher::new("host", MatchOp::Re, "web.*")?,
+            Matcher::ne("env", "dev"),
+        ]
+    );
+    assert_eq!(sel.to_string(), r#"cpu{host=~"web.*",env!="dev"}"#);
+    assert_eq!(Selector::parse(&sel.to_string())?, sel);
+
+    let quoted = Selector::parse(r#"{path="a\"b\\c", id=~"\d+",}"#)?;
+    assert_eq!(quoted.matchers[0].value(), r#"a"b\c"#);
+    assert_eq!(quoted.matchers[1].value(), r"\d+");
+    assert_eq!(Selector::parse(&quoted.to_string())?, quoted);
+    assert_eq!(
+        Selector::parse("node:cpu:rate5m")?.to_string(),
+        "node:cpu:rate5m"
+    );
+
+    // A metric name that is not an identifier prints inside the braces.
+    for name in ["", "a-b"] {
+        let sel = Selector::new(vec![Matcher::eq(METRIC_NAME, name)]);
+        assert_eq!(sel.to_string(), format!(r#"{{__name__="{}"}}"#, name));
+        assert_eq!(Selector::parse(&sel.to_string())?, sel);
+    }
+
+    // The empty selector prints as `{}`, which parses back to it.
+    assert_eq!(Selector::default().to_string(), "{}");
+    assert_eq!(Selector::parse(" { } ")?, Selector::default());
+
+    for bad in [
+        "",
+        "{",
+        "cpu{host}",
+        r#"cpu{host="a""#,
+        r#"cpu{host="a" env="b"}"#,
+        r#"cpu{host=~"("}"#,
+        r#"cpu{1host="a"}"#,
+    ] {
+        assert!(Selector::parse(bad).is_err(), "{}", bad);
+    }
+    Ok(())
+}
+
+#[test]
+fn regex_matchers_are_anchored() -> Result<()> {
+    let re = Matcher::new("host", MatchOp::Re, "web.*")?;
+    assert!(re.matches("web-1"));
+    assert!(!re.matches("my-web-1"));
+    assert!(!re.matches_missing());
+    let not_re = Matcher::new("host", MatchOp::NotRe, "web-[12]")?;
+    assert!(not_re.matches("web-3"));
+    assert!(!not_re.matches("web-1"));
+    assert!(not_re.matches_missing());
+    Ok(())
+}
+
+#[test]
+fn select_series() -> Result<()> {
+    let index = make_index()?;
+    let select = |text: &str| index.select(&Selector::parse(text).unwrap());
+
+    assert_eq!(index.postings("host", "web-1"), &[0, 3]);
+    assert_eq!(
+        index.label_names().collect::<Vec<_>>(),
+        vec![METRIC_NAME, "env", "host"]
+    );
+    assert_eq!(
+        index.label_values("env").collect::<Vec<_>>(),
+        vec!["dev", "prod"]
+    );
+
+    assert_eq!(select("cpu"), vec![0, 1, 2, 5]);
+    assert_eq!(select(r#"cpu{host=~"web.*"}"#), vec![0, 1, 5]);
+    // Series 5 has no env, which counts as env="".
+    assert_eq!(select(r#"cpu{host=~"web.*",env!="dev"}"#), vec![0, 5]);
+    assert_eq!(select(r#"{env=""}"#), vec![5]);
+    assert_eq!(select(r#"{env=~"prod|"}"#), vec![0, 2, 3, 5]);
+    assert_eq!(select(r#"cpu{host!~"web-[12]"}"#), vec![2, 5]);
+    assert_eq!(select(r#"{host="web-1"}"#), vec![0, 3]);
+    assert_eq!(select(r#"mem{host="db-1"}"#), vec![4]);
+    assert_eq!(select(r#"mem{host="web-2"}"#), Vec::<u32>::new());
+    assert_eq!(select(r#"{zone=~".*"}"#), index.all().to_vec());
+    assert_eq!(select("{}"), index.all().to_vec());
+    assert_eq!(select(r#"{zone=~".+"}"#), Vec::<u32>::new());
+    Ok(())
+}
+
+#[test]
+fn series_must_be_added_in_order() -> Result<()> {
+    let mut index = InvertedIndex::new();
+    index.add(3, &Labels::parse("host=a")?)?;
+    assert!(index.add(3, &Labels::parse("host=b")?).is_err());
+    assert!(index.add(1, &Labels::parse("host=b")?).is_err());
+    assert_eq!(index.all(), &[3]);
+    Ok(())
+}
+
+fn make_index() -> Result<InvertedIndex> {
+    let series = [
+        "__name__=cpu,env=prod,host=web-1",
+        "__name__=cpu,env=dev,host=web-2",
+        "__name__=cpu,env=prod,host=db-1",
+        "__name__=mem,env=prod,host=web-1",
+        "__name__=mem,env=dev,host=db-1",
+        "__name__=cpu,host=web-3",
+    ];
+    let mut index = InvertedIndex::new();
+    for (id, text) in series.iter().enumerate() {
+        index.add(id as u32, &Labels::parse(text)?)?;
+    }
+    Ok(index)
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::schema::{DataType, Field, Schema, SERIES_ID_COLUMN, TS_COLUMN};
+use index::manifest::{ChunkEntry, ChunkManifest};
+use storage::reader::open_meta;
+use storage::writer::write_chunk;
+
+#[test]
+fn manifest_finds_chunks_by_time_and_series() -> Result<()> {
+    let (dir, path) = temp_paths("query");
+    fs::create_dir_all(&dir)?;
+
+    let mut manifest = ChunkManifest::open(&path)?;
+    // Hourly chunks for series 0..10, then 10..20.
+    for hour in 0..6 {
+        let series = if hour < 3 { 0 } else { 10 };
+        let chunk = dir.join(format!("chunk_{}.bin", hour));
+        write_chunk(&chunk, &make_batch(hour * 3600, series))?;
+        manifest.add_chunk(&chunk)?;
+    }
+    assert_eq!(manifest.len(), 6);
+    let entry = manifest.get(&dir.join("chunk_4.bin")).unwrap();
+    assert_eq!(
+        (entry.ts_min, entry.ts_max, entry.series),
+        (4 * 3600, 4 * 3600 + 3590, Some((10, 19)))
+    );
+
+    assert_eq!(names(manifest.candidates(3600, 7200)), vec!["chunk_1.bin"]);
+    assert_eq!(
+        names(manifest.candidates(7190, 7201)),
+        vec!["chunk_1.bin", "chunk_2.bin"]
+    );
+    assert!(names(manifest.candidates(-100, 0)).is_empty());
+    assert!(names(manifest.candidates(6 * 3600, i64::MAX)).is_empty());
+    assert_eq!(
+        names(manifest.candidates_for_series(0, i64::MAX, 12, 12)),
+        vec!["chunk_3.bin", "chunk_4.bin", "chunk_5.bin"]
+    );
+    assert!(names(manifest.candidates_for_series(0, i64::MAX, 20, 30)).is_empty());
+    assert!(manifest.add_chunk(&dir.join("chunk_0.bin")).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn long_chunks_are_not_missed() -> Result<()> {
+    let (dir, path) = temp_paths("long");
+    fs::create_dir_all(&dir)?;
+    let mut manifest = ChunkManifest::open(&path)?;
+    // A backfilled chunk spanning everything lands between short ones.
+    let ranges = [(0, 99), (100, 199), (50, 10_000), (200, 299), (300, 399)];
+    for (i, &(ts_min, ts_max)) in ranges.iter().enumerate() {
+        manifest.add(entry(&format!("c{}", i), ts_min, ts_max))?;
+    }
+
+    for (t0, t1) in [
+        (0, 50),
+        (250, 260),
+        (399, 400),
+        (5000, 6000),
+        (-5, 1),
+        (10_000, 10_001),
+    ] {
+        let expected: Vec<&str> = ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, &(lo, hi))| lo < t1 && hi >= t0)
+            .map(|(i, _)| ["c0", "c1", "c2", "c3", "c4"][i])
+            .collect();
+        let mut found = names(manifest.candidates(t0, t1));
+        found.sort();
+        assert_eq!(found, expected, "[{}, {})", t0, t1);
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn manifest_survives_reopen() -> Result<()> {
+    let (dir, path) = temp_paths("reopen");
+    fs::create_dir_all(&dir)?;
+    {
+        let mut manifest = ChunkManifest::open(&path)?;
+        manifest.add(entry("a", 0, 99))?;
+        manifest.add(entry("b", 100, 199))?;
+        manifest.add(entry("c", 200, 299))?;
+        manifest.add(entry("d", 200, 250))?;
+        // Compaction replaced `a` and `b` with `ab`.
+        manifest.add(entry("ab", 0, 199))?;
+        assert!(manifest.remove(Path::new("a"))?);
+        assert!(manifest.remove(Path::new("b"))?);
+        assert!(!manifest.remove(Path::new("b"))?);
+    }
+
+    let manifest = ChunkManifest::open(&path)?;
+    // Ties on `ts_min` keep the order the chunks were added in.
+    assert_eq!(names(manifest.iter()), vec!["ab", "c", "d"]);
+    assert_eq!(manifest.get(Path::new("ab")), Some(&entry("ab", 0, 199)));
+    assert_eq!(manifest.get(Path::new("a")), None);
+    assert_eq!(names(manifest.candidates(150, 250)), vec!["ab", "c", "d"]);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn series_range_needs_u32_series_ids() -> Result<()> {
+    let (dir, _) = temp_paths("dtype");
+    fs::create_dir_all(&dir)?;
+    let ids: Vec<i64> = (0..100).map(|i| (i % 5) << 32).collect();
+    let columns = [
+        (DataType::I64, Column::I64(ids.clone())),
+        (
+            DataType::Utf8,
+            Column::Utf8(ids.iter().map(|id| format!("s{}", id)).collect()),
+        ),
+    ];
+    for (dtype, series_id) in columns {
+        let schema = Schema::new(vec![
+            Field::new(TS_COLUMN, DataType::I64),
+            Field::new(SERIES_ID_COLUMN, dtype),
+        ])?;
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Column::I64((0..100).collect()), series_id],
+        )?;
+        let chunk = dir.join("chunk.bin");
+        write_chunk(&chunk, &batch)?;
+        let entry = ChunkEntry::from_meta(chunk.clone(), &open_meta(&chunk)?);
+        assert_eq!(entry.series, None, "{:?}", dtype);
+        assert!(entry.may_contain_series(3, 3));
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn entry(name: &str, ts_min: i64, ts_max: i64) -> ChunkEntry {
+    ChunkEntry {
+        path: PathBuf::from(name),
+        row_count: 100,
+        ts_min,
+        ts_max,
+        series: Some((0, 9)),
+    }
+}
+
+fn names<'a>(entries: impl Iterator<Item = &'a ChunkEntry>) -> Vec<&'a str> {
+    entries
+        .map(|entry| entry.path.file_name().unwrap().to_str().unwrap())
+        .collect()
+}
+
+/// One hour of points every 10s, cycling through series `first..first + 10`.
+fn make_batch(start: i64, first: u32) -> RecordBatch {
+    let ts: Vec<i64> = (0..360).map(|i| start + i * 10).collect();
+    let series_id = (0..360).map(|i| first + i % 10).collect();
+    let value = (0..360).map(|i| i as f64).collect();
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_index_manifest_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunks.manifest");
+    (dir, path)
+}
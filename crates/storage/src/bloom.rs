@@ -0,0 +1,95 @@
+use common::error::{Error, Result};
+
+const MAX_HASHES: u32 = 16;
+
+/// A bloom filter over u32 keys, used for a chunk's series ids.
+///
+/// Bit positions come from double hashing a 64-bit mix of the key, so they
+/// are part of the chunk format and must not change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    words: Vec<u64>,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Sized for `items` keys at false positive rate `fpp`.
+    pub fn with_capacity(items: usize, fpp: f64) -> Self {
+        let items = items.max(1) as f64;
+        let fpp = fpp.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-items * fpp.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let num_hashes = ((bits / items) * ln2).round() as u32;
+        Self {
+            words: vec![0; (bits as usize).div_ceil(64)],
+            num_hashes: num_hashes.clamp(1, MAX_HASHES),
+        }
+    }
+
+    pub fn insert(&mut self, key: u32) {
+        let bits = self.num_bits();
+        for bit in positions(key, self.num_hashes, bits) {
+            self.words[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// False only if `key` was never inserted.
+    pub fn may_contain(&self, key: u32) -> bool {
+        let bits = self.num_bits();
+        positions(key, self.num_hashes, bits)
+            .all(|bit| self.words[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.words.len() * 64
+    }
+
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    /// `num_hashes: u32` followed by the bit words as little-endian u64s.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(4 + self.words.len() * 8);
+        payload.extend_from_slice(&self.num_hashes.to_le_bytes());
+        for word in &self.words {
+            payload.extend_from_slice(&word.to_le_bytes());
+        }
+        payload
+    }
+
+    pub(crate) fn decode(payload: &[u8]) -> Result<Self> {
+        if payload.len() < 4 + 8 || (payload.len() - 4) % 8 != 0 {
+            return Err(Error::Corrupt("bloom filter length mismatch".into()));
+        }
+        let num_hashes = u32::from_le_bytes(payload[..4].try_into().unwrap());
+        if !(1..=MAX_HASHES).contains(&num_hashes) {
+            return Err(Error::Corrupt(format!(
+                "bad bloom filter hash count: {}",
+                num_hashes
+            )));
+        }
+        let words = payload[4..]
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        Ok(Self { words, num_hashes })
+    }
+}
+
+fn positions(key: u32, num_hashes: u32, bits: usize) -> impl Iterator<Item = usize> {
+    let hash = mix64(key as u64);
+    let h1 = hash as u32 as u64;
+    // Odd, so the step between probes is never zero.
+    let h2 = (hash >> 32) | 1;
+    (0..num_hashes as u64)
+        .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits as u64) as usize)
+}
+
+/// The splitmix64 finalizer.
+fn mix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
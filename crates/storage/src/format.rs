@@ -4,22 +4,24 @@ use std::io::{Read, Write};
 use common::error::{Error, Result};
 
 pub const MAGIC: [u8; 4] = *b"TSDB";
-pub const VERSION: u16 = 1;
+pub const VERSION: u16 = 2;
+pub const MIN_VERSION: u16 = 1;
 pub const HEADER_LEN: usize = 16;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Header {
+    pub version: u16,
     pub meta_len: u32,
     pub meta_crc32: u32,
 }
 
-pub fn write_header(file: &mut File, header: &Header) -> Result<()> {
+pub fn write_header<W: Write>(out: &mut W, header: &Header) -> Result<()> {
     let header_len = HEADER_LEN as u16;
-    file.write_all(&MAGIC)?;
-    file.write_all(&VERSION.to_le_bytes())?;
-    file.write_all(&header_len.to_le_bytes())?;
-    file.write_all(&header.meta_len.to_le_bytes())?;
-    file.write_all(&header.meta_crc32.to_le_bytes())?;
+    out.write_all(&MAGIC)?;
+    out.write_all(&header.version.to_le_bytes())?;
+    out.write_all(&header_len.to_le_bytes())?;
+    out.write_all(&header.meta_len.to_le_bytes())?;
+    out.write_all(&header.meta_crc32.to_le_bytes())?;
     Ok(())
 }
 
@@ -33,7 +35,7 @@ pub fn read_header(file: &mut File) -> Result<Header> {
     let mut version_bytes = [0u8; 2];
     file.read_exact(&mut version_bytes)?;
     let version = u16::from_le_bytes(version_bytes);
-    if version != VERSION {
+    if !(MIN_VERSION..=VERSION).contains(&version) {
         return Err(Error::Unsupported(format!(
             "unsupported version: {}",
             version
@@ -56,6 +58,7 @@ pub fn read_header(file: &mut File) -> Result<Header> {
     let meta_crc32 = u32::from_le_bytes(meta_crc_bytes);
 
     Ok(Header {
+        version,
         meta_len,
         meta_crc32,
     })
@@ -1,4 +1,6 @@
+pub mod bloom;
 pub mod format;
 pub mod meta;
 pub mod reader;
+pub mod stats;
 pub mod writer;
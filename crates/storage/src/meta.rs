@@ -1,4 +1,34 @@
+use std::collections::HashSet;
+
 use common::error::{Error, Result};
+use datamodel::schema::{DataType, Field, Schema};
+
+use crate::bloom::BloomFilter;
+pub use crate::stats::{ColumnStats, StatValue};
+
+pub const ENCODING_RAW: u16 = 0;
+pub const ENCODING_DELTA_OF_DELTA: u16 = 1;
+pub const ENCODING_XOR: u16 = 2;
+pub const ENCODING_RLE: u16 = 3;
+pub const ENCODING_DICT: u16 = 4;
+
+const STAT_INT: u8 = 0;
+const STAT_FLOAT: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct BlockMeta {
+    pub row_offset: u32,
+    pub row_count: u32,
+    pub first_ts: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ColumnBlock {
+    pub offset: u64,
+    pub len: u64,
+    pub stats: Option<ColumnStats>,
+    pub crc32: Option<u32>,
+}
 
 #[derive(Debug, Clone)]
 pub struct ColumnMeta {
@@ -6,6 +36,22 @@ pub struct ColumnMeta {
     pub encoding: u16,
     pub offset: u64,
     pub len: u64,
+    pub stats: Option<ColumnStats>,
+    pub blocks: Vec<ColumnBlock>,
+    /// Only present when the column has nulls.
+    pub validity: Option<ValidityMeta>,
+    /// Strings of a utf8 column, indexed by the u32 codes stored in its
+    /// blocks. Stats of such a column describe the codes.
+    pub dictionary: Option<Vec<String>>,
+}
+
+/// A column's validity bitmap: one bit per chunk row, packed LSB first and
+/// stored after all column data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValidityMeta {
+    pub offset: u64,
+    pub len: u64,
+    pub crc32: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -13,14 +59,79 @@ pub struct ChunkMeta {
     pub row_count: u32,
     pub ts_min: i64,
     pub ts_max: i64,
+    pub blocks: Vec<BlockMeta>,
+    /// Column `col_id` holds field `col_id` of the schema.
+    pub schema: Schema,
     pub cols: Vec<ColumnMeta>,
+    /// Every `series_id` in the chunk, when the writer was asked for one.
+    pub series_bloom: Option<BloomFilter>,
 }
 
+impl ChunkMeta {
+    pub fn col(&self, col_id: u16) -> Option<&ColumnMeta> {
+        self.cols.iter().find(|col| col.col_id == col_id)
+    }
+
+    pub fn col_named(&self, name: &str) -> Option<&ColumnMeta> {
+        self.col(self.schema.index_of(name)? as u16)
+    }
+
+    /// Index of the block holding `row`; `row` must be below `row_count`.
+    pub fn block_of(&self, row: usize) -> usize {
+        self.blocks
+            .partition_point(|block| (block.row_offset + block.row_count) as usize <= row)
+    }
+}
+
+/// The type a column's blocks hold: utf8 columns store u32 dictionary codes.
+pub(crate) fn stored_type(dtype: DataType) -> DataType {
+    match dtype {
+        DataType::Utf8 => DataType::U32,
+        dtype => dtype,
+    }
+}
+
+/// Meta sections with this bit set change how chunk data must be read; readers
+/// reject unknown required sections and skip unknown optional ones. Each
+/// section appears at most once, with or without this bit.
+pub const SECTION_REQUIRED: u16 = 0x8000;
+/// CRC32 of every column block, column-major.
+pub const SECTION_BLOCK_CRC32: u16 = 0x0001;
+/// Field names and types. Absent when the chunk uses `Schema::time_series()`,
+/// otherwise written as a required section.
+pub const SECTION_SCHEMA: u16 = 0x0002;
+/// Validity bitmap extents for columns with nulls. Written as a required
+/// section, and only when some column has nulls.
+pub const SECTION_VALIDITY: u16 = 0x0003;
+/// Per-chunk dictionaries of utf8 columns. Written as a required section,
+/// and only when the schema has a utf8 column.
+pub const SECTION_DICTIONARY: u16 = 0x0004;
+/// Bloom filter over the chunk's series ids. Optional: readers that skip it
+/// just lose the pruning.
+pub const SECTION_SERIES_BLOOM: u16 = 0x0005;
+
+const V1_BASE_LEN: usize = 4 + 8 + 8 + 4;
+const V1_COL_LEN: usize = 2 + 2 + 8 + 8;
+const V2_BASE_LEN: usize = 4 + 8 + 8 + 4 + 4 + 2;
+const V2_BLOCK_LEN: usize = 4 + 4 + 8;
+const V2_COL_MIN_LEN: usize = 2 + 2 + 8 + 8 + 1;
+const V2_COL_BLOCK_MIN_LEN: usize = 8 + 8 + 1;
+const V2_SECTION_MIN_LEN: usize = 2 + 4;
+const VALIDITY_ENTRY_LEN: usize = 2 + 8 + 8 + 4;
+
+/// Encodes `meta` in the current format version.
 pub fn encode_meta(meta: &ChunkMeta) -> Vec<u8> {
     let mut buf = Vec::new();
     buf.extend_from_slice(&meta.row_count.to_le_bytes());
     buf.extend_from_slice(&meta.ts_min.to_le_bytes());
     buf.extend_from_slice(&meta.ts_max.to_le_bytes());
+    let block_count = meta.blocks.len() as u32;
+    buf.extend_from_slice(&block_count.to_le_bytes());
+    for block in &meta.blocks {
+        buf.extend_from_slice(&block.row_offset.to_le_bytes());
+        buf.extend_from_slice(&block.row_count.to_le_bytes());
+        buf.extend_from_slice(&block.first_ts.to_le_bytes());
+    }
     let col_count = meta.cols.len() as u32;
     buf.extend_from_slice(&col_count.to_le_bytes());
     for col in &meta.cols {
@@ -28,62 +139,557 @@ pub fn encode_meta(meta: &ChunkMeta) -> Vec<u8> {
         buf.extend_from_slice(&col.encoding.to_le_bytes());
         buf.extend_from_slice(&col.offset.to_le_bytes());
         buf.extend_from_slice(&col.len.to_le_bytes());
+        encode_stats(&mut buf, col.stats.as_ref());
+        for block in &col.blocks {
+            buf.extend_from_slice(&block.offset.to_le_bytes());
+            buf.extend_from_slice(&block.len.to_le_bytes());
+            encode_stats(&mut buf, block.stats.as_ref());
+        }
+    }
+    let mut sections = Vec::new();
+    if let Some(crcs) = encode_block_crcs(meta) {
+        sections.push((SECTION_BLOCK_CRC32, crcs));
+    }
+    if meta.schema != Schema::time_series() {
+        sections.push((
+            SECTION_REQUIRED | SECTION_SCHEMA,
+            encode_schema(&meta.schema),
+        ));
+    }
+    if let Some(validity) = encode_validity(meta) {
+        sections.push((SECTION_REQUIRED | SECTION_VALIDITY, validity));
+    }
+    if let Some(dictionaries) = encode_dictionaries(meta) {
+        sections.push((SECTION_REQUIRED | SECTION_DICTIONARY, dictionaries));
+    }
+    if let Some(bloom) = &meta.series_bloom {
+        sections.push((SECTION_SERIES_BLOOM, bloom.encode()));
+    }
+    buf.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+    for (tag, payload) in sections {
+        buf.extend_from_slice(&tag.to_le_bytes());
+        buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&payload);
     }
     buf
 }
 
-pub fn decode_meta(buf: &[u8]) -> Result<ChunkMeta> {
-    const BASE_LEN: usize = 4 + 8 + 8 + 4;
-    const COL_LEN: usize = 2 + 2 + 8 + 8;
+/// Only written when every block carries a checksum.
+fn encode_block_crcs(meta: &ChunkMeta) -> Option<Vec<u8>> {
+    let mut payload = Vec::new();
+    for col in &meta.cols {
+        for block in &col.blocks {
+            payload.extend_from_slice(&block.crc32?.to_le_bytes());
+        }
+    }
+    if payload.is_empty() {
+        return None;
+    }
+    Some(payload)
+}
+
+fn decode_block_crcs(payload: &[u8], cols: &mut [ColumnMeta]) -> Result<()> {
+    let block_count: usize = cols.iter().map(|col| col.blocks.len()).sum();
+    if payload.len() != block_count * 4 {
+        return Err(Error::Corrupt("block crc section length mismatch".into()));
+    }
+    let mut crcs = payload.chunks_exact(4);
+    for col in cols {
+        for block in &mut col.blocks {
+            block.crc32 = Some(u32::from_le_bytes(crcs.next().unwrap().try_into().unwrap()));
+        }
+    }
+    Ok(())
+}
+
+fn encode_validity(meta: &ChunkMeta) -> Option<Vec<u8>> {
+    let entries: Vec<_> = meta
+        .cols
+        .iter()
+        .filter_map(|col| Some((col.col_id, col.validity?)))
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    let mut payload = Vec::with_capacity(2 + entries.len() * VALIDITY_ENTRY_LEN);
+    payload.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (col_id, validity) in entries {
+        payload.extend_from_slice(&col_id.to_le_bytes());
+        payload.extend_from_slice(&validity.offset.to_le_bytes());
+        payload.extend_from_slice(&validity.len.to_le_bytes());
+        payload.extend_from_slice(&validity.crc32.to_le_bytes());
+    }
+    Some(payload)
+}
+
+fn decode_validity(payload: &[u8], row_count: u32, cols: &mut [ColumnMeta]) -> Result<()> {
+    let mut cursor = Cursor {
+        buf: payload,
+        pos: 0,
+    };
+    let entry_count = cursor.u16()? as usize;
+    if payload.len() != 2 + entry_count * VALIDITY_ENTRY_LEN {
+        return Err(Error::Corrupt("validity section length mismatch".into()));
+    }
+    let bitmap_len = (row_count as u64).div_ceil(8);
+    for _ in 0..entry_count {
+        let col_id = cursor.u16()?;
+        let validity = ValidityMeta {
+            offset: cursor.u64()?,
+            len: cursor.u64()?,
+            crc32: cursor.u32()?,
+        };
+        if validity.len != bitmap_len {
+            return Err(Error::Corrupt("validity bitmap length mismatch".into()));
+        }
+        let col = cols
+            .iter_mut()
+            .find(|col| col.col_id == col_id)
+            .ok_or_else(|| Error::Corrupt("validity for missing column".into()))?;
+        if col.validity.replace(validity).is_some() {
+            return Err(Error::Corrupt("duplicate validity bitmap".into()));
+        }
+    }
+    Ok(())
+}
+
+fn encode_dictionaries(meta: &ChunkMeta) -> Option<Vec<u8>> {
+    let entries: Vec<_> = meta
+        .cols
+        .iter()
+        .filter_map(|col| Some((col.col_id, col.dictionary.as_ref()?)))
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for (col_id, dictionary) in entries {
+        payload.extend_from_slice(&col_id.to_le_bytes());
+        payload.extend_from_slice(&(dictionary.len() as u32).to_le_bytes());
+        for value in dictionary {
+            payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            payload.extend_from_slice(value.as_bytes());
+        }
+    }
+    Some(payload)
+}
+
+fn decode_dictionaries(payload: &[u8], cols: &mut [ColumnMeta]) -> Result<()> {
+    let mut cursor = Cursor {
+        buf: payload,
+        pos: 0,
+    };
+    let entry_count = cursor.u16()?;
+    for _ in 0..entry_count {
+        let col_id = cursor.u16()?;
+        let value_count = cursor.count(4)?;
+        let mut dictionary = Vec::with_capacity(value_count);
+        for _ in 0..value_count {
+            let len = usize::try_from(cursor.u32()?)
+                .map_err(|_| Error::Corrupt("dictionary value too long".into()))?;
+            let value = std::str::from_utf8(cursor.bytes(len)?)
+                .map_err(|_| Error::Corrupt("dictionary value is not utf-8".into()))?;
+            dictionary.push(value.to_string());
+        }
+        let col = cols
+            .iter_mut()
+            .find(|col| col.col_id == col_id)
+            .ok_or_else(|| Error::Corrupt("dictionary for missing column".into()))?;
+        if col.dictionary.replace(dictionary).is_some() {
+            return Err(Error::Corrupt("duplicate dictionary".into()));
+        }
+    }
+    if cursor.pos != payload.len() {
+        return Err(Error::Corrupt("dictionary section length mismatch".into()));
+    }
+    Ok(())
+}
+
+fn encode_schema(schema: &Schema) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&(schema.len() as u16).to_le_bytes());
+    for field in schema.fields() {
+        payload.push(field.dtype.id());
+        payload.extend_from_slice(&(field.name.len() as u16).to_le_bytes());
+        payload.extend_from_slice(field.name.as_bytes());
+    }
+    payload
+}
+
+fn decode_schema(payload: &[u8]) -> Result<Schema> {
+    let mut cursor = Cursor {
+        buf: payload,
+        pos: 0,
+    };
+    let field_count = cursor.u16()? as usize;
+    let mut fields = Vec::with_capacity(field_count);
+    for _ in 0..field_count {
+        let dtype = cursor.u8()?;
+        let dtype = DataType::from_id(dtype)
+            .ok_or_else(|| Error::Unsupported(format!("unsupported data type: {}", dtype)))?;
+        let name_len = cursor.u16()? as usize;
+        let name = std::str::from_utf8(cursor.bytes(name_len)?)
+            .map_err(|_| Error::Corrupt("field name is not utf-8".into()))?;
+        fields.push(Field::new(name, dtype));
+    }
+    if cursor.pos != payload.len() {
+        return Err(Error::Corrupt("schema section length mismatch".into()));
+    }
+    Schema::new(fields).map_err(|_| Error::Corrupt("invalid schema".into()))
+}
+
+/// Every schema field needs exactly one column with its index as `col_id`,
+/// and exactly the utf8 columns carry a dictionary.
+fn check_schema(schema: &Schema, cols: &[ColumnMeta]) -> Result<()> {
+    if cols.len() != schema.len() {
+        return Err(Error::Corrupt("column count does not match schema".into()));
+    }
+    for col in cols {
+        let is_utf8 = schema
+            .fields()
+            .get(col.col_id as usize)
+            .is_some_and(|field| field.dtype == DataType::Utf8);
+        if is_utf8 != col.dictionary.is_some() {
+            return Err(Error::Corrupt(format!(
+                "column {} dictionary does not match its type",
+                col.col_id
+            )));
+        }
+    }
+    for col_id in 0..schema.len() {
+        if cols
+            .iter()
+            .filter(|col| col.col_id as usize == col_id)
+            .count()
+            != 1
+        {
+            return Err(Error::Corrupt(format!(
+                "schema field {} has no unique column",
+                col_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn encode_stats(buf: &mut Vec<u8>, stats: Option<&ColumnStats>) {
+    let stats = match stats {
+        Some(stats) => stats,
+        None => {
+            buf.push(0);
+            return;
+        }
+    };
+    buf.push(1);
+    buf.extend_from_slice(&stats.count.to_le_bytes());
+    buf.extend_from_slice(&stats.null_count.to_le_bytes());
+    encode_stat_value(buf, stats.min);
+    encode_stat_value(buf, stats.max);
+    buf.extend_from_slice(&stats.sum.to_le_bytes());
+    buf.extend_from_slice(&stats.distinct.to_le_bytes());
+}
+
+fn encode_stat_value(buf: &mut Vec<u8>, value: StatValue) {
+    match value {
+        StatValue::Int(v) => {
+            buf.push(STAT_INT);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        StatValue::Float(v) => {
+            buf.push(STAT_FLOAT);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+}
 
-    if buf.len() < BASE_LEN {
+/// Decodes a meta section written with format `version`.
+pub fn decode_meta(buf: &[u8], version: u16) -> Result<ChunkMeta> {
+    match version {
+        1 => decode_meta_v1(buf),
+        2 => decode_meta_v2(buf),
+        _ => Err(Error::Unsupported(format!(
+            "unsupported version: {}",
+            version
+        ))),
+    }
+}
+
+/// Version 1 chunks have no block index or statistics; each column is
+/// exposed as a single block spanning the whole chunk.
+fn decode_meta_v1(buf: &[u8]) -> Result<ChunkMeta> {
+    if buf.len() < V1_BASE_LEN {
         return Err(Error::Corrupt("meta too short".into()));
     }
 
-    let mut offset = 0;
-    let row_count = u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap());
-    offset += 4;
-    let ts_min = i64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
-    offset += 8;
-    let ts_max = i64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
-    offset += 8;
-    let col_count = u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap());
-    offset += 4;
-
-    let col_count_usize =
-        usize::try_from(col_count).map_err(|_| Error::Corrupt("col_count overflow".into()))?;
-    let cols_len = col_count_usize
-        .checked_mul(COL_LEN)
-        .ok_or_else(|| Error::Corrupt("col_count too large".into()))?;
-    let expected_len = BASE_LEN
-        .checked_add(cols_len)
-        .ok_or_else(|| Error::Corrupt("meta length overflow".into()))?;
-    if buf.len() != expected_len {
+    let mut cursor = Cursor { buf, pos: 0 };
+    let row_count = cursor.u32()?;
+    let ts_min = cursor.i64()?;
+    let ts_max = cursor.i64()?;
+    let col_count = cursor.count(V1_COL_LEN)?;
+    if buf.len() != V1_BASE_LEN + col_count * V1_COL_LEN {
         return Err(Error::Corrupt("meta length mismatch".into()));
     }
 
-    let mut cols = Vec::with_capacity(col_count_usize);
-    for _ in 0..col_count_usize {
-        let col_id = u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap());
-        offset += 2;
-        let encoding = u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap());
-        offset += 2;
-        let col_offset = u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
-        offset += 8;
-        let len = u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
-        offset += 8;
+    let blocks = if row_count == 0 {
+        Vec::new()
+    } else {
+        vec![BlockMeta {
+            row_offset: 0,
+            row_count,
+            first_ts: ts_min,
+        }]
+    };
+
+    let mut cols = Vec::with_capacity(col_count);
+    for _ in 0..col_count {
+        let col_id = cursor.u16()?;
+        let encoding = cursor.u16()?;
+        let offset = cursor.u64()?;
+        let len = cursor.u64()?;
+        let col_blocks = blocks
+            .iter()
+            .map(|_| ColumnBlock {
+                offset,
+                len,
+                stats: None,
+                crc32: None,
+            })
+            .collect();
+        cols.push(ColumnMeta {
+            col_id,
+            encoding,
+            offset,
+            len,
+            stats: None,
+            blocks: col_blocks,
+            validity: None,
+            dictionary: None,
+        });
+    }
+
+    let schema = Schema::time_series();
+    check_schema(&schema, &cols)?;
+    Ok(ChunkMeta {
+        row_count,
+        ts_min,
+        ts_max,
+        blocks,
+        schema,
+        cols,
+        series_bloom: None,
+    })
+}
+
+fn decode_meta_v2(buf: &[u8]) -> Result<ChunkMeta> {
+    if buf.len() < V2_BASE_LEN {
+        return Err(Error::Corrupt("meta too short".into()));
+    }
+
+    let mut cursor = Cursor { buf, pos: 0 };
+    let row_count = cursor.u32()?;
+    let ts_min = cursor.i64()?;
+    let ts_max = cursor.i64()?;
+
+    let block_count = cursor.count(V2_BLOCK_LEN)?;
+    let mut blocks = Vec::with_capacity(block_count);
+    let mut next_row = 0u32;
+    for _ in 0..block_count {
+        let row_offset = cursor.u32()?;
+        let block_rows = cursor.u32()?;
+        let first_ts = cursor.i64()?;
+        if row_offset != next_row || block_rows == 0 {
+            return Err(Error::Corrupt("block rows not contiguous".into()));
+        }
+        next_row = next_row
+            .checked_add(block_rows)
+            .ok_or_else(|| Error::Corrupt("block row overflow".into()))?;
+        blocks.push(BlockMeta {
+            row_offset,
+            row_count: block_rows,
+            first_ts,
+        });
+    }
+    if next_row != row_count {
+        return Err(Error::Corrupt("block rows do not cover chunk".into()));
+    }
+
+    let col_count = cursor.count(V2_COL_MIN_LEN + block_count * V2_COL_BLOCK_MIN_LEN)?;
+    let mut cols = Vec::with_capacity(col_count);
+    for _ in 0..col_count {
+        let col_id = cursor.u16()?;
+        let encoding = cursor.u16()?;
+        let col_offset = cursor.u64()?;
+        let len = cursor.u64()?;
+        let stats = cursor.stats()?;
+        let mut col_blocks = Vec::with_capacity(block_count);
+        let mut next_offset = col_offset;
+        for _ in 0..block_count {
+            let offset = cursor.u64()?;
+            let block_len = cursor.u64()?;
+            let block_stats = cursor.stats()?;
+            if offset != next_offset {
+                return Err(Error::Corrupt("column blocks not contiguous".into()));
+            }
+            next_offset = offset
+                .checked_add(block_len)
+                .ok_or_else(|| Error::Corrupt("block offset overflow".into()))?;
+            col_blocks.push(ColumnBlock {
+                offset,
+                len: block_len,
+                stats: block_stats,
+                crc32: None,
+            });
+        }
+        if next_offset - col_offset != len {
+            return Err(Error::Corrupt("column blocks do not cover column".into()));
+        }
         cols.push(ColumnMeta {
             col_id,
             encoding,
             offset: col_offset,
             len,
+            stats,
+            blocks: col_blocks,
+            validity: None,
+            dictionary: None,
         });
     }
 
+    let section_count = cursor.u16()? as usize;
+    if section_count * V2_SECTION_MIN_LEN > buf.len() - cursor.pos {
+        return Err(Error::Corrupt("meta length mismatch".into()));
+    }
+    let mut schema = Schema::time_series();
+    let mut series_bloom = None;
+    let mut seen = HashSet::new();
+    for _ in 0..section_count {
+        let tag = cursor.u16()?;
+        if !seen.insert(tag & !SECTION_REQUIRED) {
+            return Err(Error::Corrupt("duplicate meta section".into()));
+        }
+        let len = usize::try_from(cursor.u32()?)
+            .map_err(|_| Error::Corrupt("section length overflow".into()))?;
+        let payload = cursor.bytes(len)?;
+        match tag {
+            SECTION_BLOCK_CRC32 => decode_block_crcs(payload, &mut cols)?,
+            _ if tag & !SECTION_REQUIRED == SECTION_SCHEMA => schema = decode_schema(payload)?,
+            _ if tag & !SECTION_REQUIRED == SECTION_VALIDITY => {
+                decode_validity(payload, row_count, &mut cols)?
+            }
+            _ if tag & !SECTION_REQUIRED == SECTION_DICTIONARY => {
+                decode_dictionaries(payload, &mut cols)?
+            }
+            SECTION_SERIES_BLOOM => series_bloom = Some(BloomFilter::decode(payload)?),
+            _ if tag & SECTION_REQUIRED != 0 => {
+                return Err(Error::Unsupported(format!(
+                    "unsupported meta section: {:#06x}",
+                    tag
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    if cursor.pos != buf.len() {
+        return Err(Error::Corrupt("meta length mismatch".into()));
+    }
+    check_schema(&schema, &cols)?;
+
     Ok(ChunkMeta {
         row_count,
         ts_min,
         ts_max,
+        blocks,
+        schema,
         cols,
+        series_bloom,
     })
 }
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.buf.len() - self.pos {
+            return Err(Error::Corrupt("meta length mismatch".into()));
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let end = self.pos + N;
+        if end > self.buf.len() {
+            return Err(Error::Corrupt("meta length mismatch".into()));
+        }
+        let bytes = self.buf[self.pos..end].try_into().unwrap();
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.take()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+
+    fn stats(&mut self) -> Result<Option<ColumnStats>> {
+        match self.u8()? {
+            0 => return Ok(None),
+            1 => {}
+            _ => return Err(Error::Corrupt("bad stats flag".into())),
+        }
+        Ok(Some(ColumnStats {
+            count: self.u32()?,
+            null_count: self.u32()?,
+            min: self.stat_value()?,
+            max: self.stat_value()?,
+            sum: self.f64()?,
+            distinct: self.u32()?,
+        }))
+    }
+
+    fn stat_value(&mut self) -> Result<StatValue> {
+        match self.u8()? {
+            STAT_INT => Ok(StatValue::Int(self.i64()?)),
+            STAT_FLOAT => Ok(StatValue::Float(self.f64()?)),
+            _ => Err(Error::Corrupt("unknown stat value tag".into())),
+        }
+    }
+
+    /// Reads a u32 element count and checks the elements can fit in the rest of the buffer.
+    fn count(&mut self, elem_len: usize) -> Result<usize> {
+        let count =
+            usize::try_from(self.u32()?).map_err(|_| Error::Corrupt("count overflow".into()))?;
+        let needed = count
+            .checked_mul(elem_len)
+            .ok_or_else(|| Error::Corrupt("count too large".into()))?;
+        if needed > self.buf.len() - self.pos {
+            return Err(Error::Corrupt("meta length mismatch".into()));
+        }
+        Ok(count)
+    }
+}
@@ -1,81 +1,272 @@
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{self, Read};
+use std::mem;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
 
 use common::error::{Error, Result};
 use crc32fast::Hasher;
-use datamodel::batch::RecordBatch;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::{DataType, TS_COLUMN};
+use memmap2::Mmap;
 
 use crate::format;
-use crate::meta::{ChunkMeta, ColumnMeta};
+use crate::meta::{
+    self, ChunkMeta, ColumnMeta, ENCODING_DELTA_OF_DELTA, ENCODING_DICT, ENCODING_RAW,
+    ENCODING_RLE, ENCODING_XOR,
+};
 
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOptions {
+    /// Map the whole file instead of reading each range into a fresh buffer.
+    /// Required for the zero-copy `slice_*` accessors.
+    pub mmap: bool,
+    pub verify_checksums: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            mmap: false,
+            verify_checksums: true,
+        }
+    }
+}
+
+/// An open chunk. Reads take `&self` and never move a shared file cursor, so
+/// the handle is `Send + Sync`; clones share the same file or mapping and
+/// only copy the meta.
+#[derive(Clone)]
 pub struct ChunkFile {
     pub meta: ChunkMeta,
-    file: File,
+    header: format::Header,
+    source: Arc<Source>,
+    verify_checksums: bool,
+}
+
+enum Source {
+    File(File),
+    Mmap(Mmap),
+}
+
+impl Source {
+    /// Positional read that leaves the file cursor alone, so any number of
+    /// threads can read through a shared handle.
+    fn read_at(&self, offset: u64, len: usize) -> Result<Cow<'_, [u8]>> {
+        match self {
+            Source::File(file) => {
+                let mut buf = vec![0u8; len];
+                read_exact_at(file, &mut buf, offset)?;
+                Ok(Cow::Owned(buf))
+            }
+            Source::Mmap(map) => Ok(Cow::Borrowed(map_range(map, offset, len)?)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn map_range(map: &Mmap, offset: u64, len: usize) -> Result<&[u8]> {
+    usize::try_from(offset)
+        .ok()
+        .and_then(|start| Some(start..start.checked_add(len)?))
+        .and_then(|range| map.get(range))
+        .ok_or_else(|| Error::Corrupt("range exceeds chunk file".into()))
+}
+
+/// Fixed-width values for which every bit pattern is valid, so a raw column
+/// can be viewed in place.
+trait RawValue: Copy {
+    const DTYPE: DataType;
+}
+
+impl RawValue for i64 {
+    const DTYPE: DataType = DataType::I64;
+}
+
+impl RawValue for u32 {
+    const DTYPE: DataType = DataType::U32;
+}
+
+impl RawValue for f64 {
+    const DTYPE: DataType = DataType::F64;
 }
 
 impl ChunkFile {
-    pub fn read_ts_at(&mut self, idx: usize) -> Result<i64> {
+    /// Block checksums are verified on every read by default. Turning this off
+    /// lets raw range reads fetch just the requested rows instead of whole blocks.
+    pub fn set_verify_checksums(&mut self, verify: bool) {
+        self.verify_checksums = verify;
+    }
+
+    pub fn is_mmap(&self) -> bool {
+        matches!(*self.source, Source::Mmap(_))
+    }
+
+    /// Borrows rows `start..end` of a raw column straight from the mapping.
+    /// Fails with `Unsupported` unless the chunk was opened with `mmap` and
+    /// the column is raw encoded. The writer aligns every column, so only
+    /// a chunk from elsewhere can also fail on alignment.
+    pub fn slice_i64(&self, col_id: u16, start: usize, end: usize) -> Result<&[i64]> {
+        self.raw_slice(col_id, start, end)
+    }
+
+    pub fn slice_u32(&self, col_id: u16, start: usize, end: usize) -> Result<&[u32]> {
+        self.raw_slice(col_id, start, end)
+    }
+
+    pub fn slice_f64(&self, col_id: u16, start: usize, end: usize) -> Result<&[f64]> {
+        self.raw_slice(col_id, start, end)
+    }
+
+    pub fn read_ts_at(&self, idx: usize) -> Result<i64> {
         if idx >= self.meta.row_count as usize {
             return Err(Error::Corrupt("ts index out of bounds".into()));
         }
-        let ts_col = self
+        let ts = self.read_range_i64(self.ts_col_id()?, idx, idx + 1)?;
+        Ok(ts[0])
+    }
+
+    /// First row whose ts is `>= target`, assuming the chunk is sorted by ts.
+    /// Only the block that can contain the boundary is decoded.
+    pub fn lower_bound_ts(&self, target: i64) -> Result<usize> {
+        let first_after = self
             .meta
-            .cols
-            .iter()
-            .find(|col| col.col_id == 0)
-            .ok_or_else(|| Error::Corrupt("missing ts column".into()))?;
-        if ts_col.encoding != 0 {
-            return Err(Error::Unsupported("unsupported encoding".into()));
+            .blocks
+            .partition_point(|block| block.first_ts < target);
+        if first_after == 0 {
+            return Ok(0);
         }
+        let row_offset = self.meta.blocks[first_after - 1].row_offset as usize;
+        let col = self.find_col(self.ts_col_id()?)?;
+        let ts = read_blocks(
+            &self.source,
+            col,
+            &self.meta,
+            first_after - 1..first_after,
+            self.verify_checksums,
+            decode_i64,
+        )?;
+        let pos = ts.partition_point(|&ts| ts < target);
+        Ok(row_offset + pos)
+    }
 
-        let pos = ts_col
-            .offset
-            .checked_add((idx as u64) * 8)
-            .ok_or_else(|| Error::Corrupt("ts offset overflow".into()))?;
-        self.file.seek(SeekFrom::Start(pos))?;
-        let mut buf = [0u8; 8];
-        self.file.read_exact(&mut buf)?;
-        Ok(i64::from_le_bytes(buf))
+    /// Rows `start..end` of any column, typed by the chunk schema.
+    pub fn read_column(&self, col_id: u16, start: usize, end: usize) -> Result<Column> {
+        let field = self
+            .meta
+            .schema
+            .fields()
+            .get(col_id as usize)
+            .ok_or_else(|| Error::Corrupt("missing column".into()))?;
+        Ok(match field.dtype {
+            DataType::I64 => Column::I64(self.read_range_i64(col_id, start, end)?),
+            DataType::U32 => Column::U32(self.read_range_u32(col_id, start, end)?),
+            DataType::F64 => Column::F64(self.read_range_f64(col_id, start, end)?),
+            DataType::Utf8 => {
+                let codes = self.read_range_u32(col_id, start, end)?;
+                Column::Utf8(lookup_codes(self.find_col(col_id)?, &codes)?)
+            }
+        })
     }
 
-    pub fn read_range_i64(&mut self, col_id: u16, start: usize, end: usize) -> Result<Vec<i64>> {
-        let col = self.find_col(col_id)?.clone();
-        if col.encoding != 0 {
-            return Err(Error::Unsupported("unsupported encoding".into()));
-        }
-        let buf = self.read_range_bytes(&col, start, end, 8)?;
-        let mut out = Vec::with_capacity(buf.len() / 8);
-        for chunk in buf.chunks_exact(8) {
-            out.push(i64::from_le_bytes(chunk.try_into().unwrap()));
-        }
-        Ok(out)
+    /// Validity of rows `start..end`, or `None` when none of them is null.
+    /// Reads the whole bitmap; callers reading many ranges of one column
+    /// use `read_chunk_validity` once and slice it.
+    pub fn read_validity(&self, col_id: u16, start: usize, end: usize) -> Result<Option<Bitmap>> {
+        check_range(&self.meta, start, end)?;
+        let bitmap = match self.read_chunk_validity(col_id)? {
+            Some(bitmap) => bitmap.slice(start..end),
+            None => return Ok(None),
+        };
+        Ok((bitmap.null_count() > 0).then_some(bitmap))
     }
 
-    pub fn read_range_u32(&mut self, col_id: u16, start: usize, end: usize) -> Result<Vec<u32>> {
-        let col = self.find_col(col_id)?.clone();
-        if col.encoding != 0 {
-            return Err(Error::Unsupported("unsupported encoding".into()));
-        }
-        let buf = self.read_range_bytes(&col, start, end, 4)?;
-        let mut out = Vec::with_capacity(buf.len() / 4);
-        for chunk in buf.chunks_exact(4) {
-            out.push(u32::from_le_bytes(chunk.try_into().unwrap()));
-        }
-        Ok(out)
+    /// Validity of every row, or `None` when the column has no nulls.
+    pub fn read_chunk_validity(&self, col_id: u16) -> Result<Option<Bitmap>> {
+        self.read_column_validity(self.find_col(col_id)?)
+    }
+
+    pub fn read_range_i64(&self, col_id: u16, start: usize, end: usize) -> Result<Vec<i64>> {
+        self.read_range(col_id, start, end, decode_i64)
+    }
+
+    pub fn read_range_u32(&self, col_id: u16, start: usize, end: usize) -> Result<Vec<u32>> {
+        self.read_range(col_id, start, end, decode_u32)
+    }
+
+    pub fn read_range_f64(&self, col_id: u16, start: usize, end: usize) -> Result<Vec<f64>> {
+        self.read_range(col_id, start, end, decode_f64)
+    }
+
+    fn ts_col_id(&self) -> Result<u16> {
+        self.meta
+            .schema
+            .index_of(TS_COLUMN)
+            .map(|idx| idx as u16)
+            .ok_or_else(|| Error::Corrupt("missing ts column".into()))
     }
 
-    pub fn read_range_f64(&mut self, col_id: u16, start: usize, end: usize) -> Result<Vec<f64>> {
-        let col = self.find_col(col_id)?.clone();
-        if col.encoding != 0 {
-            return Err(Error::Unsupported("unsupported encoding".into()));
+    /// Also checks the column stores values of type `T`, so a typed read
+    /// cannot reinterpret another type's raw bytes. A utf8 column reads as
+    /// its u32 dictionary codes.
+    fn typed_col<T: RawValue>(&self, col_id: u16) -> Result<&ColumnMeta> {
+        match self.meta.schema.fields().get(col_id as usize) {
+            Some(field) if meta::stored_type(field.dtype) == T::DTYPE => self.find_col(col_id),
+            Some(field) => Err(Error::Unsupported(format!(
+                "column {} has type {:?}, not {:?}",
+                col_id,
+                field.dtype,
+                T::DTYPE
+            ))),
+            None => Err(Error::Corrupt("missing column".into())),
         }
-        let buf = self.read_range_bytes(&col, start, end, 8)?;
-        let mut out = Vec::with_capacity(buf.len() / 8);
-        for chunk in buf.chunks_exact(8) {
-            out.push(f64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+
+    /// The whole bitmap is read at once; at one bit per row it is far
+    /// smaller than any block of column data.
+    fn read_column_validity(&self, col: &ColumnMeta) -> Result<Option<Bitmap>> {
+        let validity = match col.validity {
+            Some(validity) => validity,
+            None => return Ok(None),
+        };
+        let len = usize::try_from(validity.len)
+            .map_err(|_| Error::Corrupt("validity len overflow".into()))?;
+        let bytes = self.source.read_at(validity.offset, len)?;
+        if self.verify_checksums {
+            let mut hasher = Hasher::new();
+            hasher.update(&bytes);
+            if hasher.finalize() != validity.crc32 {
+                return Err(Error::Corrupt(format!(
+                    "crc mismatch in column {} validity",
+                    col.col_id
+                )));
+            }
         }
-        Ok(out)
+        Bitmap::from_bytes(bytes.into_owned(), self.meta.row_count as usize).map(Some)
     }
 
     fn find_col(&self, col_id: u16) -> Result<&ColumnMeta> {
@@ -86,51 +277,148 @@ impl ChunkFile {
             .ok_or_else(|| Error::Corrupt("missing column".into()))
     }
 
-    fn read_range_bytes(
-        &mut self,
-        col: &ColumnMeta,
+    fn raw_slice<T: RawValue>(&self, col_id: u16, start: usize, end: usize) -> Result<&[T]> {
+        let map = match &*self.source {
+            Source::Mmap(map) => map,
+            Source::File(_) => {
+                return Err(Error::Unsupported(
+                    "zero-copy slices need a memory-mapped chunk".into(),
+                ))
+            }
+        };
+        if cfg!(target_endian = "big") {
+            return Err(Error::Unsupported(
+                "zero-copy slices need a little-endian host".into(),
+            ));
+        }
+        check_range(&self.meta, start, end)?;
+        let col = self.typed_col::<T>(col_id)?;
+        if col.encoding != ENCODING_RAW {
+            return Err(Error::Unsupported(format!(
+                "column {} is not raw encoded",
+                col_id
+            )));
+        }
+        if start == end {
+            return Ok(&[]);
+        }
+        if self.verify_checksums {
+            let first = self.meta.block_of(start);
+            let last = self.meta.block_of(end - 1);
+            for idx in first..=last {
+                let block = col
+                    .blocks
+                    .get(idx)
+                    .ok_or_else(|| Error::Corrupt("column block count mismatch".into()))?;
+                let bytes = map_range(map, block.offset, block.len as usize)?;
+                verify_block(col, idx, bytes)?;
+            }
+        }
+
+        let (offset, len) = raw_extent(col, start, end, mem::size_of::<T>())?;
+        let bytes = map_range(map, offset, len)?;
+        if bytes.as_ptr().align_offset(mem::align_of::<T>()) != 0 {
+            return Err(Error::Unsupported(format!(
+                "column {} is not aligned for zero-copy access",
+                col_id
+            )));
+        }
+        // SAFETY: `bytes` is in bounds and aligned for `T`, holds exactly
+        // `end - start` values, and any bit pattern is a valid `T`. Chunks are
+        // replaced by rename and never modified in place, so the mapping stays
+        // valid for as long as `self` is borrowed.
+        Ok(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, end - start) })
+    }
+
+    fn read_range<T: RawValue>(
+        &self,
+        col_id: u16,
         start: usize,
         end: usize,
-        width: usize,
-    ) -> Result<Vec<u8>> {
-        if start > end {
-            return Err(Error::Corrupt("range start > end".into()));
-        }
-        let row_count = self.meta.row_count as usize;
-        if end > row_count {
-            return Err(Error::Corrupt("range end out of bounds".into()));
+        decode: DecodeFn<T>,
+    ) -> Result<Vec<T>> {
+        check_range(&self.meta, start, end)?;
+        let col = self.typed_col::<T>(col_id)?.clone();
+        if start == end {
+            return Ok(Vec::new());
         }
-        let end_bytes = end
-            .checked_mul(width)
-            .ok_or_else(|| Error::Corrupt("range byte overflow".into()))?;
-        if end_bytes as u64 > col.len {
-            return Err(Error::Corrupt("range exceeds column length".into()));
+
+        // Still one copy, since the returned values are owned; borrowing
+        // callers use `slice_*`.
+        if col.encoding == ENCODING_RAW && self.is_mmap() {
+            if let Ok(values) = self.raw_slice::<T>(col_id, start, end) {
+                return Ok(values.to_vec());
+            }
         }
-        let start_bytes = start
-            .checked_mul(width)
-            .ok_or_else(|| Error::Corrupt("range byte overflow".into()))?;
-        let byte_len = end_bytes
-            .checked_sub(start_bytes)
-            .ok_or_else(|| Error::Corrupt("range byte underflow".into()))?;
-
-        let offset = col
-            .offset
-            .checked_add(start_bytes as u64)
-            .ok_or_else(|| Error::Corrupt("range offset overflow".into()))?;
-        self.file.seek(SeekFrom::Start(offset))?;
-        let mut buf = vec![0u8; byte_len];
-        if !buf.is_empty() {
-            self.file.read_exact(&mut buf)?;
+        let has_crcs = col.blocks.iter().any(|block| block.crc32.is_some());
+        if col.encoding == ENCODING_RAW && !(self.verify_checksums && has_crcs) {
+            // Raw blocks are fixed width and contiguous, so rows map straight to bytes.
+            let (offset, len) = raw_extent(&col, start, end, mem::size_of::<T>())?;
+            let buf = self.source.read_at(offset, len)?;
+            return decode(&buf, col.encoding, end - start);
         }
-        Ok(buf)
+
+        let first = self.meta.block_of(start);
+        let last = self.meta.block_of(end - 1);
+        let values = read_blocks(
+            &self.source,
+            &col,
+            &self.meta,
+            first..last + 1,
+            self.verify_checksums,
+            decode,
+        )?;
+        let base = self.meta.blocks[first].row_offset as usize;
+        Ok(values[start - base..end - base].to_vec())
+    }
+}
+
+type DecodeFn<T> = fn(&[u8], u16, usize) -> Result<Vec<T>>;
+
+fn check_range(meta: &ChunkMeta, start: usize, end: usize) -> Result<()> {
+    if start > end {
+        return Err(Error::Corrupt("range start > end".into()));
+    }
+    if end > meta.row_count as usize {
+        return Err(Error::Corrupt("range end out of bounds".into()));
     }
+    Ok(())
+}
+
+/// Byte offset and length of rows `start..end` in a raw column.
+fn raw_extent(col: &ColumnMeta, start: usize, end: usize, width: usize) -> Result<(u64, usize)> {
+    let offset = (start as u64)
+        .checked_mul(width as u64)
+        .and_then(|start_bytes| col.offset.checked_add(start_bytes))
+        .ok_or_else(|| Error::Corrupt("range offset overflow".into()))?;
+    let len = (end - start) * width;
+    if offset + len as u64 > col.offset + col.len {
+        return Err(Error::Corrupt("range exceeds column length".into()));
+    }
+    Ok((offset, len))
 }
 
 pub fn open_chunk(path: &Path) -> Result<ChunkFile> {
+    open_chunk_with_options(path, &ReadOptions::default())
+}
+
+pub fn open_chunk_with_options(path: &Path, options: &ReadOptions) -> Result<ChunkFile> {
     let mut file = File::open(path)?;
     let header = format::read_header(&mut file)?;
     let meta = read_meta(&mut file, &header)?;
-    Ok(ChunkFile { meta, file })
+    let source = if options.mmap {
+        // SAFETY: chunk files are written once and published by rename, so
+        // the mapped bytes are not modified while the mapping is alive.
+        Source::Mmap(unsafe { Mmap::map(&file)? })
+    } else {
+        Source::File(file)
+    };
+    Ok(ChunkFile {
+        meta,
+        header,
+        source: Arc::new(source),
+        verify_checksums: options.verify_checksums,
+    })
 }
 
 pub fn open_meta(path: &Path) -> Result<ChunkMeta> {
@@ -139,43 +427,27 @@ pub fn open_meta(path: &Path) -> Result<ChunkMeta> {
     read_meta(&mut file, &header)
 }
 
-pub fn read_batch(chunk: &mut ChunkFile) -> Result<RecordBatch> {
-    let row_count = chunk.meta.row_count as usize;
-    let min_data_offset = format::HEADER_LEN as u64 + meta_total_len(&chunk.meta) as u64;
-    let mut ts: Option<Vec<i64>> = None;
-    let mut series_id: Option<Vec<u32>> = None;
-    let mut value: Option<Vec<f64>> = None;
-
-    for col in &chunk.meta.cols {
+pub fn read_batch(chunk: &ChunkFile) -> Result<RecordBatch> {
+    let min_data_offset = format::HEADER_LEN as u64 + chunk.header.meta_len as u64;
+    let all_blocks = 0..chunk.meta.blocks.len();
+    let mut columns = Vec::with_capacity(chunk.meta.schema.len());
+    let mut validity = Vec::with_capacity(chunk.meta.schema.len());
+    for (col_id, field) in chunk.meta.schema.fields().iter().enumerate() {
+        let col = chunk.find_col(col_id as u16)?;
         if col.offset < min_data_offset {
             return Err(Error::Corrupt("column offset before data section".into()));
         }
-        if col.encoding != 0 {
-            return Err(Error::Unsupported("unsupported encoding".into()));
-        }
-        match col.col_id {
-            0 => {
-                ts = Some(read_i64_col(&mut chunk.file, col, row_count)?);
-            }
-            1 => {
-                series_id = Some(read_u32_col(&mut chunk.file, col, row_count)?);
-            }
-            2 => {
-                value = Some(read_f64_col(&mut chunk.file, col, row_count)?);
-            }
-            _ => {}
-        }
+        columns.push(read_blocks_column(
+            &chunk.source,
+            col,
+            &chunk.meta,
+            all_blocks.clone(),
+            chunk.verify_checksums,
+            field.dtype,
+        )?);
+        validity.push(chunk.read_column_validity(col)?);
     }
-
-    let ts = ts.ok_or_else(|| Error::Corrupt("missing ts column".into()))?;
-    let series_id = series_id.ok_or_else(|| Error::Corrupt("missing series_id column".into()))?;
-    let value = value.ok_or_else(|| Error::Corrupt("missing value column".into()))?;
-
-    Ok(RecordBatch {
-        ts,
-        series_id,
-        value,
-    })
+    RecordBatch::try_new_with_validity(Arc::new(chunk.meta.schema.clone()), columns, validity)
 }
 
 fn read_meta(file: &mut File, header: &format::Header) -> Result<ChunkMeta> {
@@ -195,22 +467,109 @@ fn read_meta(file: &mut File, header: &format::Header) -> Result<ChunkMeta> {
         return Err(Error::Corrupt("meta crc mismatch".into()));
     }
 
-    crate::meta::decode_meta(&meta_buf)
+    crate::meta::decode_meta(&meta_buf, header.version)
 }
 
-fn meta_total_len(meta: &ChunkMeta) -> usize {
-    let base_len = 4 + 8 + 8 + 4;
-    let col_len = 2 + 2 + 8 + 8;
-    base_len + meta.cols.len() * col_len
+fn read_blocks<T>(
+    source: &Source,
+    col: &ColumnMeta,
+    meta: &ChunkMeta,
+    blocks: Range<usize>,
+    verify: bool,
+    decode: DecodeFn<T>,
+) -> Result<Vec<T>> {
+    if blocks.is_empty() {
+        return Ok(Vec::new());
+    }
+    if col.blocks.len() != meta.blocks.len() {
+        return Err(Error::Corrupt("column block count mismatch".into()));
+    }
+    let extents = &col.blocks[blocks.clone()];
+    let offset = extents[0].offset;
+    let len: u64 = extents.iter().map(|extent| extent.len).sum();
+    let len = usize::try_from(len).map_err(|_| Error::Corrupt("column len overflow".into()))?;
+    let buf = source.read_at(offset, len)?;
+
+    let rows: usize = meta.blocks[blocks.clone()]
+        .iter()
+        .map(|block| block.row_count as usize)
+        .sum();
+    let mut out = Vec::with_capacity(rows);
+    let mut pos = 0usize;
+    for (idx, (extent, block)) in extents.iter().zip(&meta.blocks[blocks.clone()]).enumerate() {
+        let block_len = extent.len as usize;
+        let bytes = &buf[pos..pos + block_len];
+        if verify {
+            verify_block(col, blocks.start + idx, bytes)?;
+        }
+        let values = decode(bytes, col.encoding, block.row_count as usize)?;
+        out.extend(values);
+        pos += block_len;
+    }
+    Ok(out)
 }
 
-fn read_i64_col(file: &mut File, col: &ColumnMeta, row_count: usize) -> Result<Vec<i64>> {
-    let buf = read_col_bytes(file, col)?;
+fn read_blocks_column(
+    source: &Source,
+    col: &ColumnMeta,
+    meta: &ChunkMeta,
+    blocks: Range<usize>,
+    verify: bool,
+    dtype: DataType,
+) -> Result<Column> {
+    Ok(match dtype {
+        DataType::I64 => Column::I64(read_blocks(source, col, meta, blocks, verify, decode_i64)?),
+        DataType::U32 => Column::U32(read_blocks(source, col, meta, blocks, verify, decode_u32)?),
+        DataType::F64 => Column::F64(read_blocks(source, col, meta, blocks, verify, decode_f64)?),
+        DataType::Utf8 => {
+            let codes = read_blocks(source, col, meta, blocks, verify, decode_u32)?;
+            Column::Utf8(lookup_codes(col, &codes)?)
+        }
+    })
+}
+
+fn lookup_codes(col: &ColumnMeta, codes: &[u32]) -> Result<Vec<String>> {
+    let dictionary = col
+        .dictionary
+        .as_ref()
+        .ok_or_else(|| Error::Corrupt("utf8 column has no dictionary".into()))?;
+    codes
+        .iter()
+        .map(|&code| {
+            dictionary
+                .get(code as usize)
+                .cloned()
+                .ok_or_else(|| Error::Corrupt("dictionary code out of range".into()))
+        })
+        .collect()
+}
+
+fn verify_block(col: &ColumnMeta, idx: usize, bytes: &[u8]) -> Result<()> {
+    let expected = match col.blocks.get(idx).and_then(|block| block.crc32) {
+        Some(crc) => crc,
+        None => return Ok(()),
+    };
+    let mut hasher = Hasher::new();
+    hasher.update(bytes);
+    if hasher.finalize() != expected {
+        return Err(Error::Corrupt(format!(
+            "crc mismatch in column {} block {}",
+            col.col_id, idx
+        )));
+    }
+    Ok(())
+}
+
+fn decode_i64(buf: &[u8], encoding: u16, count: usize) -> Result<Vec<i64>> {
+    match encoding {
+        ENCODING_RAW => {}
+        ENCODING_DELTA_OF_DELTA => return encoding::delta::decode_i64(buf, count),
+        _ => return Err(Error::Unsupported("unsupported encoding".into())),
+    }
     if buf.len() % 8 != 0 {
         return Err(Error::Corrupt("i64 column length mismatch".into()));
     }
-    let count = buf.len() / 8;
-    if count != row_count {
+    if buf.len() / 8 != count {
         return Err(Error::Corrupt("row_count mismatch".into()));
     }
     let mut out = Vec::with_capacity(count);
@@ -221,13 +580,17 @@ fn read_i64_col(file: &mut File, col: &ColumnMeta, row_count: usize) -> Result<V
     Ok(out)
 }
 
-fn read_u32_col(file: &mut File, col: &ColumnMeta, row_count: usize) -> Result<Vec<u32>> {
-    let buf = read_col_bytes(file, col)?;
+fn decode_u32(buf: &[u8], encoding: u16, count: usize) -> Result<Vec<u32>> {
+    match encoding {
+        ENCODING_RAW => {}
+        ENCODING_RLE => return encoding::rle::decode_u32(buf, count),
+        ENCODING_DICT => return encoding::dict::decode_u32(buf, count),
+        _ => return Err(Error::Unsupported("unsupported encoding".into())),
+    }
     if buf.len() % 4 != 0 {
         return Err(Error::Corrupt("u32 column length mismatch".into()));
     }
-    let count = buf.len() / 4;
-    if count != row_count {
+    if buf.len() / 4 != count {
         return Err(Error::Corrupt("row_count mismatch".into()));
     }
     let mut out = Vec::with_capacity(count);
@@ -238,13 +601,16 @@ fn read_u32_col(file: &mut File, col: &ColumnMeta, row_count: usize) -> Result<V
     Ok(out)
 }
 
-fn read_f64_col(file: &mut File, col: &ColumnMeta, row_count: usize) -> Result<Vec<f64>> {
-    let buf = read_col_bytes(file, col)?;
+fn decode_f64(buf: &[u8], encoding: u16, count: usize) -> Result<Vec<f64>> {
+    match encoding {
+        ENCODING_RAW => {}
+        ENCODING_XOR => return encoding::xor::decode_f64(buf, count),
+        _ => return Err(Error::Unsupported("unsupported encoding".into())),
+    }
     if buf.len() % 8 != 0 {
         return Err(Error::Corrupt("f64 column length mismatch".into()));
     }
-    let count = buf.len() / 8;
-    if count != row_count {
+    if buf.len() / 8 != count {
         return Err(Error::Corrupt("row_count mismatch".into()));
     }
     let mut out = Vec::with_capacity(count);
@@ -254,11 +620,3 @@ fn read_f64_col(file: &mut File, col: &ColumnMeta, row_count: usize) -> Result<V
     }
     Ok(out)
 }
-
-fn read_col_bytes(file: &mut File, col: &ColumnMeta) -> Result<Vec<u8>> {
-    let len = usize::try_from(col.len).map_err(|_| Error::Corrupt("column len overflow".into()))?;
-    file.seek(SeekFrom::Start(col.offset))?;
-    let mut buf = vec![0u8; len];
-    file.read_exact(&mut buf)?;
-    Ok(buf)
-}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatValue {
+    Int(i64),
+    Float(f64),
+}
+
+impl StatValue {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            StatValue::Int(v) => v as f64,
+            StatValue::Float(v) => v,
+        }
+    }
+
+    /// Truncating conversion; exact for integer stats.
+    pub fn as_i64(&self) -> i64 {
+        match *self {
+            StatValue::Int(v) => v,
+            StatValue::Float(v) => v as i64,
+        }
+    }
+}
+
+/// Min and max skip NaN; when every value is NaN both are NaN.
+/// `count` includes nulls, while min, max, sum and distinct cover only the
+/// valid values; with no valid values min and max are 0.
+/// `distinct` is only tracked for u32 columns and is 0 otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnStats {
+    pub count: u32,
+    pub null_count: u32,
+    pub min: StatValue,
+    pub max: StatValue,
+    pub sum: f64,
+    pub distinct: u32,
+}
+
+impl ColumnStats {
+    pub fn from_i64(values: &[i64]) -> Self {
+        let min = values.iter().copied().min().unwrap_or(0);
+        let max = values.iter().copied().max().unwrap_or(0);
+        Self {
+            count: values.len() as u32,
+            null_count: 0,
+            min: StatValue::Int(min),
+            max: StatValue::Int(max),
+            sum: values.iter().map(|&v| v as f64).sum(),
+            distinct: 0,
+        }
+    }
+
+    pub fn from_u32(values: &[u32]) -> Self {
+        let min = values.iter().copied().min().unwrap_or(0);
+        let max = values.iter().copied().max().unwrap_or(0);
+        let distinct = values.iter().collect::<HashSet<_>>().len();
+        Self {
+            count: values.len() as u32,
+            null_count: 0,
+            min: StatValue::Int(min as i64),
+            max: StatValue::Int(max as i64),
+            sum: values.iter().map(|&v| v as f64).sum(),
+            distinct: distinct as u32,
+        }
+    }
+
+    pub fn from_f64(values: &[f64]) -> Self {
+        let mut min = f64::NAN;
+        let mut max = f64::NAN;
+        for &v in values {
+            if v.is_nan() {
+                continue;
+            }
+            if min.is_nan() || v < min {
+                min = v;
+            }
+            if max.is_nan() || v > max {
+                max = v;
+            }
+        }
+        if values.is_empty() {
+            min = 0.0;
+            max = 0.0;
+        }
+        Self {
+            count: values.len() as u32,
+            null_count: 0,
+            min: StatValue::Float(min),
+            max: StatValue::Float(max),
+            sum: values.iter().sum(),
+            distinct: 0,
+        }
+    }
+
+    /// Adds `null_count` null rows to stats built from the valid values.
+    pub fn with_nulls(mut self, null_count: u32) -> Self {
+        self.count += null_count;
+        self.null_count += null_count;
+        self
+    }
+
+    pub fn valid_count(&self) -> u32 {
+        self.count - self.null_count
+    }
+
+    /// Folds `other` into `self`. `distinct` cannot be combined from two
+    /// summaries, so callers that track it must set it afterwards.
+    pub fn merge(&mut self, other: &ColumnStats) {
+        let had_values = self.valid_count() > 0;
+        self.count += other.count;
+        self.null_count += other.null_count;
+        if other.valid_count() == 0 {
+            return;
+        }
+        if !had_values {
+            self.min = other.min;
+            self.max = other.max;
+            self.sum = other.sum;
+            return;
+        }
+        self.sum += other.sum;
+        self.min = match (self.min, other.min) {
+            (StatValue::Int(a), StatValue::Int(b)) => StatValue::Int(a.min(b)),
+            (a, b) => StatValue::Float(a.as_f64().min(b.as_f64())),
+        };
+        self.max = match (self.max, other.max) {
+            (StatValue::Int(a), StatValue::Int(b)) => StatValue::Int(a.max(b)),
+            (a, b) => StatValue::Float(a.as_f64().max(b.as_f64())),
+        };
+    }
+}
@@ -1,123 +1,722 @@
-use std::fs::File;
-use std::io::{Seek, SeekFrom, Write};
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
 
+use common::config::DEFAULT_BLOCK_ROWS;
 use common::error::{Error, Result};
 use crc32fast::Hasher;
-use datamodel::batch::RecordBatch;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::{DataType, Schema, SERIES_ID_COLUMN, TS_COLUMN};
 
+use crate::bloom::BloomFilter;
 use crate::format::{self, Header};
-use crate::meta::{self, ChunkMeta, ColumnMeta};
+use crate::meta::{
+    self, BlockMeta, ChunkMeta, ColumnBlock, ColumnMeta, ValidityMeta, ENCODING_DELTA_OF_DELTA,
+    ENCODING_DICT, ENCODING_RAW, ENCODING_RLE, ENCODING_XOR,
+};
+use crate::stats::ColumnStats;
+
+const MAX_DICT_SIZE: usize = 1 << 16;
+
+/// Every column's data starts at a multiple of this, so raw blocks of any
+/// fixed-width type can be borrowed from a mapping in place.
+const COLUMN_ALIGN: u64 = 8;
+
+/// Encodings are chosen per data type and apply to every column of that type.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteOptions {
+    pub block_rows: usize,
+    pub i64_encoding: u16,
+    /// Also used for the dictionary codes of utf8 columns. `None` picks raw,
+    /// RLE or dictionary per column from the rows at hand when the first
+    /// block is written.
+    pub u32_encoding: Option<u16>,
+    pub f64_encoding: u16,
+    /// False positive rate of the bloom filter over the `series_id` column,
+    /// or `None` to write no filter.
+    pub series_bloom_fpp: Option<f64>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            block_rows: DEFAULT_BLOCK_ROWS,
+            i64_encoding: ENCODING_DELTA_OF_DELTA,
+            u32_encoding: None,
+            f64_encoding: ENCODING_XOR,
+            series_bloom_fpp: Some(0.01),
+        }
+    }
+}
 
 pub fn write_chunk(path: &Path, batch: &RecordBatch) -> Result<()> {
-    let row_count = batch.len();
-    if batch.ts.len() != batch.series_id.len() || batch.ts.len() != batch.value.len() {
-        return Err(Error::Corrupt("column length mismatch".into()));
-    }
-    if row_count > u32::MAX as usize {
-        return Err(Error::Unsupported("row_count exceeds u32".into()));
-    }
-
-    let (ts_min, ts_max) = if batch.ts.is_empty() {
-        (0, 0)
-    } else {
-        let mut min = batch.ts[0];
-        let mut max = batch.ts[0];
-        for &ts in &batch.ts[1..] {
-            if ts < min {
-                min = ts;
+    write_chunk_with_options(path, batch, &WriteOptions::default())
+}
+
+pub fn write_chunk_with_options(
+    path: &Path,
+    batch: &RecordBatch,
+    options: &WriteOptions,
+) -> Result<()> {
+    let mut writer = ChunkWriter::create(path, batch.schema().as_ref().clone(), *options)?;
+    writer.append(batch)?;
+    writer.finish()
+}
+
+/// Builds a chunk from any number of appended batches. Rows are encoded one
+/// block at a time and spilled to per-column temp files next to `path`, so
+/// only the current partial block is held in memory. Nothing appears at
+/// `path` until `finish` succeeds.
+pub struct ChunkWriter {
+    path: PathBuf,
+    options: WriteOptions,
+    schema: Schema,
+    ts_col: usize,
+    /// Fixed when the first block is written, from that block's rows, since
+    /// every block of a column shares one encoding.
+    /// Later blocks that would suit another encoding better still use it.
+    encodings: Option<Vec<u16>>,
+    pending: Vec<Column>,
+    pending_validity: Vec<Bitmap>,
+    row_count: u64,
+    ts_min: i64,
+    ts_max: i64,
+    blocks: Vec<BlockMeta>,
+    columns: Vec<SpillColumn>,
+    /// Set when writing a block fails, which can leave some columns a block
+    /// ahead of the others.
+    failed: bool,
+}
+
+impl ChunkWriter {
+    pub fn create(path: &Path, schema: Schema, options: WriteOptions) -> Result<Self> {
+        if options.block_rows == 0 {
+            return Err(Error::Unsupported("block_rows must be > 0".into()));
+        }
+        let ts_col = match schema.index_of(TS_COLUMN) {
+            Some(idx) if schema.field(idx).dtype == DataType::I64 => idx,
+            _ => return Err(Error::Unsupported("schema needs an i64 ts column".into())),
+        };
+        if schema.len() > u16::MAX as usize {
+            return Err(Error::Unsupported("too many columns".into()));
+        }
+        let pending = schema
+            .fields()
+            .iter()
+            .map(|field| Column::new_empty(field.dtype))
+            .collect();
+        let pending_validity = vec![Bitmap::new(); schema.len()];
+        let mut writer = Self {
+            path: path.to_path_buf(),
+            options,
+            schema,
+            ts_col,
+            encodings: None,
+            pending,
+            pending_validity,
+            row_count: 0,
+            ts_min: 0,
+            ts_max: 0,
+            blocks: Vec::new(),
+            columns: Vec::new(),
+            failed: false,
+        };
+        // Pushed one at a time so `Drop` cleans up if a later create fails.
+        let tmp = temp_suffix();
+        for (idx, field) in writer.schema.fields().iter().enumerate() {
+            let spill_path = sibling_path(path, &format!("{}.col{}.tmp", tmp, idx))?;
+            writer
+                .columns
+                .push(SpillColumn::create(spill_path, field.dtype)?);
+        }
+        Ok(writer)
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.row_count
+    }
+
+    /// Rows count towards the chunk only once they are buffered or written;
+    /// after a failed write the writer accepts nothing more.
+    pub fn append(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.check_failed()?;
+        if **batch.schema() != self.schema {
+            return Err(Error::Unsupported(
+                "batch schema does not match writer".into(),
+            ));
+        }
+        let row_count = self.row_count + batch.len() as u64;
+        if row_count > u32::MAX as u64 {
+            return Err(Error::Unsupported("row_count exceeds u32".into()));
+        }
+        if batch.is_empty() {
+            return Ok(());
+        }
+        if batch.null_count(self.ts_col) > 0 {
+            return Err(Error::Unsupported("ts column cannot contain nulls".into()));
+        }
+
+        let validity: Vec<_> = (0..batch.columns().len())
+            .map(|idx| batch.validity(idx))
+            .collect();
+        let block_rows = self.options.block_rows;
+        let pending_rows = self.pending[self.ts_col].len();
+        if self.encodings.is_none() && pending_rows + batch.len() >= block_rows {
+            let mut rows = self.pending.clone();
+            for (rows, column) in rows.iter_mut().zip(batch.columns()) {
+                rows.extend_from(column, 0..block_rows - pending_rows)?;
             }
-            if ts > max {
-                max = ts;
+            self.encodings = Some(self.choose_encodings(&rows));
+        }
+        let mut start = 0;
+        if pending_rows > 0 {
+            let take = (block_rows - pending_rows).min(batch.len());
+            self.extend_pending(batch.columns(), &validity, 0..take)?;
+            start = take;
+            if pending_rows + take == block_rows {
+                self.flush_pending()?;
             }
         }
-        (min, max)
-    };
+        while batch.len() - start >= block_rows {
+            self.write_block(batch.columns(), &validity, start..start + block_rows)?;
+            start += block_rows;
+        }
+        self.extend_pending(batch.columns(), &validity, start..batch.len())?;
+
+        let ts = batch.column(self.ts_col).as_i64().unwrap();
+        if self.row_count == 0 {
+            self.ts_min = ts[0];
+            self.ts_max = ts[0];
+        }
+        for &ts in ts {
+            self.ts_min = self.ts_min.min(ts);
+            self.ts_max = self.ts_max.max(ts);
+        }
+        self.row_count = row_count;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.check_failed()?;
+        self.flush_pending()?;
+        let encodings = match self.encodings.take() {
+            Some(encodings) => encodings,
+            None => self.choose_encodings(&self.pending),
+        };
+        for column in &mut self.columns {
+            column.finish()?;
+        }
+
+        let columns: Vec<_> = self.columns.iter().zip(&encodings).collect();
+        let mut meta = ChunkMeta {
+            row_count: self.row_count as u32,
+            ts_min: self.ts_min,
+            ts_max: self.ts_max,
+            blocks: std::mem::take(&mut self.blocks),
+            schema: self.schema.clone(),
+            cols: layout_columns(&columns, 0),
+            series_bloom: self.series_bloom(),
+        };
+        let data_offset = format::HEADER_LEN + meta::encode_meta(&meta).len();
+        meta.cols = layout_columns(&columns, data_offset as u64);
+        let meta_bytes = meta::encode_meta(&meta);
+        if format::HEADER_LEN + meta_bytes.len() != data_offset {
+            return Err(Error::Corrupt("meta length mismatch".into()));
+        }
+
+        let mut hasher = Hasher::new();
+        hasher.update(&meta_bytes);
+        let meta_crc32 = hasher.finalize();
+        let meta_len_u32 = u32::try_from(meta_bytes.len())
+            .map_err(|_| Error::Unsupported("meta too large".into()))?;
+
+        write_atomic(&self.path, |file| {
+            let mut out = BufWriter::new(file);
+            format::write_header(
+                &mut out,
+                &Header {
+                    version: format::VERSION,
+                    meta_len: meta_len_u32,
+                    meta_crc32,
+                },
+            )?;
+            out.write_all(&meta_bytes)?;
+            let mut pos = data_offset as u64;
+            for (column, col) in self.columns.iter().zip(&meta.cols) {
+                write_padding(&mut out, col.offset - pos)?;
+                pos = col.offset + column.len;
+                let copied = io::copy(&mut File::open(&column.path)?, &mut out)?;
+                if copied != column.len {
+                    return Err(Error::Corrupt("spilled column length mismatch".into()));
+                }
+            }
+            for column in &self.columns {
+                if column.has_nulls() {
+                    out.write_all(column.validity.as_bytes())?;
+                }
+            }
+            out.flush()?;
+            Ok(())
+        })
+    }
+
+    fn check_failed(&self) -> Result<()> {
+        if self.failed {
+            return Err(Error::Unsupported(
+                "chunk writer failed on an earlier write".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn series_bloom(&self) -> Option<BloomFilter> {
+        let fpp = self.options.series_bloom_fpp?;
+        let idx = self.schema.index_of(SERIES_ID_COLUMN)?;
+        let ids = self.columns[idx].distinct.as_ref()?;
+        if self.schema.field(idx).dtype != DataType::U32 {
+            return None;
+        }
+        let mut bloom = BloomFilter::with_capacity(ids.len(), fpp);
+        for &id in ids {
+            bloom.insert(id);
+        }
+        Some(bloom)
+    }
+
+    fn choose_encodings(&self, columns: &[Column]) -> Vec<u16> {
+        columns
+            .iter()
+            .map(|column| match column {
+                Column::I64(_) => self.options.i64_encoding,
+                Column::U32(values) => self
+                    .options
+                    .u32_encoding
+                    .unwrap_or_else(|| choose_u32_encoding(values)),
+                Column::F64(_) => self.options.f64_encoding,
+                Column::Utf8(values) => self
+                    .options
+                    .u32_encoding
+                    .unwrap_or_else(|| choose_u32_encoding(&Dictionary::default().intern(values))),
+            })
+            .collect()
+    }
+
+    fn extend_pending(
+        &mut self,
+        columns: &[Column],
+        validity: &[Option<&Bitmap>],
+        rows: Range<usize>,
+    ) -> Result<()> {
+        for (pending, column) in self.pending.iter_mut().zip(columns) {
+            pending.extend_from(column, rows.clone())?;
+        }
+        for (pending, bitmap) in self.pending_validity.iter_mut().zip(validity) {
+            match bitmap {
+                Some(bitmap) => pending.extend_from(bitmap, rows.clone()),
+                None => pending.extend_constant(true, rows.len()),
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_pending(&mut self) -> Result<()> {
+        let rows = self.pending[self.ts_col].len();
+        if rows == 0 {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let pending_validity = std::mem::take(&mut self.pending_validity);
+        let validity: Vec<_> = pending_validity.iter().map(Some).collect();
+        let result = self.write_block(&pending, &validity, 0..rows);
+        self.pending = pending;
+        self.pending_validity = pending_validity;
+        for column in &mut self.pending {
+            column.clear();
+        }
+        for bitmap in &mut self.pending_validity {
+            bitmap.clear();
+        }
+        result
+    }
+
+    fn write_block(
+        &mut self,
+        columns: &[Column],
+        validity: &[Option<&Bitmap>],
+        rows: Range<usize>,
+    ) -> Result<()> {
+        if self.encodings.is_none() {
+            // Only when `finish` flushes a first block short of `block_rows`,
+            // so `columns` holds just its rows.
+            self.encodings = Some(self.choose_encodings(columns));
+        }
+        let encodings = self.encodings.as_ref().unwrap();
+        for (((spill, column), bitmap), &encoding) in self
+            .columns
+            .iter_mut()
+            .zip(columns)
+            .zip(validity)
+            .zip(encodings)
+        {
+            if let Err(e) = spill.push(column, *bitmap, rows.clone(), encoding) {
+                self.failed = true;
+                return Err(e);
+            }
+        }
+        let row_offset = self
+            .blocks
+            .last()
+            .map_or(0, |block| block.row_offset + block.row_count);
+        self.blocks.push(BlockMeta {
+            row_offset,
+            row_count: rows.len() as u32,
+            first_ts: columns[self.ts_col].as_i64().unwrap()[rows.start],
+        });
+        Ok(())
+    }
+}
+
+impl Drop for ChunkWriter {
+    fn drop(&mut self) {
+        for column in &self.columns {
+            let _ = fs::remove_file(&column.path);
+        }
+    }
+}
+
+/// Encoded blocks of one column, appended to a temp file as they are produced.
+struct SpillColumn {
+    path: PathBuf,
+    out: BufWriter<File>,
+    len: u64,
+    stats: ColumnStats,
+    /// Distinct values seen so far; only tracked for u32 and utf8 columns.
+    distinct: Option<HashSet<u32>>,
+    blocks: Vec<EncodedBlock>,
+    /// Validity of every row written so far. At one bit per row it stays
+    /// small enough to keep in memory until `finish`.
+    validity: Bitmap,
+    /// Only for utf8 columns, whose blocks hold codes into it.
+    dictionary: Option<Dictionary>,
+}
+
+/// Assigns each distinct string the next code as it is first seen.
+#[derive(Default)]
+struct Dictionary {
+    codes: HashMap<String, u32>,
+    values: Vec<String>,
+}
+
+impl Dictionary {
+    fn intern(&mut self, values: &[String]) -> Vec<u32> {
+        values
+            .iter()
+            .map(|value| {
+                if let Some(&code) = self.codes.get(value) {
+                    return code;
+                }
+                let code = self.values.len() as u32;
+                self.codes.insert(value.clone(), code);
+                self.values.push(value.clone());
+                code
+            })
+            .collect()
+    }
+}
+
+struct EncodedBlock {
+    len: u64,
+    stats: ColumnStats,
+    crc32: u32,
+}
+
+impl SpillColumn {
+    fn create(path: PathBuf, dtype: DataType) -> Result<Self> {
+        let out = BufWriter::new(create_new(&path)?);
+        Ok(Self {
+            path,
+            out,
+            len: 0,
+            stats: column_stats(&Column::new_empty(meta::stored_type(dtype)), None, 0..0),
+            distinct: matches!(dtype, DataType::U32 | DataType::Utf8).then(HashSet::new),
+            blocks: Vec::new(),
+            validity: Bitmap::new(),
+            dictionary: (dtype == DataType::Utf8).then(Dictionary::default),
+        })
+    }
+
+    fn push(
+        &mut self,
+        column: &Column,
+        validity: Option<&Bitmap>,
+        rows: Range<usize>,
+        encoding: u16,
+    ) -> Result<()> {
+        if let (Column::Utf8(values), Some(dictionary)) = (column, self.dictionary.as_mut()) {
+            let codes = Column::U32(dictionary.intern(&values[rows.clone()]));
+            let validity = validity.map(|bitmap| bitmap.slice(rows.clone()));
+            return self.push(&codes, validity.as_ref(), 0..rows.len(), encoding);
+        }
+        let encoded = encode_column(column, rows.clone(), encoding)?;
+        let stats = column_stats(column, validity, rows.clone());
+        self.out.write_all(&encoded)?;
+        let mut hasher = Hasher::new();
+        hasher.update(&encoded);
+        self.blocks.push(EncodedBlock {
+            len: encoded.len() as u64,
+            stats,
+            crc32: hasher.finalize(),
+        });
+        self.len += encoded.len() as u64;
+        self.stats.merge(&stats);
+        if let (Some(distinct), Column::U32(values)) = (self.distinct.as_mut(), column) {
+            let valid = rows
+                .clone()
+                .filter(|&row| validity.map_or(true, |b| b.get(row)));
+            distinct.extend(valid.map(|row| values[row]));
+        }
+        match validity {
+            Some(bitmap) => self.validity.extend_from(bitmap, rows),
+            None => self.validity.extend_constant(true, rows.len()),
+        }
+        Ok(())
+    }
+
+    fn has_nulls(&self) -> bool {
+        self.stats.null_count > 0
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.out.flush()?;
+        if let Some(distinct) = &self.distinct {
+            self.stats.distinct = distinct.len() as u32;
+        }
+        Ok(())
+    }
+}
+
+/// Writes through a temporary file in the same directory and renames it over
+/// `path`, so readers see either the previous file or the complete new one.
+fn write_atomic(path: &Path, write: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+    let tmp_path = sibling_path(path, &format!("{}.tmp", temp_suffix()))?;
+
+    let result = (|| -> Result<()> {
+        let mut file = create_new(&tmp_path)?;
+        write(&mut file)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+    sync_dir(path)
+}
 
-    let col_count = 3usize;
-    let meta_len = meta_len_for_cols(col_count);
-
-    let mut file = File::create(path)?;
-    format::write_header(
-        &mut file,
-        &Header {
-            meta_len: 0,
-            meta_crc32: 0,
-        },
-    )?;
-
-    if meta_len > 0 {
-        file.write_all(&vec![0u8; meta_len])?;
-    }
-
-    let ts_offset = file.seek(SeekFrom::Current(0))?;
-    for &ts in &batch.ts {
-        file.write_all(&ts.to_le_bytes())?;
-    }
-
-    let series_offset = file.seek(SeekFrom::Current(0))?;
-    for &series_id in &batch.series_id {
-        file.write_all(&series_id.to_le_bytes())?;
-    }
-
-    let value_offset = file.seek(SeekFrom::Current(0))?;
-    for &value in &batch.value {
-        file.write_all(&value.to_le_bytes())?;
-    }
-
-    let row_count_u32 = row_count as u32;
-    let cols = vec![
-        ColumnMeta {
-            col_id: 0,
-            encoding: 0,
-            offset: ts_offset,
-            len: row_count as u64 * 8,
-        },
-        ColumnMeta {
-            col_id: 1,
-            encoding: 0,
-            offset: series_offset,
-            len: row_count as u64 * 4,
-        },
-        ColumnMeta {
-            col_id: 2,
-            encoding: 0,
-            offset: value_offset,
-            len: row_count as u64 * 8,
-        },
-    ];
-
-    let meta = ChunkMeta {
-        row_count: row_count_u32,
-        ts_min,
-        ts_max,
-        cols,
+/// `.<pid>.<n>`, unique to the call, so temporary files of concurrent
+/// writers to one path never collide; they all end in `.tmp`.
+fn temp_suffix() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let n = NEXT.fetch_add(1, Ordering::Relaxed);
+    format!(".{}.{}", process::id(), n)
+}
+
+fn create_new(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::Unsupported("chunk path has no file name".into()))?;
+    let mut name = file_name.to_os_string();
+    name.push(suffix);
+    Ok(path.with_file_name(name))
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
     };
-    let meta_bytes = meta::encode_meta(&meta);
-    if meta_bytes.len() != meta_len {
-        return Err(Error::Corrupt("meta length mismatch".into()));
-    }
-
-    let mut hasher = Hasher::new();
-    hasher.update(&meta_bytes);
-    let meta_crc32 = hasher.finalize();
-    let meta_len_u32 =
-        u32::try_from(meta_bytes.len()).map_err(|_| Error::Unsupported("meta too large".into()))?;
-
-    file.seek(SeekFrom::Start(0))?;
-    format::write_header(
-        &mut file,
-        &Header {
-            meta_len: meta_len_u32,
-            meta_crc32,
-        },
-    )?;
-    file.write_all(&meta_bytes)?;
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn meta_len_for_cols(col_count: usize) -> usize {
-    let base_len = 4 + 8 + 8 + 4;
-    let col_len = 2 + 2 + 8 + 8;
-    base_len + col_count * col_len
+fn write_padding(out: &mut impl Write, len: u64) -> Result<()> {
+    io::copy(&mut io::repeat(0).take(len), out)?;
+    Ok(())
+}
+
+fn align_column(offset: u64) -> u64 {
+    offset.next_multiple_of(COLUMN_ALIGN)
+}
+
+/// Each column starts on a `COLUMN_ALIGN` boundary at or after
+/// `data_offset`, and the validity bitmaps follow the last one.
+fn layout_columns(columns: &[(&SpillColumn, &u16)], data_offset: u64) -> Vec<ColumnMeta> {
+    let mut validity_offset = columns.iter().fold(data_offset, |end, (column, _)| {
+        align_column(end) + column.len
+    });
+    let mut offset = data_offset;
+    let mut cols = Vec::with_capacity(columns.len());
+    for (col_id, &(column, &encoding)) in columns.iter().enumerate() {
+        offset = align_column(offset);
+        let mut blocks = Vec::with_capacity(column.blocks.len());
+        let mut block_offset = offset;
+        for block in &column.blocks {
+            blocks.push(ColumnBlock {
+                offset: block_offset,
+                len: block.len,
+                stats: Some(block.stats),
+                crc32: Some(block.crc32),
+            });
+            block_offset += block.len;
+        }
+        cols.push(ColumnMeta {
+            col_id: col_id as u16,
+            encoding,
+            offset,
+            len: column.len,
+            stats: Some(column.stats),
+            blocks,
+            validity: column.has_nulls().then(|| {
+                let bytes = column.validity.as_bytes();
+                let mut hasher = Hasher::new();
+                hasher.update(bytes);
+                let validity = ValidityMeta {
+                    offset: validity_offset,
+                    len: bytes.len() as u64,
+                    crc32: hasher.finalize(),
+                };
+                validity_offset += validity.len;
+                validity
+            }),
+            dictionary: column
+                .dictionary
+                .as_ref()
+                .map(|dictionary| dictionary.values.clone()),
+        });
+        offset += column.len;
+    }
+    cols
+}
+
+/// Stats over the valid values in `rows`, with the null rows added to the counts.
+fn column_stats(column: &Column, validity: Option<&Bitmap>, rows: Range<usize>) -> ColumnStats {
+    let valid: Vec<usize> = match validity {
+        Some(bitmap) => rows.clone().filter(|&row| bitmap.get(row)).collect(),
+        None => Vec::new(),
+    };
+    if validity.is_none() || valid.len() == rows.len() {
+        return match column {
+            Column::I64(values) => ColumnStats::from_i64(&values[rows]),
+            Column::U32(values) => ColumnStats::from_u32(&values[rows]),
+            Column::F64(values) => ColumnStats::from_f64(&values[rows]),
+            Column::Utf8(_) => unreachable!("utf8 columns are stored as codes"),
+        };
+    }
+    fn gather<T: Copy>(values: &[T], rows: &[usize]) -> Vec<T> {
+        rows.iter().map(|&row| values[row]).collect()
+    }
+    let stats = match column {
+        Column::I64(values) => ColumnStats::from_i64(&gather(values, &valid)),
+        Column::U32(values) => ColumnStats::from_u32(&gather(values, &valid)),
+        Column::F64(values) => ColumnStats::from_f64(&gather(values, &valid)),
+        Column::Utf8(_) => unreachable!("utf8 columns are stored as codes"),
+    };
+    stats.with_nulls((rows.len() - valid.len()) as u32)
+}
+
+fn encode_column(column: &Column, rows: Range<usize>, encoding: u16) -> Result<Vec<u8>> {
+    match column {
+        Column::I64(values) => encode_i64(&values[rows], encoding),
+        Column::U32(values) => encode_u32(&values[rows], encoding),
+        Column::F64(values) => encode_f64(&values[rows], encoding),
+        Column::Utf8(_) => Err(Error::Unsupported(
+            "utf8 columns are stored as codes".into(),
+        )),
+    }
+}
+
+fn encode_i64(values: &[i64], encoding: u16) -> Result<Vec<u8>> {
+    match encoding {
+        ENCODING_RAW => {
+            let mut buf = Vec::with_capacity(values.len() * 8);
+            for &value in values {
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Ok(buf)
+        }
+        ENCODING_DELTA_OF_DELTA => Ok(encoding::delta::encode_i64(values)),
+        _ => Err(Error::Unsupported(format!(
+            "unsupported i64 encoding: {}",
+            encoding
+        ))),
+    }
+}
+
+fn choose_u32_encoding(values: &[u32]) -> u16 {
+    let mut best = (ENCODING_RAW, values.len() * 4);
+    let rle_len = encoding::rle::encoded_len(values);
+    if rle_len < best.1 {
+        best = (ENCODING_RLE, rle_len);
+    }
+    if let Some(dict_len) = encoding::dict::encoded_len(values, MAX_DICT_SIZE) {
+        if dict_len < best.1 {
+            best = (ENCODING_DICT, dict_len);
+        }
+    }
+    best.0
+}
+
+fn encode_u32(values: &[u32], encoding: u16) -> Result<Vec<u8>> {
+    match encoding {
+        ENCODING_RAW => {
+            let mut buf = Vec::with_capacity(values.len() * 4);
+            for &value in values {
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Ok(buf)
+        }
+        ENCODING_RLE => Ok(encoding::rle::encode_u32(values)),
+        ENCODING_DICT => Ok(encoding::dict::encode_u32(values)),
+        _ => Err(Error::Unsupported(format!(
+            "unsupported u32 encoding: {}",
+            encoding
+        ))),
+    }
+}
+
+fn encode_f64(values: &[f64], encoding: u16) -> Result<Vec<u8>> {
+    match encoding {
+        ENCODING_RAW => {
+            let mut buf = Vec::with_capacity(values.len() * 8);
+            for &value in values {
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Ok(buf)
+        }
+        ENCODING_XOR => Ok(encoding::xor::encode_f64(values)),
+        _ => Err(Error::Unsupported(format!(
+            "unsupported f64 encoding: {}",
+            encoding
+        ))),
+    }
 }
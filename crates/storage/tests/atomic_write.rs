@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::PathBuf;
+
+use common::error::Result;
+use datamodel::batch::RecordBatch;
+use storage::reader::{open_chunk, read_batch};
+use storage::writer::{write_chunk, write_chunk_with_options, WriteOptions};
+
+#[test]
+fn rewrite_replaces_chunk_without_leftovers() -> Result<()> {
+    let (dir, path) = temp_paths("rewrite");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk(&path, &make_batch(100, 0))?;
+    let second = make_batch(250, 7);
+    write_chunk(&path, &second)?;
+
+    let chunk = open_chunk(&path)?;
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.ts(), second.ts());
+    assert_eq!(read.value(), second.value());
+
+    let names: Vec<_> = fs::read_dir(&dir)?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    assert_eq!(names, vec![path.file_name().unwrap().to_os_string()]);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn failed_write_keeps_previous_chunk() -> Result<()> {
+    let (dir, path) = temp_paths("failed");
+    fs::create_dir_all(&dir)?;
+
+    let first = make_batch(100, 0);
+    write_chunk(&path, &first)?;
+    let options = WriteOptions {
+        f64_encoding: 0xff,
+        ..WriteOptions::default()
+    };
+    assert!(write_chunk_with_options(&path, &make_batch(50, 3), &options).is_err());
+
+    let chunk = open_chunk(&path)?;
+    assert_eq!(read_batch(&chunk)?.ts(), first.ts());
+    assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+    let missing = dir.join("missing").join("chunk.bin");
+    assert!(write_chunk(&missing, &first).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn concurrent_writes_leave_one_whole_chunk() -> Result<()> {
+    let (dir, path) = temp_paths("concurrent");
+    fs::create_dir_all(&dir)?;
+
+    let batches: Vec<_> = (0..4).map(|seed| make_batch(500, seed)).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = batches
+            .iter()
+            .map(|batch| scope.spawn(|| write_chunk(&path, batch)))
+            .collect();
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().unwrap())
+    })?;
+
+    // Whichever write landed last, the chunk is one of them in full.
+    let read = read_batch(&open_chunk(&path)?)?;
+    assert!(batches.iter().any(|batch| batch.value() == read.value()));
+    assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(len: usize, seed: u32) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(i as i64 * 1000 + seed as i64);
+        series_id.push((i as u32 + seed) % 4);
+        value.push(i as f64 + seed as f64 * 0.5);
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_storage_atomic_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::PathBuf;
+
+use common::error::Result;
+use datamodel::batch::RecordBatch;
+use storage::reader::{open_chunk, read_batch};
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn block_index_covers_chunk() -> Result<()> {
+    let batch = make_batch(10_050);
+    let (dir, path) = temp_paths("index");
+    fs::create_dir_all(&dir)?;
+
+    let options = WriteOptions {
+        block_rows: 1000,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+    let chunk = open_chunk(&path)?;
+
+    assert_eq!(chunk.meta.blocks.len(), 11);
+    assert_eq!(chunk.meta.blocks[10].row_offset, 10_000);
+    assert_eq!(chunk.meta.blocks[10].row_count, 50);
+    for (i, block) in chunk.meta.blocks.iter().enumerate() {
+        assert_eq!(block.first_ts, batch.ts()[i * 1000]);
+    }
+    for col in &chunk.meta.cols {
+        assert_eq!(col.blocks.len(), 11);
+        let total: u64 = col.blocks.iter().map(|b| b.len).sum();
+        assert_eq!(total, col.len);
+    }
+
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.ts(), batch.ts());
+    assert_eq!(read.series_id(), batch.series_id());
+    assert_eq!(read.value(), batch.value());
+
+    let ts = chunk.read_range_i64(0, 999, 3001)?;
+    assert_eq!(ts, batch.ts()[999..3001].to_vec());
+    let value = chunk.read_range_f64(2, 9990, 10_050)?;
+    assert_eq!(value, batch.value()[9990..10_050].to_vec());
+    assert!(chunk.read_range_u32(1, 5, 5)?.is_empty());
+    assert!(chunk.read_range_u32(1, 0, 10_051).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn lower_bound_matches_linear_search() -> Result<()> {
+    let batch = make_batch(5000);
+    let (dir, path) = temp_paths("lower_bound");
+    fs::create_dir_all(&dir)?;
+
+    let options = WriteOptions {
+        block_rows: 128,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+    let chunk = open_chunk(&path)?;
+
+    let last = *batch.ts().last().unwrap();
+    for target in (-10..last + 20).step_by(7) {
+        let expected = batch.ts().partition_point(|&ts| ts < target);
+        assert_eq!(chunk.lower_bound_ts(target)?, expected, "target {}", target);
+    }
+    assert_eq!(chunk.read_ts_at(4321)?, batch.ts()[4321]);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    let mut cur = 0i64;
+    for i in 0..len {
+        cur += 1 + (i as i64 % 5) / 4 * 3;
+        ts.push(cur);
+        series_id.push((i as u32 / 64) % 10);
+        value.push((i as f64 * 0.01).cos());
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_storage_blocks_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::schema::{DataType, Field, Schema};
+use storage::bloom::BloomFilter;
+use storage::reader::open_meta;
+use storage::writer::{write_chunk, write_chunk_with_options, WriteOptions};
+
+#[test]
+fn bloom_has_no_false_negatives() {
+    let mut bloom = BloomFilter::with_capacity(1000, 0.01);
+    for id in (0..1000).map(|i| i * 7) {
+        bloom.insert(id);
+    }
+    assert!((0..1000).all(|i| bloom.may_contain(i * 7)));
+    let false_positives = (0..10_000)
+        .map(|i| 1_000_000 + i)
+        .filter(|&id| bloom.may_contain(id))
+        .count();
+    assert!(false_positives < 300, "{}", false_positives);
+    assert!(bloom.num_hashes() >= 1);
+}
+
+#[test]
+fn writer_stores_series_bloom() -> Result<()> {
+    let (dir, path) = temp_paths("meta");
+    fs::create_dir_all(&dir)?;
+    let ts: Vec<i64> = (0..5000).collect();
+    let series_id: Vec<u32> = (0..5000).map(|i| (i % 250) * 4).collect();
+    let value = vec![1.0; 5000];
+    let batch = RecordBatch::time_series(ts, series_id.clone(), value);
+
+    write_chunk(&path, &batch)?;
+    let bloom = open_meta(&path)?.series_bloom.unwrap();
+    assert!(series_id.iter().all(|&id| bloom.may_contain(id)));
+    let absent = (0..1000).filter(|id| id % 4 != 0);
+    assert!(absent.filter(|&id| bloom.may_contain(id)).count() < 30);
+
+    let options = WriteOptions {
+        series_bloom_fpp: None,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+    assert!(open_meta(&path)?.series_bloom.is_none());
+
+    // No series_id column, no filter.
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("value", DataType::F64),
+    ])?;
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Column::I64(vec![1, 2]), Column::F64(vec![1.0, 2.0])],
+    )?;
+    write_chunk(&path, &batch)?;
+    assert!(open_meta(&path)?.series_bloom.is_none());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir =
+        std::env::temp_dir().join(format!("tsdb_storage_bloom_{}_{}", tag, std::process::id()));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
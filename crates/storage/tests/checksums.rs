@@ -0,0 +1,122 @@
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use common::error::{Error, Result};
+use datamodel::batch::RecordBatch;
+use storage::meta::ENCODING_RAW;
+use storage::reader::{open_chunk, read_batch};
+use storage::writer::{write_chunk_with_options, WriteOptions};
+
+#[test]
+fn every_block_carries_a_crc() -> Result<()> {
+    let batch = make_batch(3000);
+    let (dir, path) = temp_paths("present");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk_with_options(&path, &batch, &options())?;
+    let chunk = open_chunk(&path)?;
+    for col in &chunk.meta.cols {
+        assert!(col.blocks.iter().all(|block| block.crc32.is_some()));
+    }
+    assert_eq!(read_batch(&chunk)?.value(), batch.value());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn flipped_data_byte_is_reported() -> Result<()> {
+    let batch = make_batch(3000);
+    let (dir, path) = temp_paths("flipped");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk_with_options(&path, &batch, &options())?;
+    let block = open_chunk(&path)?.meta.col(2).unwrap().blocks[1].clone();
+    flip_byte(&path, block.offset + 3)?;
+
+    let chunk = open_chunk(&path)?;
+    match read_batch(&chunk) {
+        Err(Error::Corrupt(msg)) => assert_eq!(msg, "crc mismatch in column 2 block 1"),
+        other => panic!("expected crc error, got {:?}", other.map(|b| b.len())),
+    }
+    match chunk.read_range_f64(2, 1500, 1600) {
+        Err(Error::Corrupt(msg)) => assert!(msg.contains("column 2"), "{}", msg),
+        other => panic!("expected crc error, got {:?}", other.map(|v| v.len())),
+    }
+    // Untouched blocks and columns still read fine.
+    assert_eq!(
+        chunk.read_range_f64(2, 0, 1000)?,
+        batch.value()[..1000].to_vec()
+    );
+    assert_eq!(
+        chunk.read_range_i64(0, 1000, 2000)?,
+        batch.ts()[1000..2000].to_vec()
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn verification_can_be_disabled() -> Result<()> {
+    let batch = make_batch(3000);
+    let (dir, path) = temp_paths("disabled");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk_with_options(&path, &batch, &options())?;
+    let block = open_chunk(&path)?.meta.col(2).unwrap().blocks[2].clone();
+    // Corrupt the last row of block 2 so the rows read below stay intact.
+    flip_byte(&path, block.offset + block.len - 1)?;
+
+    let mut chunk = open_chunk(&path)?;
+    assert!(chunk.read_range_f64(2, 2000, 2010).is_err());
+    chunk.set_verify_checksums(false);
+    assert_eq!(
+        chunk.read_range_f64(2, 2000, 2010)?,
+        batch.value()[2000..2010].to_vec()
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn options() -> WriteOptions {
+    WriteOptions {
+        block_rows: 1000,
+        f64_encoding: ENCODING_RAW,
+        ..WriteOptions::default()
+    }
+}
+
+fn flip_byte(path: &Path, offset: u64) -> Result<()> {
+    let bytes = fs::read(path)?;
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(&[bytes[offset as usize] ^ 0xff])?;
+    Ok(())
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(1_000 + i as i64 * 10);
+        series_id.push(i as u32 % 7);
+        value.push(i as f64 * 0.5);
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_storage_checksums_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::PathBuf;
+
+use common::error::{Error, Result};
+use datamodel::batch::RecordBatch;
+use datamodel::schema::Schema;
+use storage::meta::{ENCODING_DICT, ENCODING_RLE};
+use storage::reader::{open_chunk, read_batch};
+use storage::writer::{write_chunk_with_options, ChunkWriter, WriteOptions};
+
+#[test]
+fn appended_batches_match_single_write() -> Result<()> {
+    let batch = make_batch(5000);
+    let (dir, path) = temp_paths("appended");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 512,
+        u32_encoding: Some(ENCODING_DICT),
+        ..WriteOptions::default()
+    };
+
+    let whole_path = dir.join("whole.bin");
+    write_chunk_with_options(&whole_path, &batch, &options)?;
+
+    let mut writer = ChunkWriter::create(&path, Schema::time_series(), options)?;
+    let mut start = 0;
+    for len in [1, 0, 700, 511, 512, 1500, 3].iter().cycle() {
+        let end = (start + len).min(batch.len());
+        writer.append(&batch.slice(start..end))?;
+        start = end;
+        if start == batch.len() {
+            break;
+        }
+    }
+    assert_eq!(writer.rows(), 5000);
+    writer.finish()?;
+
+    assert_eq!(fs::read(&path)?, fs::read(&whole_path)?);
+    let chunk = open_chunk(&path)?;
+    assert_eq!(chunk.meta.blocks.len(), 10);
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.ts(), batch.ts());
+    assert_eq!(read.series_id(), batch.series_id());
+    assert_eq!(read.value(), batch.value());
+    assert_eq!(chunk.meta.col(1).unwrap().stats.unwrap().distinct, 13);
+
+    let mut names: Vec<_> = fs::read_dir(&dir)?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    names.sort();
+    assert_eq!(names, vec!["chunk.bin", "whole.bin"]);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn dropped_writer_leaves_nothing_behind() -> Result<()> {
+    let (dir, path) = temp_paths("dropped");
+    fs::create_dir_all(&dir)?;
+
+    let mut writer = ChunkWriter::create(&path, Schema::time_series(), WriteOptions::default())?;
+    writer.append(&make_batch(3000))?;
+    drop(writer);
+    assert_eq!(fs::read_dir(&dir)?.count(), 0);
+
+    let writer = ChunkWriter::create(&path, Schema::time_series(), WriteOptions::default())?;
+    writer.finish()?;
+    let chunk = open_chunk(&path)?;
+    assert_eq!(chunk.meta.row_count, 0);
+    assert!(read_batch(&chunk)?.is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn encodings_wait_for_the_first_block() -> Result<()> {
+    let (dir, path) = temp_paths("first_block");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 512,
+        ..WriteOptions::default()
+    };
+
+    // Three distinct ids alone would pick raw; by the time the first block
+    // is written the rows are mostly one id, which picks RLE.
+    let mut writer = ChunkWriter::create(&path, Schema::time_series(), options)?;
+    writer.append(&RecordBatch::time_series(
+        vec![0, 1, 2],
+        vec![0, 1, 2],
+        vec![0.0; 3],
+    ))?;
+    let ts = (3..2000).collect();
+    writer.append(&RecordBatch::time_series(
+        ts,
+        vec![7; 1997],
+        vec![0.0; 1997],
+    ))?;
+    writer.finish()?;
+    let chunk = open_chunk(&path)?;
+    assert_eq!(chunk.meta.col(1).unwrap().encoding, ENCODING_RLE);
+
+    // The choice holds for the whole chunk, even where a later block would
+    // be smaller another way.
+    let mut writer = ChunkWriter::create(&path, Schema::time_series(), options)?;
+    writer.append(&RecordBatch::time_series(
+        (0..512).collect(),
+        vec![7; 512],
+        vec![0.0; 512],
+    ))?;
+    writer.append(&make_batch(1024))?;
+    writer.finish()?;
+    let chunk = open_chunk(&path)?;
+    assert_eq!(chunk.meta.col(1).unwrap().encoding, ENCODING_RLE);
+    assert_eq!(
+        read_batch(&chunk)?.series_id()[512..],
+        make_batch(1024).series_id()[..]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn failed_block_write_is_not_counted() -> Result<()> {
+    let (dir, path) = temp_paths("failed");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 512,
+        f64_encoding: 0xff,
+        ..WriteOptions::default()
+    };
+
+    let mut writer = ChunkWriter::create(&path, Schema::time_series(), options)?;
+    writer.append(&make_batch(100))?;
+    assert_eq!(writer.rows(), 100);
+    // Filling the first block writes it, which fails on the f64 column.
+    assert!(writer.append(&make_batch(600)).is_err());
+    assert_eq!(writer.rows(), 100);
+    assert!(matches!(
+        writer.append(&make_batch(1)),
+        Err(Error::Unsupported(_))
+    ));
+    assert!(writer.finish().is_err());
+    assert_eq!(fs::read_dir(&dir)?.count(), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(10_000 + i as i64 * 15);
+        series_id.push((i as u32 * 7) % 13);
+        // Whole numbers keep the merged block sums exact.
+        value.push((i % 100) as f64);
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_storage_chunk_writer_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
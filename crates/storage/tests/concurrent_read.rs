@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+use common::error::Result;
+use datamodel::batch::RecordBatch;
+use storage::reader::{open_chunk, open_chunk_with_options, ChunkFile, ReadOptions};
+use storage::writer::write_chunk;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn threads_share_one_handle() -> Result<()> {
+    assert_send_sync::<ChunkFile>();
+
+    let batch = make_batch(20_000);
+    let (dir, path) = temp_paths("threads");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &batch)?;
+
+    let mmap = ReadOptions {
+        mmap: true,
+        ..ReadOptions::default()
+    };
+    for chunk in [open_chunk(&path)?, open_chunk_with_options(&path, &mmap)?] {
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|t| {
+                    let chunk = &chunk;
+                    let batch = &batch;
+                    scope.spawn(move || -> Result<()> {
+                        for i in 0..50 {
+                            let start = (t * 4999 + i * 331) % 19_000;
+                            let end = start + 1000;
+                            let ts = chunk.read_range_i64(0, start, end)?;
+                            assert_eq!(ts, batch.ts()[start..end].to_vec());
+                            let value = chunk.read_range_f64(2, start, end)?;
+                            assert_eq!(value, batch.value()[start..end].to_vec());
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap()?;
+            }
+            Ok::<_, common::error::Error>(())
+        })?;
+    }
+
+    // Clones share the file but keep their own settings.
+    let chunk = open_chunk(&path)?;
+    let mut unchecked = chunk.clone();
+    unchecked.set_verify_checksums(false);
+    assert_eq!(
+        unchecked.read_range_u32(1, 10, 20)?,
+        chunk.read_range_u32(1, 10, 20)?
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(i as i64 * 3);
+        series_id.push(i as u32 % 17);
+        value.push((i as f64).sqrt());
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_storage_concurrent_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
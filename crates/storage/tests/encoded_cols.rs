@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::PathBuf;
+
+use common::config::{DEFAULT_BLOCK_ROWS, DEFAULT_CHUNK_ROWS};
+use common::error::Result;
+use datamodel::batch::RecordBatch;
+use storage::meta::{
+    ENCODING_DELTA_OF_DELTA, ENCODING_DICT, ENCODING_RAW, ENCODING_RLE, ENCODING_XOR,
+};
+use storage::reader::{open_chunk, read_batch};
+use storage::writer::{write_chunk, write_chunk_with_options, WriteOptions};
+
+#[test]
+fn encoded_cols_roundtrip_and_range() -> Result<()> {
+    let batch = make_batch(DEFAULT_CHUNK_ROWS);
+    let dir = temp_dir("mixed");
+    let raw_path = dir.join("raw.bin");
+    let enc_path = dir.join("encoded.bin");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk_with_options(
+        &raw_path,
+        &batch,
+        &WriteOptions {
+            block_rows: DEFAULT_BLOCK_ROWS,
+            i64_encoding: ENCODING_RAW,
+            u32_encoding: Some(ENCODING_RAW),
+            f64_encoding: ENCODING_RAW,
+            series_bloom_fpp: None,
+        },
+    )?;
+    write_chunk_with_options(
+        &enc_path,
+        &batch,
+        &WriteOptions {
+            block_rows: DEFAULT_BLOCK_ROWS,
+            i64_encoding: ENCODING_DELTA_OF_DELTA,
+            u32_encoding: None,
+            f64_encoding: ENCODING_XOR,
+            series_bloom_fpp: None,
+        },
+    )?;
+
+    let raw_size = fs::metadata(&raw_path)?.len();
+    let enc_size = fs::metadata(&enc_path)?.len();
+    assert!(enc_size * 4 < raw_size);
+
+    let chunk = open_chunk(&enc_path)?;
+    let ts_col = chunk.meta.cols.iter().find(|c| c.col_id == 0).unwrap();
+    assert_eq!(ts_col.encoding, ENCODING_DELTA_OF_DELTA);
+
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.ts(), batch.ts());
+    assert_eq!(read.series_id(), batch.series_id());
+    assert_eq!(read.value(), batch.value());
+
+    let series_col = chunk.meta.cols.iter().find(|c| c.col_id == 1).unwrap();
+    assert_eq!(series_col.encoding, ENCODING_DICT);
+    let value_col = chunk.meta.cols.iter().find(|c| c.col_id == 2).unwrap();
+    assert_eq!(value_col.encoding, ENCODING_XOR);
+
+    let ts = chunk.read_range_i64(0, 500, 1500)?;
+    assert_eq!(ts, batch.ts()[500..1500].to_vec());
+    assert_eq!(chunk.read_ts_at(4242)?, batch.ts()[4242]);
+    let series_id = chunk.read_range_u32(1, 500, 1500)?;
+    assert_eq!(series_id, batch.series_id()[500..1500].to_vec());
+    let value = chunk.read_range_f64(2, 500, 1500)?;
+    assert_eq!(value, batch.value()[500..1500].to_vec());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn sorted_series_picks_rle() -> Result<()> {
+    let batch = make_batch(DEFAULT_CHUNK_ROWS);
+    let mut series_id = batch.series_id().to_vec();
+    series_id.sort_unstable();
+    let batch = RecordBatch::time_series(batch.ts().to_vec(), series_id, batch.value().to_vec());
+    let dir = temp_dir("rle");
+    let path = dir.join("chunk.bin");
+    fs::create_dir_all(&dir)?;
+
+    write_chunk(&path, &batch)?;
+    let chunk = open_chunk(&path)?;
+    let series_col = chunk.meta.cols.iter().find(|c| c.col_id == 1).unwrap();
+    assert_eq!(series_col.encoding, ENCODING_RLE);
+    assert!(series_col.len < 4096);
+
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.series_id(), batch.series_id());
+    assert_eq!(
+        chunk.read_range_u32(1, 8000, 8100)?,
+        batch.series_id()[8000..8100].to_vec()
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        let jitter = if i % 7 == 0 { 3 } else { 0 };
+        ts.push(1_700_000_000_000 + i as i64 * 1000 + jitter);
+        // Few enough series that a single block is worth a dictionary.
+        series_id.push((i as u32) % 100);
+        value.push(20.0 + (i / 64) as f64 * 0.5);
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_dir(tag: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "tsdb_storage_encoded_cols_{}_{}",
+        tag,
+        std::process::id()
+    ))
+}
@@ -1,10 +1,10 @@
-use std::fs::{self, OpenOptions};
+use std::fs::{self, File, OpenOptions};
 use std::path::PathBuf;
 
 use common::config::DEFAULT_CHUNK_ROWS;
 use common::error::Result;
 use datamodel::batch::RecordBatch;
-use storage::format::HEADER_LEN;
+use storage::format::{read_header, HEADER_LEN};
 use storage::reader::open_meta;
 use storage::writer::write_chunk;
 
@@ -20,8 +20,8 @@ fn read_meta_without_data() -> Result<()> {
     let meta = open_meta(&path)?;
     assert_eq!(meta.row_count, batch.len() as u32);
 
-    let ts_min = *batch.ts.iter().min().unwrap();
-    let ts_max = *batch.ts.iter().max().unwrap();
+    let ts_min = *batch.ts().iter().min().unwrap();
+    let ts_max = *batch.ts().iter().max().unwrap();
     assert_eq!(meta.ts_min, ts_min);
     assert_eq!(meta.ts_max, ts_max);
 
@@ -41,26 +41,17 @@ fn make_batch(len: usize) -> RecordBatch {
         value.push((i as f64).sin());
     }
 
-    RecordBatch {
-        ts,
-        series_id,
-        value,
-    }
+    RecordBatch::time_series(ts, series_id, value)
 }
 
 fn truncate_to_meta_only(path: &PathBuf) -> Result<()> {
-    let truncate_len = HEADER_LEN as u64 + meta_len_for_cols(3) as u64;
+    let header = read_header(&mut File::open(path)?)?;
+    let truncate_len = HEADER_LEN as u64 + header.meta_len as u64;
     let file = OpenOptions::new().write(true).open(path)?;
     file.set_len(truncate_len)?;
     Ok(())
 }
 
-fn meta_len_for_cols(col_count: usize) -> usize {
-    let base_len = 4 + 8 + 8 + 4;
-    let col_len = 2 + 2 + 8 + 8;
-    base_len + col_count * col_len
-}
-
 fn temp_paths() -> (PathBuf, PathBuf) {
     let mut rng = Lcg::new(0xF1EE_5EED_D00D_BEEF);
     let suffix = rng.next_u64();
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::PathBuf;
+
+use common::error::{Error, Result};
+use datamodel::batch::RecordBatch;
+use storage::meta::ENCODING_RAW;
+use storage::reader::{open_chunk, open_chunk_with_options, read_batch, ReadOptions};
+use storage::writer::{write_chunk, write_chunk_with_options, WriteOptions};
+
+#[test]
+fn mmap_reads_match_file_reads() -> Result<()> {
+    let batch = make_batch(4000);
+    let (dir, path) = temp_paths("match");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &batch)?;
+
+    let file_chunk = open_chunk(&path)?;
+    let mmap_chunk = open_chunk_with_options(&path, &mmap_options())?;
+    assert!(!file_chunk.is_mmap());
+    assert!(mmap_chunk.is_mmap());
+
+    let read = read_batch(&mmap_chunk)?;
+    assert_eq!(read.ts(), batch.ts());
+    assert_eq!(read.series_id(), batch.series_id());
+    assert_eq!(read.value(), batch.value());
+    for (start, end) in [(0, 1), (17, 1500), (3990, 4000)] {
+        assert_eq!(
+            mmap_chunk.read_range_f64(2, start, end)?,
+            file_chunk.read_range_f64(2, start, end)?
+        );
+    }
+    assert_eq!(mmap_chunk.lower_bound_ts(2500)?, 500);
+
+    // Encoded columns cannot be borrowed in place.
+    assert!(matches!(
+        mmap_chunk.slice_i64(0, 0, 10),
+        Err(Error::Unsupported(_))
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn raw_columns_are_borrowed_from_the_mapping() -> Result<()> {
+    let batch = make_batch(3001);
+    let (dir, path) = temp_paths("slices");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        i64_encoding: ENCODING_RAW,
+        u32_encoding: Some(ENCODING_RAW),
+        f64_encoding: ENCODING_RAW,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+
+    let chunk = open_chunk_with_options(&path, &mmap_options())?;
+    // Column data is aligned in the file and the mapping is page aligned,
+    // so raw columns can always be borrowed.
+    for col in &chunk.meta.cols {
+        assert_eq!(col.offset % 8, 0);
+    }
+    assert_eq!(chunk.slice_i64(0, 100, 2100)?, &batch.ts()[100..2100]);
+    assert_eq!(chunk.slice_u32(1, 0, 3001)?, batch.series_id());
+    assert_eq!(chunk.slice_f64(2, 2999, 3001)?, &batch.value()[2999..]);
+    assert!(chunk.slice_f64(2, 5, 5)?.is_empty());
+    assert!(chunk.slice_f64(2, 0, 3002).is_err());
+
+    let file_chunk = open_chunk(&path)?;
+    assert!(matches!(
+        file_chunk.slice_i64(0, 0, 10),
+        Err(Error::Unsupported(_))
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn mmap_options() -> ReadOptions {
+    ReadOptions {
+        mmap: true,
+        ..ReadOptions::default()
+    }
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(2000 + i as i64);
+        series_id.push(i as u32 % 9);
+        value.push((i as f64 * 0.1).sin());
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir =
+        std::env::temp_dir().join(format!("tsdb_storage_mmap_{}_{}", tag, std::process::id()));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::{Error, Result};
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::Schema;
+use storage::reader::{open_chunk, open_meta, read_batch};
+use storage::stats::StatValue;
+use storage::writer::{write_chunk, write_chunk_with_options, ChunkWriter, WriteOptions};
+
+#[test]
+fn validity_roundtrip() -> Result<()> {
+    let batch = make_batch(3000)?;
+    let (dir, path) = temp_paths("roundtrip");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 1000,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+
+    let meta = open_meta(&path)?;
+    assert!(meta.col(0).unwrap().validity.is_none());
+    assert!(meta.col(1).unwrap().validity.is_none());
+    let value = meta.col(2).unwrap();
+    assert_eq!(value.validity.unwrap().len, 375);
+    let stats = value.stats.unwrap();
+    assert_eq!(stats.count, 3000);
+    assert_eq!(stats.null_count, 1400);
+    // Null slots hold -1.0, which must not leak into min or sum.
+    assert_eq!(stats.min, StatValue::Float(2.0));
+    assert_eq!(stats.sum, valid_sum(&batch, 0..3000));
+    // The middle block is entirely null.
+    assert_eq!(value.blocks[1].stats.unwrap().null_count, 1000);
+
+    let chunk = open_chunk(&path)?;
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.columns(), batch.columns());
+    assert_eq!(read.validity(2), batch.validity(2));
+    assert_eq!(read.null_count(2), 1400);
+
+    let part = chunk.read_validity(2, 990, 1010)?.unwrap();
+    assert_eq!(part, batch.validity(2).unwrap().slice(990..1010));
+    assert!(chunk.read_validity(2, 2001, 2005)?.is_none());
+    assert!(chunk.read_validity(0, 0, 3000)?.is_none());
+
+    let sliced = batch.slice(2001..2005);
+    assert!(sliced.validity(2).is_none());
+    let filtered = batch.filter(&vec![true; 3000]);
+    assert_eq!(filtered.validity(2), batch.validity(2));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn writer_merges_validity_across_appends() -> Result<()> {
+    let batch = make_batch(2500)?;
+    let (dir, path) = temp_paths("appends");
+    fs::create_dir_all(&dir)?;
+
+    let whole_path = dir.join("whole.bin");
+    write_chunk(&whole_path, &batch)?;
+    let mut writer = ChunkWriter::create(&path, Schema::time_series(), WriteOptions::default())?;
+    for start in (0..2500).step_by(333) {
+        writer.append(&batch.slice(start..(start + 333).min(2500)))?;
+    }
+    writer.finish()?;
+    assert_eq!(fs::read(&path)?, fs::read(&whole_path)?);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn null_ts_is_rejected() -> Result<()> {
+    let (dir, path) = temp_paths("null_ts");
+    fs::create_dir_all(&dir)?;
+
+    let batch = RecordBatch::try_new_with_validity(
+        Arc::new(Schema::time_series()),
+        vec![
+            Column::I64(vec![1, 2]),
+            Column::U32(vec![0, 0]),
+            Column::F64(vec![0.5, 1.5]),
+        ],
+        vec![Some(Bitmap::from_bools(&[true, false])), None, None],
+    )?;
+    assert!(write_chunk(&path, &batch).is_err());
+    assert!(!path.exists());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn corrupt_validity_is_detected() -> Result<()> {
+    let batch = make_batch(800)?;
+    let (dir, path) = temp_paths("corrupt");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &batch)?;
+
+    let validity = open_meta(&path)?.col(2).unwrap().validity.unwrap();
+    let mut bytes = fs::read(&path)?;
+    bytes[validity.offset as usize] ^= 0x01;
+    fs::write(&path, &bytes)?;
+
+    let mut chunk = open_chunk(&path)?;
+    assert!(matches!(
+        chunk.read_validity(2, 0, 10),
+        Err(Error::Corrupt(_))
+    ));
+    assert!(matches!(read_batch(&chunk), Err(Error::Corrupt(_))));
+    // Rows 0 and 5 were null; the flipped bit makes row 0 valid.
+    chunk.set_verify_checksums(false);
+    assert_eq!(chunk.read_validity(2, 0, 8)?.unwrap().null_count(), 1);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+/// Rows 1000..2000 have null values, as does every fifth row elsewhere.
+fn make_batch(len: usize) -> Result<RecordBatch> {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+    let mut valid = Bitmap::new();
+
+    for i in 0..len {
+        let is_valid = !(1000..2000).contains(&i) && i % 5 != 0;
+        ts.push(i as i64 * 10);
+        series_id.push(i as u32 % 3);
+        value.push(if is_valid {
+            (i % 50) as f64 + 1.0
+        } else {
+            -1.0
+        });
+        valid.push(is_valid);
+    }
+
+    RecordBatch::try_new_with_validity(
+        Arc::new(Schema::time_series()),
+        vec![Column::I64(ts), Column::U32(series_id), Column::F64(value)],
+        vec![None, None, Some(valid)],
+    )
+}
+
+fn valid_sum(batch: &RecordBatch, rows: std::ops::Range<usize>) -> f64 {
+    rows.filter(|&row| batch.is_valid(2, row))
+        .map(|row| batch.value()[row])
+        .sum()
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir =
+        std::env::temp_dir().join(format!("tsdb_storage_nulls_{}_{}", tag, std::process::id()));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
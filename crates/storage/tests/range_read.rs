@@ -14,7 +14,7 @@ fn read_range_columns() -> Result<()> {
     fs::create_dir_all(&dir)?;
 
     write_chunk(&path, &batch)?;
-    let mut chunk = open_chunk(&path)?;
+    let chunk = open_chunk(&path)?;
 
     let lo = 1000usize;
     let hi = 2000usize;
@@ -52,11 +52,7 @@ fn make_batch(len: usize) -> RecordBatch {
         value.push((i as f64).sin());
     }
 
-    RecordBatch {
-        ts,
-        series_id,
-        value,
-    }
+    RecordBatch::time_series(ts, series_id, value)
 }
 
 fn temp_paths() -> (PathBuf, PathBuf) {
@@ -15,16 +15,16 @@ fn roundtrip_chunk() -> Result<()> {
     fs::create_dir_all(&dir)?;
 
     write_chunk(&path, &batch)?;
-    let mut chunk = open_chunk(&path)?;
-    let read = read_batch(&mut chunk)?;
+    let chunk = open_chunk(&path)?;
+    let read = read_batch(&chunk)?;
 
     assert_eq!(read.len(), batch.len());
-    assert_eq!(read.ts, batch.ts);
-    assert_eq!(read.series_id, batch.series_id);
-    assert_eq!(read.value, batch.value);
+    assert_eq!(read.ts(), batch.ts());
+    assert_eq!(read.series_id(), batch.series_id());
+    assert_eq!(read.value(), batch.value());
 
-    let ts_min = *batch.ts.iter().min().unwrap();
-    let ts_max = *batch.ts.iter().max().unwrap();
+    let ts_min = *batch.ts().iter().min().unwrap();
+    let ts_max = *batch.ts().iter().max().unwrap();
     assert_eq!(chunk.meta.ts_min, ts_min);
     assert_eq!(chunk.meta.ts_max, ts_max);
 
@@ -45,11 +45,7 @@ fn make_batch(len: usize) -> RecordBatch {
         value.push(rng.next_f64());
     }
 
-    RecordBatch {
-        ts,
-        series_id,
-        value,
-    }
+    RecordBatch::time_series(ts, series_id, value)
 }
 
 fn temp_paths() -> (PathBuf, PathBuf) {
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::schema::{DataType, Field, Schema};
+use storage::reader::{open_chunk, open_meta, read_batch};
+use storage::writer::{write_chunk, ChunkWriter, WriteOptions};
+
+#[test]
+fn custom_schema_roundtrip() -> Result<()> {
+    let batch = make_batch(3000)?;
+    let (dir, path) = temp_paths("custom");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &batch)?;
+
+    let meta = open_meta(&path)?;
+    assert_eq!(&meta.schema, batch.schema().as_ref());
+    assert_eq!(meta.col_named("load").unwrap().col_id, 3);
+
+    let chunk = open_chunk(&path)?;
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.schema(), batch.schema());
+    assert_eq!(read.columns(), batch.columns());
+    assert_eq!(read.ts(), batch.ts());
+    assert!(read.series_id().is_empty());
+
+    assert_eq!(
+        chunk.read_column(1, 100, 110)?,
+        batch.column(1).slice(100..110)
+    );
+    assert_eq!(
+        chunk.read_range_f64(3, 2990, 3000)?,
+        batch.column(3).as_f64().unwrap()[2990..].to_vec()
+    );
+    assert!(chunk.read_range_u32(3, 0, 10).is_err());
+    assert_eq!(chunk.lower_bound_ts(batch.ts()[500])?, 500);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn default_schema_is_implied() -> Result<()> {
+    let (dir, path) = temp_paths("default");
+    fs::create_dir_all(&dir)?;
+    let batch = RecordBatch::time_series(vec![1, 2, 3], vec![7, 7, 8], vec![0.5, 1.5, 2.5]);
+    write_chunk(&path, &batch)?;
+
+    let meta = open_meta(&path)?;
+    assert_eq!(meta.schema, Schema::time_series());
+    let read = read_batch(&open_chunk(&path)?)?;
+    assert_eq!(read.series_id(), &[7, 7, 8]);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn writer_checks_schema() -> Result<()> {
+    let (dir, path) = temp_paths("checks");
+    fs::create_dir_all(&dir)?;
+
+    let no_ts = Schema::new(vec![Field::new("value", DataType::F64)])?;
+    assert!(ChunkWriter::create(&path, no_ts, WriteOptions::default()).is_err());
+    let f64_ts = Schema::new(vec![Field::new("ts", DataType::F64)])?;
+    assert!(ChunkWriter::create(&path, f64_ts, WriteOptions::default()).is_err());
+
+    let mut writer = ChunkWriter::create(&path, Schema::time_series(), WriteOptions::default())?;
+    assert!(writer.append(&make_batch(10)?).is_err());
+    drop(writer);
+
+    assert!(Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("ts", DataType::U32),
+    ])
+    .is_err());
+    let schema = Arc::new(Schema::time_series());
+    assert!(RecordBatch::try_new(schema.clone(), vec![Column::I64(vec![1])]).is_err());
+    assert!(RecordBatch::try_new(
+        schema,
+        vec![
+            Column::I64(vec![1, 2]),
+            Column::U32(vec![1]),
+            Column::F64(vec![1.0, 2.0]),
+        ],
+    )
+    .is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(len: usize) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("host", DataType::U32),
+        Field::new("temp", DataType::F64),
+        Field::new("load", DataType::F64),
+    ])?;
+    let mut ts = Vec::with_capacity(len);
+    let mut host = Vec::with_capacity(len);
+    let mut temp = Vec::with_capacity(len);
+    let mut load = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(5_000 + i as i64 * 10);
+        host.push((i as u32) % 5);
+        temp.push(20.0 + (i % 30) as f64 * 0.25);
+        load.push((i % 8) as f64 / 8.0);
+    }
+
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Column::I64(ts),
+            Column::U32(host),
+            Column::F64(temp),
+            Column::F64(load),
+        ],
+    )
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_storage_schema_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::error::Result;
+use datamodel::batch::{Column, RecordBatch};
+use datamodel::bitmap::Bitmap;
+use datamodel::schema::{DataType, Field, Schema};
+use storage::meta::ENCODING_RLE;
+use storage::reader::{open_chunk, open_meta, read_batch};
+use storage::writer::{write_chunk_with_options, ChunkWriter, WriteOptions};
+
+const HOSTS: [&str; 3] = ["web-1", "web-2", "db-1"];
+
+#[test]
+fn utf8_roundtrip() -> Result<()> {
+    let batch = make_batch(0..5000)?;
+    let (dir, path) = temp_paths("roundtrip");
+    fs::create_dir_all(&dir)?;
+    let options = WriteOptions {
+        block_rows: 1024,
+        ..WriteOptions::default()
+    };
+    write_chunk_with_options(&path, &batch, &options)?;
+
+    let meta = open_meta(&path)?;
+    let host = meta.col_named("host").unwrap();
+    assert_eq!(
+        host.dictionary.as_deref(),
+        Some(&HOSTS.map(String::from)[..])
+    );
+    assert_eq!(host.stats.unwrap().distinct, 3);
+    assert!(meta.col_named("value").unwrap().dictionary.is_none());
+
+    let chunk = open_chunk(&path)?;
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.columns(), batch.columns());
+    assert_eq!(
+        chunk.read_column(1, 1020, 1030)?,
+        batch.column(1).slice(1020..1030)
+    );
+    // Typed reads of a utf8 column see its dictionary codes.
+    assert_eq!(chunk.read_range_u32(1, 0, 4)?, vec![0, 1, 2, 0]);
+    assert!(chunk.read_range_i64(1, 0, 4).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn dictionary_spans_appended_batches() -> Result<()> {
+    let (dir, path) = temp_paths("appended");
+    fs::create_dir_all(&dir)?;
+    let schema = make_batch(0..0)?.schema().as_ref().clone();
+
+    let mut writer = ChunkWriter::create(&path, schema, WriteOptions::default())?;
+    let first = make_batch(0..1000)?;
+    // A later batch brings new strings and a null.
+    let second = RecordBatch::try_new_with_validity(
+        first.schema().clone(),
+        vec![
+            Column::I64(vec![20_000, 20_010, 20_020]),
+            Column::Utf8(vec!["cache-1".into(), String::new(), "web-2".into()]),
+            Column::F64(vec![1.0, 2.0, 3.0]),
+        ],
+        vec![None, Some(Bitmap::from_bools(&[true, false, true])), None],
+    )?;
+    writer.append(&first)?;
+    writer.append(&second)?;
+    writer.finish()?;
+
+    let chunk = open_chunk(&path)?;
+    let host = chunk.meta.col_named("host").unwrap();
+    assert_eq!(host.dictionary.as_ref().unwrap().len(), 5);
+    assert_eq!(host.stats.unwrap().distinct, 4);
+    assert_eq!(host.stats.unwrap().null_count, 1);
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.column(1).slice(1000..1003), second.column(1).clone());
+    assert_eq!(
+        read.validity(1).unwrap().slice(1000..1003),
+        Bitmap::from_bools(&[true, false, true])
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn sorted_tags_use_rle_codes() -> Result<()> {
+    let (dir, path) = temp_paths("sorted");
+    fs::create_dir_all(&dir)?;
+    let batch = make_batch(0..3000)?;
+    let mut host = batch.column(1).as_utf8().unwrap().to_vec();
+    host.sort();
+    let batch = RecordBatch::try_new(
+        batch.schema().clone(),
+        vec![
+            batch.column(0).clone(),
+            Column::Utf8(host),
+            batch.column(2).clone(),
+        ],
+    )?;
+    write_chunk_with_options(&path, &batch, &WriteOptions::default())?;
+
+    let chunk = open_chunk(&path)?;
+    assert_eq!(chunk.meta.col_named("host").unwrap().encoding, ENCODING_RLE);
+    assert_eq!(read_batch(&chunk)?.columns(), batch.columns());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn make_batch(rows: std::ops::Range<usize>) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("ts", DataType::I64),
+        Field::new("host", DataType::Utf8),
+        Field::new("value", DataType::F64),
+    ])?;
+    let ts = rows.clone().map(|i| i as i64 * 10).collect();
+    let host = rows.clone().map(|i| HOSTS[i % 3].to_string()).collect();
+    let value = rows.map(|i| (i % 7) as f64).collect();
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Column::I64(ts), Column::Utf8(host), Column::F64(value)],
+    )
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir =
+        std::env::temp_dir().join(format!("tsdb_storage_utf8_{}_{}", tag, std::process::id()));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
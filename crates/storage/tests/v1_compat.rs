@@ -0,0 +1,209 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use common::error::{Error, Result};
+use crc32fast::Hasher;
+use datamodel::batch::RecordBatch;
+use storage::format::{read_header, MAGIC, VERSION};
+use storage::meta::{decode_meta, encode_meta, SECTION_REQUIRED};
+use storage::reader::{open_chunk, open_meta, read_batch};
+use storage::writer::write_chunk;
+
+#[test]
+fn version1_chunk_still_opens() -> Result<()> {
+    let batch = make_batch(5000);
+    let (dir, path) = temp_paths("v1");
+    fs::create_dir_all(&dir)?;
+    write_v1_chunk(&path, &batch)?;
+
+    assert_eq!(read_header(&mut File::open(&path)?)?.version, 1);
+
+    let meta = open_meta(&path)?;
+    assert_eq!(meta.row_count, 5000);
+    assert_eq!(meta.blocks.len(), 1);
+    assert!(meta.cols.iter().all(|col| col.stats.is_none()));
+
+    let chunk = open_chunk(&path)?;
+    let read = read_batch(&chunk)?;
+    assert_eq!(read.ts(), batch.ts());
+    assert_eq!(read.series_id(), batch.series_id());
+    assert_eq!(read.value(), batch.value());
+
+    assert_eq!(
+        chunk.read_range_i64(0, 100, 200)?,
+        batch.ts()[100..200].to_vec()
+    );
+    assert_eq!(
+        chunk.read_range_u32(1, 4990, 5000)?,
+        batch.series_id()[4990..].to_vec()
+    );
+    assert_eq!(chunk.read_range_f64(2, 0, 3)?, batch.value()[..3].to_vec());
+    assert_eq!(chunk.lower_bound_ts(2500)?, 1250);
+    assert_eq!(chunk.read_ts_at(4999)?, batch.ts()[4999]);
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn writers_emit_current_version() -> Result<()> {
+    let batch = make_batch(100);
+    let (dir, path) = temp_paths("v2");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &batch)?;
+
+    let header = read_header(&mut File::open(&path)?)?;
+    assert_eq!(header.version, VERSION);
+    assert_eq!(VERSION, 2);
+
+    let mut bytes = fs::read(&path)?;
+    bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    fs::write(&path, &bytes)?;
+    assert!(matches!(open_meta(&path), Err(Error::Unsupported(_))));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn unknown_meta_sections() -> Result<()> {
+    let batch = make_batch(100);
+    let (dir, path) = temp_paths("sections");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &batch)?;
+    // Drop the block checksums and series bloom filter so the meta carries
+    // no sections of its own.
+    let mut meta = open_meta(&path)?;
+    for col in &mut meta.cols {
+        for block in &mut col.blocks {
+            block.crc32 = None;
+        }
+    }
+    meta.series_bloom = None;
+    let meta_bytes = encode_meta(&meta);
+
+    let optional = with_section(&meta_bytes, 0x0042);
+    assert_eq!(decode_meta(&optional, VERSION)?.row_count, 100);
+
+    let required = with_section(&meta_bytes, SECTION_REQUIRED | 0x0042);
+    assert!(matches!(
+        decode_meta(&required, VERSION),
+        Err(Error::Unsupported(_))
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn duplicate_meta_section_is_corrupt() -> Result<()> {
+    let (dir, path) = temp_paths("duplicate");
+    fs::create_dir_all(&dir)?;
+    write_chunk(&path, &make_batch(100))?;
+    // Keep only the series bloom filter, then repeat its section.
+    let mut meta = open_meta(&path)?;
+    for col in &mut meta.cols {
+        for block in &mut col.blocks {
+            block.crc32 = None;
+        }
+    }
+    let with_bloom = encode_meta(&meta);
+    meta.series_bloom = None;
+    let without = encode_meta(&meta);
+    let section = &with_bloom[without.len()..];
+
+    let mut twice = without[..without.len() - 2].to_vec();
+    twice.extend_from_slice(&2u16.to_le_bytes());
+    twice.extend_from_slice(section);
+    twice.extend_from_slice(section);
+    assert!(matches!(
+        decode_meta(&twice, VERSION),
+        Err(Error::Corrupt(_))
+    ));
+    assert!(decode_meta(&with_bloom, VERSION)?.series_bloom.is_some());
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+/// Appends one section to a meta that currently has none.
+fn with_section(meta_bytes: &[u8], tag: u16) -> Vec<u8> {
+    let mut out = meta_bytes[..meta_bytes.len() - 2].to_vec();
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&tag.to_le_bytes());
+    out.extend_from_slice(&3u32.to_le_bytes());
+    out.extend_from_slice(&[1, 2, 3]);
+    out
+}
+
+/// Writes `batch` in the original version 1 layout: raw columns, no block
+/// index and no statistics.
+fn write_v1_chunk(path: &Path, batch: &RecordBatch) -> Result<()> {
+    let rows = batch.len() as u64;
+    let meta_len = 4 + 8 + 8 + 4 + 3 * (2 + 2 + 8 + 8);
+    let ts_offset = 16 + meta_len as u64;
+    let series_offset = ts_offset + rows * 8;
+    let value_offset = series_offset + rows * 4;
+
+    let mut meta = Vec::new();
+    meta.extend_from_slice(&(batch.len() as u32).to_le_bytes());
+    meta.extend_from_slice(&batch.ts().iter().min().unwrap().to_le_bytes());
+    meta.extend_from_slice(&batch.ts().iter().max().unwrap().to_le_bytes());
+    meta.extend_from_slice(&3u32.to_le_bytes());
+    for (col_id, offset, width) in [
+        (0u16, ts_offset, 8),
+        (1, series_offset, 4),
+        (2, value_offset, 8),
+    ] {
+        meta.extend_from_slice(&col_id.to_le_bytes());
+        meta.extend_from_slice(&0u16.to_le_bytes());
+        meta.extend_from_slice(&offset.to_le_bytes());
+        meta.extend_from_slice(&(rows * width).to_le_bytes());
+    }
+    assert_eq!(meta.len(), meta_len);
+
+    let mut hasher = Hasher::new();
+    hasher.update(&meta);
+    let mut out = Vec::new();
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(&(meta_len as u32).to_le_bytes());
+    out.extend_from_slice(&hasher.finalize().to_le_bytes());
+    out.extend_from_slice(&meta);
+    for ts in batch.ts() {
+        out.extend_from_slice(&ts.to_le_bytes());
+    }
+    for series_id in batch.series_id() {
+        out.extend_from_slice(&series_id.to_le_bytes());
+    }
+    for value in batch.value() {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    fs::write(path, out)?;
+    Ok(())
+}
+
+fn make_batch(len: usize) -> RecordBatch {
+    let mut ts = Vec::with_capacity(len);
+    let mut series_id = Vec::with_capacity(len);
+    let mut value = Vec::with_capacity(len);
+
+    for i in 0..len {
+        ts.push(i as i64 * 2);
+        series_id.push((i as u32) % 7);
+        value.push((i as f64).sqrt());
+    }
+
+    RecordBatch::time_series(ts, series_id, value)
+}
+
+fn temp_paths(tag: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "tsdb_storage_v1_compat_{}_{}",
+        tag,
+        std::process::id()
+    ));
+    let path = dir.join("chunk.bin");
+    (dir, path)
+}
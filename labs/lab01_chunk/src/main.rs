@@ -39,15 +39,15 @@ fn main() -> Result<()> {
         let mut first_ts: Option<i64> = None;
         let mut last_ts: Option<i64> = None;
         while let Some(batch) = scan.next_batch()? {
-            if batch.series_id.len() != batch.len() || batch.value.len() != batch.len() {
+            if batch.series_id().len() != batch.len() || batch.value().len() != batch.len() {
                 return Err(Error::Corrupt("filtered batch size mismatch".into()));
             }
             num_batches += 1;
             if !batch.is_empty() {
                 if first_ts.is_none() {
-                    first_ts = batch.ts.first().copied();
+                    first_ts = batch.ts().first().copied();
                 }
-                last_ts = batch.ts.last().copied();
+                last_ts = batch.ts().last().copied();
                 total_rows += batch.len();
             }
         }
@@ -75,7 +75,7 @@ fn main() -> Result<()> {
         }
     }
 
-    let pred = Pred::GtF64(Col::Value, 0.5);
+    let pred = Pred::gt(Col::Value, 0.5);
     let scan =
         SeqScan::open(path.clone(), 0, 16_384, 1024, Cols::ts_value())?.with_predicate(Some(
             pred.clone(),
@@ -83,7 +83,7 @@ fn main() -> Result<()> {
     let scan_stats = scan.stats_handle();
     let filter = FilterOp::new(Box::new(scan), pred);
     let filter_stats = filter.stats_handle();
-    let mut project = ProjectOp::new(Box::new(filter), true, false, true);
+    let mut project = ProjectOp::new(Box::new(filter), &["ts", "value"]);
     let project_stats = project.stats_handle();
 
     println!("{}", project.explain(0));
@@ -103,6 +103,8 @@ fn main() -> Result<()> {
     println!("after_filter_rows: {}", filter_stats.output_rows);
     println!("after_project_rows: {}", project_stats.output_rows);
     println!("bytes_read: {}", scan_stats.bytes_read);
+    println!("rows_pruned: {}", scan_stats.rows_pruned);
+    println!("bytes_pruned: {}", scan_stats.bytes_pruned);
     println!("num_batches_scan: {}", scan_stats.num_batches);
     println!("num_batches_filter: {}", filter_stats.num_batches);
     println!("num_batches_project: {}", project_stats.num_batches);
@@ -124,11 +126,7 @@ fn make_batch(len: usize) -> RecordBatch {
         value.push((i as f64).sin());
     }
 
-    RecordBatch {
-        ts,
-        series_id,
-        value,
-    }
+    RecordBatch::time_series(ts, series_id, value)
 }
 
 fn chunk_path() -> PathBuf {
@@ -4,8 +4,9 @@ use std::path::PathBuf;
 use common::config::DEFAULT_CHUNK_ROWS;
 use common::error::{Error, Result};
 use datamodel::batch::RecordBatch;
-use exec::agg::AggResult;
+use exec::agg::{AggRegistry, AggResult};
 use exec::operators::agg_downsample::AggDownsampleOp;
+use exec::operators::agg_grouped::{GroupBy, GroupedDownsampleOp};
 use exec::operators::scan::{Cols, SeqScan};
 use storage::writer::write_chunk;
 
@@ -18,8 +19,9 @@ fn main() -> Result<()> {
 
     write_chunk(&path, &batch)?;
 
-    let scan = SeqScan::open(path, 0, 16_384, 1024, Cols::ts_value())?;
-    let mut agg = AggDownsampleOp::new(Box::new(scan), 100)?;
+    let scan = SeqScan::open(path.clone(), 0, 16_384, 1024, Cols::ts_value())?;
+    let aggs = AggRegistry::with_builtins().select(&["min", "max", "avg"])?;
+    let mut agg = AggDownsampleOp::new(Box::new(scan), 100)?.with_aggregates(aggs);
     let result = agg.execute_all()?;
 
     print_top_rows(&result, 5);
@@ -27,15 +29,31 @@ fn main() -> Result<()> {
     println!("total_count: {}", total_count);
     println!("num_windows: {}", result.rows.len());
 
+    let scan = SeqScan::open(path, 0, 16_384, 1024, Cols::all())?;
+    let mut grouped = GroupedDownsampleOp::new(Box::new(scan), 1000, GroupBy::Series)?;
+    let grouped = grouped.execute_all()?;
+    let grouped_count: u32 = grouped.rows.iter().map(|row| row.agg.count).sum();
+    if grouped_count != total_count {
+        return Err(Error::Corrupt(format!(
+            "grouped total_count mismatch: {}",
+            grouped_count
+        )));
+    }
+    println!("num_series_windows: {}", grouped.rows.len());
+
     Ok(())
 }
 
 fn print_top_rows(result: &AggResult, n: usize) {
     for row in result.rows.iter().take(n) {
-        let avg = row.sum / row.count as f64;
+        let (min, max, avg) = (row.values[0], row.values[1], row.values[2]);
         println!(
             "window_start={} count={} min={:.6} max={:.6} avg={:.6}",
-            row.window_start, row.count, row.min, row.max, avg
+            row.window_start,
+            row.count,
+            min.unwrap_or(f64::NAN),
+            max.unwrap_or(f64::NAN),
+            avg.unwrap_or(f64::NAN)
         );
     }
 }
@@ -73,11 +91,7 @@ fn make_batch(len: usize) -> RecordBatch {
         value.push((i as f64).sin());
     }
 
-    RecordBatch {
-        ts,
-        series_id,
-        value,
-    }
+    RecordBatch::time_series(ts, series_id, value)
 }
 
 fn chunk_path() -> PathBuf {